@@ -2,7 +2,6 @@ use std::path::Path;
 
 use ndarray::*;
 use ndarray_ode::prelude::*;
-use rayon::prelude::*;
 
 const DOF: usize = 4;
 #[allow(non_upper_case_globals)]
@@ -42,72 +41,59 @@ fn run(
     T: f64,
     folder: &std::path::PathBuf,
 ) {
-    ode.par_iter().for_each(|e| match e {
-        OdeType::SymplecticEuler => {
-            let euler = SymplecticEuler::new(h, keppler);
-            let mut ode = Ode::implicit(euler, x0.clone());
-            ode.set_step_size(h).set_t(T).set_with_progress(true);
-
-            let (time, result) = ode.run();
-            let file = std::fs::File::create(folder.join("keppler_symplectic.parquet")).unwrap();
-            store(time, result, file);
-        }
-        OdeType::ImplicitEuler => {
-            let euler = ImplicitEuler::new(h, keppler);
-            let mut ode = Ode::implicit(euler, x0.clone());
-            ode.set_step_size(h).set_t(T).set_with_progress(true);
-
-            let (time, result) = ode.run();
-            let file = std::fs::File::create(folder.join("keppler_implicit.parquet")).unwrap();
-            store(time, result, file);
-        }
-        OdeType::Expliciteuler => {
-            let euler = ExplicitEuler::new(h, keppler_f64);
-            let mut ode = Ode::explicit(euler, x0.clone());
-            ode.set_step_size(h).set_t(T).set_with_progress(true);
-
-            let (time, result) = ode.run();
-            let file = std::fs::File::create(folder.join("keppler_explicit.parquet")).unwrap();
-            store(time, result, file);
-        }
+    let hamiltonian = Hamiltonian::new(DOF / 2, keppler);
+    let ensemble = Ensemble::new(
+        ode.len(),
+        |i| (x0.clone(), ode[i]),
+        |x0, ode_type| match ode_type {
+            OdeType::SymplecticEuler => {
+                let euler = SymplecticEuler::new(h, hamiltonian.flow());
+                let mut ode = Ode::implicit(euler, x0);
+                ode.set_step_size(h).set_t(T).set_with_progress(false);
+                ode.run()
+            }
+            OdeType::ImplicitEuler => {
+                let euler = ImplicitEuler::new(h, hamiltonian.flow());
+                let mut ode = Ode::implicit(euler, x0);
+                ode.set_step_size(h).set_t(T).set_with_progress(false);
+                ode.run()
+            }
+            OdeType::Expliciteuler => {
+                let euler = ExplicitEuler::new(h, hamiltonian.flow_f64());
+                let mut ode = Ode::explicit(euler, x0);
+                ode.set_step_size(h).set_t(T).set_with_progress(false);
+                ode.run()
+            }
+        },
+    );
+    let drifts = ensemble.reduce(|_, ode_type, solution| {
+        let name = match ode_type {
+            OdeType::SymplecticEuler => "symplectic",
+            OdeType::ImplicitEuler => "implicit",
+            OdeType::Expliciteuler => "explicit",
+        };
+        let file = std::fs::File::create(folder.join(format!("keppler_{name}.parquet"))).unwrap();
+        store(solution.clone(), file);
+        (name, hamiltonian.energy_drift(solution))
     });
+    for (name, drift) in drifts {
+        println!("{name} euler: maximal energy drift {drift:.3e}");
+    }
 }
 
+#[derive(Clone, Copy)]
 enum OdeType {
     SymplecticEuler,
     ImplicitEuler,
     Expliciteuler,
 }
 
-fn store(
-    time: Vec<f64>,
-    result: ArrayBase<OwnedRepr<f64>, Dim<[usize; 2]>>,
-    mut file: std::fs::File,
-) {
-    let mut df = plot::Dataframe::new();
-    df.push(plot::Series::new("t", &time));
-    df.push(plot::Series::new("x", &result.column(0).to_vec()));
-    df.push(plot::Series::new("y", &result.column(1).to_vec()));
-    df.push(plot::Series::new("px", &result.column(2).to_vec()));
-    df.push(plot::Series::new("py", &result.column(3).to_vec()));
-    df.store(&mut file).unwrap();
-}
-
-#[inline]
-fn keppler(x: ArrayView1<AD>, update: &mut Array1<AD>) {
-    let (x, y, px, py) = (x[0], x[1], x[2], x[3]);
-    let factor = -μ * (x * x + y * y).powi(3).sqrt();
-
-    update[0] = px;
-    update[1] = py;
-    update[2] = factor * x;
-    update[3] = factor * y;
-    // array![px, py, factor * x, factor * y]
+fn store(mut solution: Solution, mut file: std::fs::File) {
+    solution.set_labels(&["x", "y", "px", "py"]);
+    solution.to_dataframe().store(&mut file).unwrap();
 }
 
-#[inline]
-fn keppler_f64(x: ArrayView1<f64>) -> Array1<f64> {
-    let (x, y, px, py) = (x[0], x[1], x[2], x[3]);
-    let factor = -μ * (x * x + y * y).powi(3).sqrt();
-    array![px, py, factor * x, factor * y]
+/// The energy of the Keppler problem, the canonical equations are derived from it.
+fn keppler(q: ArrayView1<AD>, p: ArrayView1<AD>) -> AD {
+    0.5 * (p[0] * p[0] + p[1] * p[1]) - μ / (q[0] * q[0] + q[1] * q[1]).sqrt()
 }
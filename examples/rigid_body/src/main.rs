@@ -0,0 +1,103 @@
+use ndarray::*;
+use ndarray_ode::prelude::*;
+
+/// Principal moments of inertia of the satellite, it spins about the unstable middle axis.
+const INERTIA: [f64; 3] = [2., 1., 2. / 3.];
+
+fn main() {
+    let h = 0.05;
+    #[allow(non_snake_case)]
+    let T = 1000.0;
+    // almost about the middle axis, so the satellite flips over periodically
+    let m0 = array![0.01, 1.0, 0.01];
+
+    // quaternion and Euler equations in one flat state, the norm of the quaternion drifts
+    let mut x0 = Quaternion::identity().to_vec();
+    x0.extend(m0.iter());
+    let mut ode = Ode::explicit(
+        RungeKutta::new(h, ButcherTableau::rk4(), spinning_satellite),
+        Array1::from(x0),
+    );
+    ode.set_step_size(h).set_t(T).set_with_progress(false);
+    let solution = ode.run();
+    report("runge kutta", &solution, |x| {
+        Quaternion::matrix(x.slice(s![..4]))
+    });
+
+    let splitting = RigidBodySplitting::<Quaternion>::new(h, INERTIA);
+    let mut ode = Ode::explicit(
+        splitting,
+        RigidBodySplitting::<Quaternion>::initial(m0.view()),
+    );
+    ode.set_step_size(h).set_t(T).set_with_progress(false);
+    let solution = ode.run();
+    report("splitting with quaternions", &solution, |x| {
+        Quaternion::matrix(x.slice(s![..4]))
+    });
+
+    let splitting = RigidBodySplitting::<RotationMatrix>::new(h, INERTIA);
+    let mut ode = Ode::explicit(
+        splitting,
+        RigidBodySplitting::<RotationMatrix>::initial(m0.view()),
+    );
+    ode.set_step_size(h).set_t(T).set_with_progress(false);
+    let solution = ode.run();
+    report("splitting with rotation matrices", &solution, |x| {
+        RotationMatrix::matrix(x.slice(s![..9]))
+    });
+
+    // Y = R^T on SO(3), the spatial angular momentum m0 is conserved, so the body momentum is Y m0
+    let generator = move |_: f64, y: ArrayView2<f64>| {
+        let m = y.dot(&m0);
+        -hat(angular_velocity(m.view()).view())
+    };
+    let rkmk = RungeKuttaMuntheKaas::new(ButcherTableau::rk4(), generator);
+    let mut ode = Ode::matrix(rkmk, Array2::eye(3));
+    ode.set_step_size(h).set_t(T).set_with_progress(false);
+    let solution = ode.run();
+    let orthogonality = solution
+        .iter()
+        .map(|(_, y)| {
+            let y = y.into_shape((3, 3)).unwrap();
+            (y.t().dot(&y) - Array2::<f64>::eye(3))
+                .iter()
+                .fold(0f64, |e, x| e.max(x.abs()))
+        })
+        .fold(0., f64::max);
+    println!("munthe-kaas: maximal deviation from orthogonality {orthogonality:.3e}");
+}
+
+fn angular_velocity(m: ArrayView1<f64>) -> Array1<f64> {
+    array![m[0] / INERTIA[0], m[1] / INERTIA[1], m[2] / INERTIA[2]]
+}
+
+/// `q' = q (0, ω) / 2` and the Euler equations `m' = m × ω` in the body frame.
+fn spinning_satellite(x: ArrayView1<f64>) -> Array1<f64> {
+    let (q, m) = (x.slice(s![..4]), x.slice(s![4..]));
+    let ω = angular_velocity(m);
+    let dq = Quaternion::product(q, array![0., ω[0], ω[1], ω[2]].view()) / 2.;
+    let dm = rigid_body(INERTIA)(m);
+    concatenate![Axis(0), dq, dm]
+}
+
+fn energy(m: ArrayView1<f64>) -> f64 {
+    m.dot(&angular_velocity(m)) / 2.
+}
+
+/// Prints how far the attitude leaves SO(3) and how far the energy drifts.
+fn report(name: &str, solution: &Solution, attitude: impl Fn(ArrayView1<f64>) -> Array2<f64>) {
+    let l = solution.states.ncols();
+    let e0 = energy(solution.states.slice(s![0, l - 3..]));
+    let (mut orthogonality, mut drift) = (0f64, 0f64);
+    for (_, x) in solution.iter() {
+        let r = attitude(x);
+        let deviation = (r.t().dot(&r) - Array2::<f64>::eye(3))
+            .iter()
+            .fold(0f64, |e, x| e.max(x.abs()));
+        orthogonality = orthogonality.max(deviation);
+        drift = drift.max((energy(x.slice(s![l - 3..])) - e0).abs());
+    }
+    println!(
+        "{name}: maximal deviation from orthogonality {orthogonality:.3e}, maximal energy drift {drift:.3e}"
+    );
+}
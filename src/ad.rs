@@ -5,6 +5,12 @@ use std::ops::{Add, Div, Index, IndexMut, Mul, Neg, Sub};
 
 mod jacobian;
 pub use jacobian::{jacobian, jacobian_par, jacobian_res};
+mod gradient;
+pub use gradient::gradient;
+mod flow;
+pub use flow::{eval_flow, flow_jacobian};
+mod hessian;
+pub use hessian::hessian;
 mod ops;
 pub use ops::*;
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -691,7 +697,7 @@ impl TrigOps for AD {
                 .zip(self.iter().skip(1).take(i).rev())
                 .enumerate()
                 .fold(0f64, |x, (k, (&u1, &x1))| {
-                    x + (C(i - 1, k) as f64) * x1 * u1
+                    x - (C(i - 1, k) as f64) * x1 * u1
                 });
         }
         (u, v)
@@ -986,3 +992,26 @@ impl<'a, F: Fn(&Vec<AD>) -> Vec<AD>> StableFn<&'a Vec<AD>> for ADFn<F> {
         (self.f)(target)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn derivatives_of_sin_and_cos() {
+        let x = 0.7f64;
+        let (s, c) = AD1(x, 1.).sin_cos();
+        assert_eq!((s.x(), s.dx()), (x.sin(), x.cos()));
+        assert_eq!((c.x(), c.dx()), (x.cos(), -x.sin()));
+
+        // u = x(t) with x' = 2 and x'' = 0.5, so sin(u)'' = cos u x'' - sin u x'²
+        let (s, c) = AD2(x, 2., 0.5).sin_cos();
+        let expected_sin = [x.sin(), 2. * x.cos(), 0.5 * x.cos() - 4. * x.sin()];
+        let expected_cos = [x.cos(), -2. * x.sin(), -0.5 * x.sin() - 4. * x.cos()];
+        for (ad, expected) in [(s, expected_sin), (c, expected_cos)] {
+            for (d, e) in ad.iter().zip(expected) {
+                assert!((d - e).abs() < 1e-15, "{ad:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use ndarray::{Array1, Array2, ArrayView1};
+
+use crate::ad::*;
+
+/// Flow evaluation
+///
+/// # Description
+/// Evaluates a flow `f(x, update)` written for [AD] at a state of `f64`, as the explicit stages of the solvers do.
+///
+/// # Examples
+/// ```
+/// use ndarray::{array, Array1, ArrayView1};
+/// use ndarray_ode::prelude::*;
+/// fn main() {
+///     let f = eval_flow(oscillator, array![1., 2.].view());
+///     assert_eq!(f, array![2., -1.]);
+/// }
+/// fn oscillator(x: ArrayView1<AD>, update: &mut Array1<AD>) {
+///     update[0] = x[1];
+///     update[1] = -x[0];
+/// }
+/// ```
+pub fn eval_flow<F: Fn(ArrayView1<AD>, &mut Array1<AD>)>(f: F, x: ArrayView1<f64>) -> Array1<f64> {
+    let x = x.to_ad();
+    let mut update = x.clone();
+    f(x.view(), &mut update);
+    update.to_f64()
+}
+
+/// The [jacobian] of a flow `f(x, update)` at a state of `f64`.
+pub fn flow_jacobian<F: Fn(ArrayView1<AD>, &mut Array1<AD>)>(
+    f: F,
+    x: ArrayView1<f64>,
+) -> Array2<f64> {
+    jacobian(
+        |x| {
+            let mut update = x.to_owned();
+            f(x, &mut update);
+            update
+        },
+        x,
+    )
+}
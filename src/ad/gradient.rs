@@ -0,0 +1,71 @@
+use ndarray::{Array1, ArrayView1};
+
+use crate::ad::*;
+
+/// Gradient of a scalar function
+///
+/// # Description
+/// Exact gradient `∇f(x)` using Automatic Differentiation, one first order pass per entry.
+///
+/// If the entries of `x` carry a first derivative `dx`, e.g. inside [jacobian_res], the entries of the gradient
+/// carry the derivative `∇²f(x) dx` along it. This needs second order passes along `e_i`, `dx` and `e_i + dx`,
+/// whose mixed terms give `e_i^T ∇²f(x) dx` by polarization. So residuals built from gradients can be solved
+/// by [crate::ode::newton].
+///
+/// # Examples
+/// ```
+/// use ndarray::{array, ArrayView1};
+/// use ndarray_ode::prelude::*;
+/// fn main() {
+///     let x = array![1., 2.].to_ad();
+///     let g = gradient(f, x.view());
+///     assert_eq!(g.to_f64(), array![4., 2.]);
+/// }
+/// fn f(xs: ArrayView1<AD>) -> AD {
+///     xs[0] * xs[0] * xs[1] + xs[1]
+/// }
+/// ```
+pub fn gradient<F: Fn(ArrayView1<AD>) -> AD>(f: F, x: ArrayView1<AD>) -> Array1<AD> {
+    let n = x.len();
+    let x0: Array1<f64> = x.iter().map(|x| x.x()).collect();
+    let dx: Array1<f64> = x.iter().map(|x| x.dx()).collect();
+    // f(x0 + s d) to first or second order in s
+    let along = |d: &Array1<f64>, second: bool| {
+        let y: Array1<AD> = x0
+            .iter()
+            .zip(d.iter())
+            .map(|(&x, &d)| {
+                if second {
+                    AD::AD2(x, d, 0.)
+                } else {
+                    AD::AD1(x, d)
+                }
+            })
+            .collect();
+        f(y.view())
+    };
+
+    if x.iter().all(|x| x.order() == 0) {
+        let mut e = Array1::zeros(n);
+        return (0..n)
+            .map(|i| {
+                e[i] = 1.;
+                let g = AD::AD0(along(&e, false).dx());
+                e[i] = 0.;
+                g
+            })
+            .collect();
+    }
+    let curvature = along(&dx, true).ddx();
+    let mut e = Array1::zeros(n);
+    (0..n)
+        .map(|i| {
+            e[i] = 1.;
+            let diagonal = along(&e, true);
+            let mixed = along(&(&e + &dx), true).ddx();
+            e[i] = 0.;
+            let dg = (mixed - diagonal.ddx() - curvature) / 2.;
+            AD::AD1(diagonal.dx(), dg)
+        })
+        .collect()
+}
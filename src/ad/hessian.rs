@@ -0,0 +1,60 @@
+use ndarray::{Array1, Array2, ArrayView1};
+
+use crate::ad::*;
+
+/// Hessian of a scalar function
+///
+/// # Description
+/// Exact Hessian `∇²f(x)` using second order Automatic Differentiation.
+///
+/// A pass with `AD2` along a direction `d` gives `d^T ∇²f(x) d`, so the diagonal needs the passes along
+/// `e_i` and each entry above it one more along `e_i + e_j`, whose mixed term is the entry by polarization.
+///
+/// # Examples
+/// ```
+/// use ndarray::{array, ArrayView1};
+/// use ndarray_ode::prelude::*;
+/// fn main() {
+///     let x = array![1., 2.];
+///     let h = hessian(f, x.view());
+///     assert_eq!(h, array![[4., 2.], [2., 0.]]);
+/// }
+/// fn f(xs: ArrayView1<AD>) -> AD {
+///     xs[0] * xs[0] * xs[1] + xs[1]
+/// }
+/// ```
+pub fn hessian<F: Fn(ArrayView1<AD>) -> AD>(f: F, x: ArrayView1<f64>) -> Array2<f64> {
+    let n = x.len();
+    // d^T ∇²f(x) d
+    let curvature = |d: &Array1<f64>| {
+        let y: Array1<AD> = x
+            .iter()
+            .zip(d.iter())
+            .map(|(&x, &d)| AD::AD2(x, d, 0.))
+            .collect();
+        f(y.view()).ddx()
+    };
+
+    let mut e = Array1::zeros(n);
+    let diagonal: Vec<f64> = (0..n)
+        .map(|i| {
+            e[i] = 1.;
+            let c = curvature(&e);
+            e[i] = 0.;
+            c
+        })
+        .collect();
+    let mut h = Array2::from_diag(&Array1::from(diagonal));
+    for i in 0..n {
+        for j in i + 1..n {
+            e[i] = 1.;
+            e[j] = 1.;
+            let mixed = (curvature(&e) - h[[i, i]] - h[[j, j]]) / 2.;
+            e[i] = 0.;
+            e[j] = 0.;
+            h[[i, j]] = mixed;
+            h[[j, i]] = mixed;
+        }
+    }
+    h
+}
@@ -0,0 +1,110 @@
+//! Solvers for two point boundary value problems `x'(t) = f(t, x)` with `g(x(a), x(b)) = 0`.
+//!
+//! The flow and the boundary conditions work on [AD] numbers, so that the Jacobian of the
+//! shooting equations follows from forward sensitivities through the integrator.
+use ndarray::{Array1, ArrayView1};
+use ndarray_linalg::error::LinalgError;
+
+use crate::ad::AD;
+
+mod banded;
+mod collocation;
+pub use collocation::*;
+mod shooting;
+pub use shooting::*;
+
+/// Reasons why the outer Newton iteration of a boundary value solver fails.
+#[derive(Debug)]
+pub enum BvpError {
+    /// The Jacobian of the boundary value equations could not be inverted.
+    Singular(LinalgError),
+    /// The trajectory left the range of [f64] at time `t`, the initial guess is too far off
+    /// or the segments are too long for an unstable problem.
+    NonFinite { t: f64 },
+    /// The residual norm is still `residual` after `iterations` Newton iterations.
+    NotConverged { residual: f64, iterations: usize },
+    /// The linearized collocation equations have a vanishing pivot near time `t`.
+    SingularPivot { t: f64 },
+    /// Mesh refinement exceeded the allowed number of nodes with a largest interval residual `residual`.
+    MeshLimit { nodes: usize, residual: f64 },
+}
+
+impl std::fmt::Display for BvpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BvpError::Singular(e) => {
+                write!(f, "Singular Jacobian of the boundary value problem: {e}")
+            }
+            BvpError::NonFinite { t } => write!(f, "The trajectory diverged at t = {t}"),
+            BvpError::NotConverged {
+                residual,
+                iterations,
+            } => write!(
+                f,
+                "No convergence after {iterations} Newton iterations, the residual is {residual:e}"
+            ),
+            BvpError::SingularPivot { t } => {
+                write!(f, "Singular collocation equations near t = {t}")
+            }
+            BvpError::MeshLimit { nodes, residual } => write!(
+                f,
+                "The mesh grew to {nodes} nodes with a remaining residual of {residual:e}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BvpError {}
+
+impl From<LinalgError> for BvpError {
+    fn from(e: LinalgError) -> Self {
+        BvpError::Singular(e)
+    }
+}
+
+pub struct Bvp;
+impl Bvp {
+    /// Single shooting on `[a, b]` from the guess `x0` of the initial state.
+    pub fn shooting<Flow, Boundary>(
+        flow: Flow,
+        boundary: Boundary,
+        (a, b): (f64, f64),
+        x0: Array1<f64>,
+    ) -> Shooting<Flow, Boundary>
+    where
+        Flow: Fn(f64, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+        Boundary: Fn(ArrayView1<AD>, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+    {
+        Shooting::new(flow, boundary, vec![a, b], |_| x0.clone())
+    }
+
+    /// Multiple shooting on the segments between `nodes`, with initial states guessed by `guess(t)`.
+    pub fn multiple_shooting<Flow, Boundary, Guess>(
+        flow: Flow,
+        boundary: Boundary,
+        nodes: Vec<f64>,
+        guess: Guess,
+    ) -> Shooting<Flow, Boundary>
+    where
+        Flow: Fn(f64, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+        Boundary: Fn(ArrayView1<AD>, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+        Guess: Fn(f64) -> Array1<f64>,
+    {
+        Shooting::new(flow, boundary, nodes, guess)
+    }
+
+    /// Collocation on the initial `mesh`, which is refined as needed, with states guessed by `guess(t)`.
+    pub fn collocation<Flow, Boundary, Guess>(
+        flow: Flow,
+        boundary: Boundary,
+        mesh: Vec<f64>,
+        guess: Guess,
+    ) -> Collocation<Flow, Boundary>
+    where
+        Flow: Fn(f64, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+        Boundary: Fn(ArrayView1<AD>, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+        Guess: Fn(f64) -> Array1<f64>,
+    {
+        Collocation::new(flow, boundary, mesh, guess)
+    }
+}
@@ -0,0 +1,83 @@
+use ndarray::{Array1, Array2};
+
+/// Square band matrix with `kl` sub- and `ku` superdiagonals.
+///
+/// Row `r` stores the columns `r - kl ..= r + kl + ku`, the additional `kl` diagonals
+/// take up the fill in of the row interchanges during the elimination.
+pub struct BandMatrix {
+    kl: usize,
+    ku: usize,
+    data: Array2<f64>,
+}
+
+impl BandMatrix {
+    pub fn zeros(n: usize, kl: usize, ku: usize) -> Self {
+        BandMatrix {
+            kl,
+            ku,
+            data: Array2::zeros((n, 2 * kl + ku + 1)),
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.data.nrows()
+    }
+
+    fn offset(&self, r: usize, c: usize) -> usize {
+        debug_assert!(c + self.kl >= r && c <= r + self.kl + self.ku);
+        c + self.kl - r
+    }
+
+    pub fn get(&self, r: usize, c: usize) -> f64 {
+        self.data[[r, self.offset(r, c)]]
+    }
+
+    pub fn set(&mut self, r: usize, c: usize, value: f64) {
+        let o = self.offset(r, c);
+        self.data[[r, o]] = value;
+    }
+
+    /// Solves `A x = b` by Gaussian elimination with partial pivoting, which overwrites the matrix.
+    ///
+    /// A vanishing pivot returns the index of its column as error.
+    pub fn solve(mut self, mut b: Array1<f64>) -> Result<Array1<f64>, usize> {
+        let n = self.dim();
+        let width = self.kl + self.ku;
+        for k in 0..n {
+            let last = (k + self.kl).min(n - 1);
+            let p = (k..=last)
+                .max_by(|&i, &j| self.get(i, k).abs().total_cmp(&self.get(j, k).abs()))
+                .unwrap();
+            let pivot = self.get(p, k);
+            if pivot == 0. || !pivot.is_finite() {
+                return Err(k);
+            }
+            let end = (k + width).min(n - 1);
+            if p != k {
+                for c in k..=end {
+                    let (a, b) = (self.get(k, c), self.get(p, c));
+                    self.set(k, c, b);
+                    self.set(p, c, a);
+                }
+                b.swap(k, p);
+            }
+            for i in k + 1..=last {
+                let l = self.get(i, k) / pivot;
+                if l == 0. {
+                    continue;
+                }
+                for c in k..=end {
+                    let value = self.get(i, c) - l * self.get(k, c);
+                    self.set(i, c, value);
+                }
+                b[i] -= l * b[k];
+            }
+        }
+        for k in (0..n).rev() {
+            let end = (k + width).min(n - 1);
+            let sum: f64 = (k + 1..=end).map(|c| self.get(k, c) * b[c]).sum();
+            b[k] = (b[k] - sum) / self.get(k, k);
+        }
+        Ok(b)
+    }
+}
@@ -0,0 +1,432 @@
+use ndarray::{concatenate, s, Array1, Array2, ArrayView1, Axis};
+use ndarray_linalg::Norm;
+
+use crate::{
+    ad::*,
+    bvp::{banded::BandMatrix, BvpError},
+    ode::{Residual, Solution, SolverStats},
+};
+
+/// Collocation with the three stage Lobatto IIIA method, i.e. piecewise cubic polynomials which satisfy
+/// the differential equation at the mesh nodes and midpoints, as in scipy's `solve_bvp`.
+///
+/// The states at all mesh nodes are found by a damped Newton method. The Jacobian blocks of every interval
+/// come from [AD], the global linear system is banded, as the boundary conditions `g(x(a), x(b))`
+/// are separated by carrying a copy of `x(a)` along the mesh. Afterwards intervals with a large relative
+/// residual of the collocation polynomial are subdivided until the residual is below the tolerance.
+pub struct Collocation<Flow, Boundary>
+where
+    Flow: Fn(f64, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+    Boundary: Fn(ArrayView1<AD>, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+{
+    flow: Flow,
+    boundary: Boundary,
+    mesh: Vec<f64>,
+    guess: Array2<f64>,
+    tol: f64,
+    ɛ: f64,
+    max_iter: usize,
+    max_nodes: usize,
+}
+
+impl<Flow, Boundary> Collocation<Flow, Boundary>
+where
+    Flow: Fn(f64, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+    Boundary: Fn(ArrayView1<AD>, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+{
+    pub fn new(
+        flow: Flow,
+        boundary: Boundary,
+        mesh: Vec<f64>,
+        guess: impl Fn(f64) -> Array1<f64>,
+    ) -> Self {
+        assert!(mesh.len() >= 2, "Collocation needs at least one interval");
+        assert!(
+            mesh.windows(2).all(|w| w[0] < w[1]),
+            "Mesh nodes have to be increasing"
+        );
+        let n = guess(mesh[0]).len();
+        let mut states = Array2::zeros((mesh.len(), n));
+        for (mut row, &t) in states.rows_mut().into_iter().zip(mesh.iter()) {
+            row.assign(&guess(t));
+        }
+        Collocation {
+            flow,
+            boundary,
+            mesh,
+            guess: states,
+            tol: 1e-3,
+            ɛ: 1e-10,
+            max_iter: 20,
+            max_nodes: 10000,
+        }
+    }
+
+    /// Tolerance of the relative residual `(S' - f(t, S)) / (1 + |f|)` of the collocation polynomial `S`.
+    pub fn set_tolerance(&mut self, tol: f64) -> &mut Self {
+        self.tol = tol;
+        self
+    }
+
+    /// Tolerance for the norm of the collocation equations in the Newton method.
+    pub fn set_epsilon(&mut self, ɛ: f64) -> &mut Self {
+        self.ɛ = ɛ;
+        self
+    }
+
+    pub fn set_max_iter(&mut self, max_iter: usize) -> &mut Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn set_max_nodes(&mut self, max_nodes: usize) -> &mut Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    /// Solves the problem, the time steps of the [Solution] are the nodes of the final mesh.
+    pub fn solve(&self) -> Result<Solution, BvpError> {
+        let mut mesh = self.mesh.clone();
+        let mut states = self.guess.clone();
+        let mut stats = SolverStats::default();
+        loop {
+            states = self.newton(&mesh, states, &mut stats)?;
+            let residuals = self.interval_residuals(&mesh, &states);
+            stats.rhs_evaluations += 3 * mesh.len();
+            if residuals.iter().all(|&r| r <= self.tol) {
+                stats.steps = mesh.len() - 1;
+                return Ok(Solution::new(mesh, states, stats));
+            }
+            (mesh, states) = self.refine(&mesh, &states, &residuals);
+            if mesh.len() > self.max_nodes {
+                return Err(BvpError::MeshLimit {
+                    nodes: mesh.len(),
+                    residual: residuals.iter().fold(0., |a: f64, &b| a.max(b)),
+                });
+            }
+        }
+    }
+
+    fn flow_f64(&self, t: f64, x: ArrayView1<f64>) -> Array1<f64> {
+        (self.flow)(t, x.to_ad().view()).to_f64()
+    }
+
+    /// Collocation equations of all intervals followed by the boundary conditions.
+    fn residual(&self, mesh: &[f64], states: &Array2<f64>) -> Array1<f64> {
+        let n = states.ncols();
+        let m = mesh.len() - 1;
+        let mut residual = Array1::zeros(n * (m + 1));
+        for i in 0..m {
+            let interval = IntervalResidual {
+                flow: &self.flow,
+                t: mesh[i],
+                h: mesh[i + 1] - mesh[i],
+            };
+            let x = concatenate![Axis(0), states.row(i), states.row(i + 1)].to_ad();
+            let mut update = Array1::from_elem(n, AD::AD0(0.));
+            interval.eval(x.view(), &mut update);
+            residual
+                .slice_mut(s![i * n..(i + 1) * n])
+                .assign(&update.to_f64());
+        }
+        let g = (self.boundary)(states.row(0).to_ad().view(), states.row(m).to_ad().view());
+        assert_eq!(g.len(), n, "Expected {n} boundary conditions");
+        residual.slice_mut(s![m * n..]).assign(&g.to_f64());
+        residual
+    }
+
+    /// Newton direction from the banded linearization.
+    ///
+    /// The unknowns of node `i` are the correction `δx_i` and the copy `δz_i` of `δx_0`, so that the
+    /// rows `δz_0 - δx_0`, the collocation rows of each interval, `δz_i+1 - δz_i` and finally the
+    /// boundary conditions in `(δz_m, δx_m)` form a band.
+    #[allow(non_snake_case)]
+    fn direction(
+        &self,
+        mesh: &[f64],
+        states: &Array2<f64>,
+        residual: &Array1<f64>,
+    ) -> Result<Array2<f64>, BvpError> {
+        let n = states.ncols();
+        let m = mesh.len() - 1;
+        let mut A = BandMatrix::zeros(2 * n * (m + 1), 2 * n - 1, 3 * n - 1);
+        let mut b = Array1::zeros(2 * n * (m + 1));
+        for j in 0..n {
+            A.set(j, j, -1.);
+            A.set(j, n + j, 1.);
+        }
+
+        let mut J = Array2::zeros((n, 2 * n));
+        let mut slopes = Array1::from_elem(n, AD::AD0(0.));
+        for i in 0..m {
+            let interval = IntervalResidual {
+                flow: &self.flow,
+                t: mesh[i],
+                h: mesh[i + 1] - mesh[i],
+            };
+            let x = concatenate![Axis(0), states.row(i), states.row(i + 1)];
+            jacobian_res(&interval, x.view(), &mut J, &mut slopes);
+            let row = n + 2 * n * i;
+            for j in 0..n {
+                for k in 0..n {
+                    A.set(row + j, 2 * n * i + k, J[[j, k]]);
+                    A.set(row + j, 2 * n * (i + 1) + k, J[[j, n + k]]);
+                }
+                b[row + j] = -residual[i * n + j];
+                A.set(row + n + j, 2 * n * i + n + j, -1.);
+                A.set(row + n + j, 2 * n * (i + 1) + n + j, 1.);
+            }
+        }
+
+        let boundary = BoundaryResidual {
+            boundary: &self.boundary,
+        };
+        let x = concatenate![Axis(0), states.row(0), states.row(m)];
+        jacobian_res(&boundary, x.view(), &mut J, &mut slopes);
+        let row = n + 2 * n * m;
+        for j in 0..n {
+            for k in 0..n {
+                A.set(row + j, 2 * n * m + n + k, J[[j, k]]);
+                A.set(row + j, 2 * n * m + k, J[[j, n + k]]);
+            }
+            b[row + j] = -residual[m * n + j];
+        }
+
+        let x = A.solve(b).map_err(|column| BvpError::SingularPivot {
+            t: mesh[(column / (2 * n)).min(m)],
+        })?;
+        let mut direction = Array2::zeros((m + 1, n));
+        for (i, mut row) in direction.rows_mut().into_iter().enumerate() {
+            row.assign(&x.slice(s![2 * n * i..2 * n * i + n]));
+        }
+        Ok(direction)
+    }
+
+    /// Damped Newton method, the step is halved until the residual norm decreases.
+    fn newton(
+        &self,
+        mesh: &[f64],
+        mut states: Array2<f64>,
+        stats: &mut SolverStats,
+    ) -> Result<Array2<f64>, BvpError> {
+        let evaluations = 3 * (mesh.len() - 1);
+        let mut residual = self.residual(mesh, &states);
+        stats.rhs_evaluations += evaluations;
+        let mut err = residual.norm();
+        for _ in 0..self.max_iter {
+            if !err.is_finite() {
+                let t = mesh
+                    .iter()
+                    .zip(states.rows())
+                    .find(|(_, x)| !x.iter().all(|x| x.is_finite()))
+                    .map_or(mesh[0], |(&t, _)| t);
+                return Err(BvpError::NonFinite { t });
+            }
+            if err < self.ɛ {
+                return Ok(states);
+            }
+            let direction = self.direction(mesh, &states, &residual)?;
+            stats.jacobian_evaluations += 1;
+            stats.lu_factorizations += 1;
+            stats.newton_iterations += 1;
+            let mut α = 1.;
+            loop {
+                let candidate = &states + &(α * &direction);
+                let candidate_residual = self.residual(mesh, &candidate);
+                stats.rhs_evaluations += evaluations;
+                let candidate_err = candidate_residual.norm();
+                if candidate_err < err || α < 1e-3 {
+                    (states, residual, err) = (candidate, candidate_residual, candidate_err);
+                    break;
+                }
+                α *= 0.5;
+            }
+        }
+        if err < self.ɛ {
+            Ok(states)
+        } else {
+            stats.newton_failures += 1;
+            Err(BvpError::NotConverged {
+                residual: err,
+                iterations: self.max_iter,
+            })
+        }
+    }
+
+    /// Root mean square of the relative residual of the collocation polynomial in each interval,
+    /// from the five point Lobatto quadrature. The residual vanishes at the nodes and the midpoint,
+    /// which leaves the two inner quadrature points.
+    fn interval_residuals(&self, mesh: &[f64], states: &Array2<f64>) -> Vec<f64> {
+        let slopes: Vec<Array1<f64>> = mesh
+            .iter()
+            .zip(states.rows())
+            .map(|(&t, x)| self.flow_f64(t, x))
+            .collect();
+        (0..mesh.len() - 1)
+            .map(|i| {
+                let polynomial = Hermite {
+                    t: mesh[i],
+                    h: mesh[i + 1] - mesh[i],
+                    x0: states.row(i),
+                    x1: states.row(i + 1),
+                    f0: slopes[i].view(),
+                    f1: slopes[i + 1].view(),
+                };
+                let sum: f64 = [0.5 - 21f64.sqrt() / 14., 0.5 + 21f64.sqrt() / 14.]
+                    .iter()
+                    .map(|&s| {
+                        let (x, dx) = polynomial.eval(s);
+                        let f = self.flow_f64(polynomial.t + s * polynomial.h, x.view());
+                        dx.iter()
+                            .zip(f.iter())
+                            .map(|(dx, f)| ((dx - f) / (1. + f.abs())).powi(2))
+                            .sum::<f64>()
+                    })
+                    .sum();
+                (0.5 * 49. / 90. * sum).sqrt()
+            })
+            .collect()
+    }
+
+    /// Splits intervals above the tolerance into two, or three if the residual is far off,
+    /// and interpolates the states of the new nodes.
+    fn refine(
+        &self,
+        mesh: &[f64],
+        states: &Array2<f64>,
+        residuals: &[f64],
+    ) -> (Vec<f64>, Array2<f64>) {
+        let n = states.ncols();
+        let mut new_mesh = vec![mesh[0]];
+        let mut new_states = Array2::zeros((0, n));
+        new_states.push_row(states.row(0)).unwrap();
+        for (i, &residual) in residuals.iter().enumerate() {
+            let inner: &[f64] = if residual <= self.tol {
+                &[]
+            } else if residual < 100. * self.tol {
+                &[0.5]
+            } else {
+                &[1. / 3., 2. / 3.]
+            };
+            let (f0, f1) = (
+                self.flow_f64(mesh[i], states.row(i)),
+                self.flow_f64(mesh[i + 1], states.row(i + 1)),
+            );
+            let polynomial = Hermite {
+                t: mesh[i],
+                h: mesh[i + 1] - mesh[i],
+                x0: states.row(i),
+                x1: states.row(i + 1),
+                f0: f0.view(),
+                f1: f1.view(),
+            };
+            for &s in inner {
+                new_mesh.push(polynomial.t + s * polynomial.h);
+                new_states.push_row(polynomial.eval(s).0.view()).unwrap();
+            }
+            new_mesh.push(mesh[i + 1]);
+            new_states.push_row(states.row(i + 1)).unwrap();
+        }
+        (new_mesh, new_states)
+    }
+}
+
+/// Cubic Hermite interpolation on `[t, t + h]`.
+struct Hermite<'a> {
+    t: f64,
+    h: f64,
+    x0: ArrayView1<'a, f64>,
+    x1: ArrayView1<'a, f64>,
+    f0: ArrayView1<'a, f64>,
+    f1: ArrayView1<'a, f64>,
+}
+
+impl<'a> Hermite<'a> {
+    /// Value and time derivative at `t + s h`.
+    fn eval(&self, s: f64) -> (Array1<f64>, Array1<f64>) {
+        let h = self.h;
+        let (s2, s3) = (s * s, s * s * s);
+        let x = (2. * s3 - 3. * s2 + 1.) * &self.x0
+            + (-2. * s3 + 3. * s2) * &self.x1
+            + (h * (s3 - 2. * s2 + s)) * &self.f0
+            + (h * (s3 - s2)) * &self.f1;
+        let dx = ((6. * s2 - 6. * s) / h) * &(&self.x0 - &self.x1)
+            + (3. * s2 - 4. * s + 1.) * &self.f0
+            + (3. * s2 - 2. * s) * &self.f1;
+        (x, dx)
+    }
+}
+
+/// Lobatto IIIA equations of one interval in the stacked states `(x_i, x_i+1)`.
+struct IntervalResidual<'a, Flow>
+where
+    Flow: Fn(f64, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+{
+    flow: &'a Flow,
+    t: f64,
+    h: f64,
+}
+
+impl<'a, Flow> Residual for IntervalResidual<'a, Flow>
+where
+    Flow: Fn(f64, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+{
+    fn eval(&self, x: ArrayView1<AD>, update: &mut Array1<AD>) {
+        let (t, h) = (self.t, self.h);
+        let n = x.len() / 2;
+        let (x0, x1) = (x.slice(s![..n]), x.slice(s![n..]));
+        let f0 = (self.flow)(t, x0);
+        let f1 = (self.flow)(t + h, x1);
+        let mut middle = f0.clone();
+        for j in 0..n {
+            middle[j] = (x0[j] + x1[j]) * 0.5 - (f1[j] - f0[j]) * (h / 8.);
+        }
+        let fm = (self.flow)(t + 0.5 * h, middle.view());
+        for j in 0..n {
+            update[j] = x1[j] - x0[j] - (f0[j] + fm[j] * 4. + f1[j]) * (h / 6.);
+        }
+    }
+}
+
+/// Boundary conditions in the stacked states `(x(a), x(b))`.
+struct BoundaryResidual<'a, Boundary>
+where
+    Boundary: Fn(ArrayView1<AD>, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+{
+    boundary: &'a Boundary,
+}
+
+impl<'a, Boundary> Residual for BoundaryResidual<'a, Boundary>
+where
+    Boundary: Fn(ArrayView1<AD>, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+{
+    fn eval(&self, x: ArrayView1<AD>, update: &mut Array1<AD>) {
+        let n = x.len() / 2;
+        update.assign(&(self.boundary)(x.slice(s![..n]), x.slice(s![n..])));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, ArrayView1};
+
+    use crate::{ad::AD, bvp::*};
+
+    #[test]
+    fn refines_boundary_layer() {
+        // 1e-4 x'' = x, x(0) = 1, x(1) = 0 has a layer of width 1e-2 at t = 0
+        let flow = |_t: f64, x: ArrayView1<AD>| array![x[1], x[0] * 1e4];
+        let boundary = |a: ArrayView1<AD>, b: ArrayView1<AD>| array![a[0] - 1., b[0]];
+        let mesh = (0..=10).map(|i| i as f64 / 10.).collect();
+        let mut bvp = Bvp::collocation(flow, boundary, mesh, |t| array![1. - t, -1.]);
+        bvp.set_tolerance(1e-6);
+        let solution = bvp.solve().unwrap();
+
+        let nodes_in = |a: f64, b: f64| solution.time.iter().filter(|&&t| a <= t && t < b).count();
+        assert!(nodes_in(0., 0.05) > nodes_in(0.5, 1.));
+        for (t, x) in solution.iter() {
+            let exact = (100. * (1. - t)).sinh() / 100f64.sinh();
+            assert!((x[0] - exact).abs() < 1e-8);
+        }
+    }
+}
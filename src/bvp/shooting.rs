@@ -0,0 +1,267 @@
+use ndarray::{s, Array, Array1, Array2, ArrayView1};
+use ndarray_linalg::Norm;
+
+use crate::{
+    ad::*,
+    bvp::BvpError,
+    ode::{solver::ButcherTableau, *},
+};
+
+/// Single and multiple shooting with a fixed step Runge-Kutta integrator.
+///
+/// The unknowns are the states `s_k` at the start of each segment `[t_k, t_k+1]`. They are found by [newton]
+/// from the continuity conditions `φ(t_k+1; t_k, s_k) = s_k+1` and the boundary conditions
+/// `g(s_0, φ(t_m; t_m-1, s_m-1)) = 0`. The Jacobian is exact for the discrete flow, as the
+/// integration runs on [AD] numbers.
+pub struct Shooting<Flow, Boundary>
+where
+    Flow: Fn(f64, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+    Boundary: Fn(ArrayView1<AD>, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+{
+    flow: Flow,
+    boundary: Boundary,
+    nodes: Vec<f64>,
+    guess: Array1<f64>,
+    tableau: ButcherTableau,
+    h: f64,
+    ɛ: f64,
+    max_sweeps: usize,
+}
+
+impl<Flow, Boundary> Shooting<Flow, Boundary>
+where
+    Flow: Fn(f64, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+    Boundary: Fn(ArrayView1<AD>, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+{
+    pub fn new(
+        flow: Flow,
+        boundary: Boundary,
+        nodes: Vec<f64>,
+        guess: impl Fn(f64) -> Array1<f64>,
+    ) -> Self {
+        assert!(nodes.len() >= 2, "Shooting needs at least one segment");
+        assert!(
+            nodes.windows(2).all(|w| w[0] < w[1]),
+            "Shooting nodes have to be increasing"
+        );
+        let guess = nodes[..nodes.len() - 1]
+            .iter()
+            .flat_map(|&t| guess(t))
+            .collect();
+        Shooting {
+            flow,
+            boundary,
+            nodes,
+            guess,
+            tableau: ButcherTableau::rk4(),
+            h: 0.01,
+            ɛ: 1e-10,
+            max_sweeps: 5,
+        }
+    }
+
+    /// Upper bound of the integrator step, each segment is divided into equal steps.
+    pub fn set_step_size(&mut self, h: f64) -> &mut Self {
+        self.h = h;
+        self
+    }
+
+    pub fn set_tableau(&mut self, tableau: ButcherTableau) -> &mut Self {
+        self.tableau = tableau;
+        self
+    }
+
+    /// Tolerance for the norm of the shooting equations.
+    pub fn set_epsilon(&mut self, ɛ: f64) -> &mut Self {
+        self.ɛ = ɛ;
+        self
+    }
+
+    /// Number of restarts of [newton], which stops on its own after a few iterations.
+    pub fn set_max_sweeps(&mut self, max_sweeps: usize) -> &mut Self {
+        self.max_sweeps = max_sweeps;
+        self
+    }
+
+    fn segments(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    fn dim(&self) -> usize {
+        self.guess.len() / self.segments()
+    }
+
+    /// Integrates segment `k` from `x`, `observe` receives every step.
+    fn propagate(
+        &self,
+        k: usize,
+        mut x: Array1<AD>,
+        mut observe: impl FnMut(f64, &Array1<AD>),
+    ) -> Array1<AD> {
+        let (t0, t1) = (self.nodes[k], self.nodes[k + 1]);
+        let n = ((t1 - t0) / self.h - 1e-9).ceil().max(1.) as usize;
+        let h = (t1 - t0) / n as f64;
+        for i in 0..n {
+            let t = t0 + i as f64 * h;
+            let slopes = self
+                .tableau
+                .stage_slopes(|c, x| (self.flow)(t + c * h, x), x.view(), h);
+            x = self.tableau.solution(x.view(), &slopes, h);
+            observe(t + h, &x);
+        }
+        x
+    }
+
+    /// Solves the shooting equations and returns the whole trajectory.
+    #[allow(non_snake_case)]
+    pub fn solve(&self) -> Result<Solution, BvpError> {
+        let l = self.guess.len();
+        let mut J = Array2::zeros((l, l));
+        let mut slope_buffer = self.guess.to_ad();
+        let residual = ShootingResidual { shooting: self };
+
+        let mut s = self.guess.to_ad();
+        let mut update = s.clone();
+        let mut stats = SolverStats::default();
+        for _ in 0..self.max_sweeps {
+            let start = s.to_f64();
+            s = newton_with_stats(self.ɛ, &residual, s, &mut J, &mut slope_buffer, &mut stats)?;
+            residual.eval(s.view(), &mut update);
+            let err = update.to_f64().norm();
+            if !err.is_finite() {
+                return Err(self.divergence(start.view(), stats.newton_iterations));
+            }
+            if err < self.ɛ {
+                let (time, states) = self.trajectory(s.to_f64().view());
+                stats.steps = time.len() - 1;
+                return Ok(Solution::new(time, states, stats));
+            }
+        }
+        residual.eval(s.view(), &mut update);
+        Err(BvpError::NotConverged {
+            residual: update.to_f64().norm(),
+            iterations: stats.newton_iterations,
+        })
+    }
+
+    /// Locates the first non finite state of the trajectory from the last finite unknowns `s`.
+    ///
+    /// If this trajectory is still finite, the Newton updates themselves overflowed.
+    fn divergence(&self, s: ArrayView1<f64>, iterations: usize) -> BvpError {
+        let (time, states) = self.trajectory(s);
+        match time
+            .iter()
+            .zip(states.rows())
+            .find(|(_, x)| !x.iter().all(|x| x.is_finite()))
+        {
+            Some((&t, _)) => BvpError::NonFinite { t },
+            None => BvpError::NotConverged {
+                residual: f64::NAN,
+                iterations,
+            },
+        }
+    }
+
+    fn trajectory(&self, s: ArrayView1<f64>) -> (Vec<f64>, Array2<f64>) {
+        let n = self.dim();
+        let mut time = vec![self.nodes[0]];
+        let mut result: Array2<f64> = Array::zeros((0, n));
+        result.push_row(s.slice(s![..n])).unwrap();
+        for k in 0..self.segments() {
+            let x = s.slice(s![k * n..(k + 1) * n]).to_ad();
+            self.propagate(k, x, |t, x| {
+                time.push(t);
+                result.push_row(x.to_f64().view()).unwrap();
+            });
+        }
+        (time, result)
+    }
+}
+
+/// Continuity and boundary conditions in the stacked unknowns of all segments.
+struct ShootingResidual<'a, Flow, Boundary>
+where
+    Flow: Fn(f64, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+    Boundary: Fn(ArrayView1<AD>, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+{
+    shooting: &'a Shooting<Flow, Boundary>,
+}
+
+impl<'a, Flow, Boundary> Residual for ShootingResidual<'a, Flow, Boundary>
+where
+    Flow: Fn(f64, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+    Boundary: Fn(ArrayView1<AD>, ArrayView1<AD>) -> Array1<AD> + std::marker::Sync,
+{
+    fn eval(&self, s: ArrayView1<AD>, update: &mut Array1<AD>) {
+        let shooting = self.shooting;
+        let (n, m) = (shooting.dim(), shooting.segments());
+        for k in 0..m {
+            let x = shooting.propagate(k, s.slice(s![k * n..(k + 1) * n]).to_owned(), |_, _| {});
+            let mut rows = update.slice_mut(s![k * n..(k + 1) * n]);
+            if k + 1 < m {
+                let next = s.slice(s![(k + 1) * n..(k + 2) * n]);
+                rows.iter_mut()
+                    .zip(x.iter().zip(next.iter()))
+                    .for_each(|(r, (&x, &next))| *r = x - next);
+            } else {
+                let g = (shooting.boundary)(s.slice(s![..n]), x.view());
+                assert_eq!(g.len(), n, "Expected {n} boundary conditions");
+                rows.assign(&g);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{ad::AD, bvp::*};
+
+    fn boundary(a: ArrayView1<AD>, b: ArrayView1<AD>) -> Array1<AD> {
+        array![a[0], b[0] - 1.]
+    }
+
+    #[test]
+    fn multiple_shooting_of_unstable_problem() {
+        // x'' = 400 x, x(0) = 0, x(1) = 1 grows like e^20 over the interval
+        let flow = |_t: f64, x: ArrayView1<AD>| array![x[1], 400. * x[0]];
+        let nodes = (0..=10).map(|i| i as f64 / 10.).collect();
+        let mut bvp = Bvp::multiple_shooting(flow, boundary, nodes, |t| array![t, 1.]);
+        bvp.set_step_size(1e-3);
+        let solution = bvp.solve().unwrap();
+
+        for (t, x) in solution.iter() {
+            let exact = (20. * t).sinh() / 20f64.sinh();
+            assert!((x[0] - exact).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn single_shooting_of_nonlinear_problem() {
+        // x'' = 3/2 x², x(0) = 4, x(1) = 1 is solved by x = 4 / (1 + t)² with x'(0) = -8
+        let flow = |_t: f64, x: ArrayView1<AD>| array![x[1], x[0] * x[0] * 1.5];
+        let boundary = |a: ArrayView1<AD>, b: ArrayView1<AD>| array![a[0] - 4., b[0] - 1.];
+        let mut bvp = Bvp::shooting(flow, boundary, (0., 1.), array![4., -6.]);
+        bvp.set_step_size(1e-3);
+        let solution = bvp.solve().unwrap();
+
+        assert!((solution.states[[0, 1]] + 8.).abs() < 1e-8);
+        for (t, x) in solution.iter() {
+            assert!((x[0] - 4. / (1. + t).powi(2)).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn single_shooting_diverges() {
+        // the unstable problem of multiple_shooting_of_unstable_problem on a longer interval, e^(20 t)
+        // leaves the range of f64 before t = 36
+        let flow = |_t: f64, x: ArrayView1<AD>| array![x[1], 400. * x[0]];
+        let mut bvp = Bvp::shooting(flow, boundary, (0., 40.), array![0., 1.]);
+        bvp.set_step_size(0.02);
+        match bvp.solve() {
+            Err(BvpError::NonFinite { t }) => assert!(t > 30. && t < 36., "{t}"),
+            result => panic!("{:?}", result.map(|solution| solution.len())),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Solvers for delay differential equations `x'(t) = f(t, x(t), x(t - τ_1), ..., x(t - τ_k))`.
+//!
+//! For `t <= t0` the state is given by an initial function, afterwards it is taken from the dense output of the
+//! accepted steps. Delays may be constant or depend on time and state.
+use ndarray::{Array1, ArrayView1};
+
+mod history;
+mod one_step;
+pub use one_step::*;
+
+/// `τ(t, x(t))`
+pub type DelayFn = dyn Fn(f64, ArrayView1<f64>) -> f64 + Send + Sync;
+
+/// A delay `τ` of the argument `x(t - τ)`.
+pub enum Delay {
+    Constant(f64),
+    StateDependent(Box<DelayFn>),
+}
+
+impl Delay {
+    pub fn state_dependent(
+        τ: impl Fn(f64, ArrayView1<f64>) -> f64 + Send + Sync + 'static,
+    ) -> Self {
+        Delay::StateDependent(Box::new(τ))
+    }
+
+    pub fn eval(&self, t: f64, x: ArrayView1<f64>) -> f64 {
+        match self {
+            Delay::Constant(τ) => *τ,
+            Delay::StateDependent(τ) => τ(t, x),
+        }
+    }
+
+    pub fn is_state_dependent(&self) -> bool {
+        matches!(self, Delay::StateDependent(_))
+    }
+}
+
+pub struct Dde;
+impl Dde {
+    /// The flow receives `t`, `x(t)` and the lagged states in the order of `delays`.
+    pub fn explicit<Flow, Initial>(
+        flow: Flow,
+        initial: Initial,
+        delays: Vec<Delay>,
+    ) -> DdeEx<Flow, Initial>
+    where
+        Flow: Fn(f64, ArrayView1<f64>, &[Array1<f64>]) -> Array1<f64>,
+        Initial: Fn(f64) -> Array1<f64>,
+    {
+        DdeEx::new(flow, initial, delays)
+    }
+}
@@ -0,0 +1,74 @@
+use ndarray::Array1;
+
+/// One accepted step together with the slopes at both ends.
+pub struct Segment {
+    pub t: f64,
+    pub h: f64,
+    pub x0: Array1<f64>,
+    pub x1: Array1<f64>,
+    pub f0: Array1<f64>,
+    pub f1: Array1<f64>,
+}
+
+impl Segment {
+    /// Cubic Hermite interpolation, which matches the order of the Bogacki-Shampine pair.
+    pub fn eval(&self, t: f64) -> Array1<f64> {
+        let s = (t - self.t) / self.h;
+        let (s2, s3) = (s * s, s * s * s);
+        let h00 = 2. * s3 - 3. * s2 + 1.;
+        let h10 = (s3 - 2. * s2 + s) * self.h;
+        let h01 = -2. * s3 + 3. * s2;
+        let h11 = (s3 - s2) * self.h;
+        let mut x = h00 * &self.x0;
+        x.scaled_add(h10, &self.f0);
+        x.scaled_add(h01, &self.x1);
+        x.scaled_add(h11, &self.f1);
+        x
+    }
+}
+
+/// Dense output of all accepted steps, continued into the past by the user supplied initial function.
+pub struct History<'a> {
+    initial: &'a dyn Fn(f64) -> Array1<f64>,
+    t0: f64,
+    segments: Vec<Segment>,
+}
+
+impl<'a> History<'a> {
+    pub fn new(initial: &'a dyn Fn(f64) -> Array1<f64>, t0: f64) -> Self {
+        History {
+            initial,
+            t0,
+            segments: vec![],
+        }
+    }
+
+    pub fn push(&mut self, segment: Segment) {
+        self.segments.push(segment);
+    }
+
+    /// `x(t)`, times after the last accepted step are extrapolated from it.
+    pub fn eval(&self, t: f64) -> Array1<f64> {
+        if t <= self.t0 || self.segments.is_empty() {
+            return (self.initial)(t.min(self.t0));
+        }
+        let k = self.segments.partition_point(|s| s.t + s.h < t);
+        self.segments[k.min(self.segments.len() - 1)].eval(t)
+    }
+}
+
+/// Bisection for a sign change of `g` in `[a, b]` with `g(a) < 0 <= g(b)`.
+pub fn bisect(g: impl Fn(f64) -> f64, mut a: f64, mut b: f64) -> f64 {
+    for _ in 0..100 {
+        let m = 0.5 * (a + b);
+        if m <= a || m >= b {
+            break;
+        }
+        if g(m) < 0. {
+            a = m;
+        } else {
+            b = m;
+        }
+    }
+    b
+}
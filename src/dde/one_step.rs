@@ -0,0 +1,366 @@
+use ndarray::*;
+
+use crate::{
+    dde::{history::*, *},
+    ode::{solver::ButcherTableau, *},
+    progress::*,
+};
+
+/// Adaptive Bogacki-Shampine 3(2) solver for delay differential equations.
+///
+/// Lagged states are taken from the dense output of all accepted steps, or from the initial function for `t <= t0`.
+/// A derivative discontinuity at `t0` propagates along the delays and the step size is
+/// shortened so that steps end exactly on these breaking points, up to the order of the method.
+/// The run fails once a step of the smallest step size still misses the tolerances.
+#[allow(non_snake_case)]
+pub struct DdeEx<Flow, Initial>
+where
+    Flow: Fn(f64, ArrayView1<f64>, &[Array1<f64>]) -> Array1<f64>,
+    Initial: Fn(f64) -> Array1<f64>,
+{
+    flow: Flow,
+    initial: Initial,
+    delays: Vec<Delay>,
+    t0: f64,
+    h: f64,
+    T: f64,
+    controller: StepController,
+    tableau: ButcherTableau,
+    breakpoints: Vec<Breakpoint>,
+    progress: Box<dyn Progress>,
+    limits: Limits,
+}
+
+/// A time at which a derivative of the solution may jump, `level` counts the propagations since `t0`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Breakpoint {
+    pub t: f64,
+    pub level: usize,
+}
+
+impl<Flow, Initial> DdeEx<Flow, Initial>
+where
+    Flow: Fn(f64, ArrayView1<f64>, &[Array1<f64>]) -> Array1<f64>,
+    Initial: Fn(f64) -> Array1<f64>,
+{
+    pub fn new(flow: Flow, initial: Initial, delays: Vec<Delay>) -> Self {
+        DdeEx {
+            flow,
+            initial,
+            delays,
+            t0: 0.0,
+            h: 0.1,
+            T: 1.0,
+            controller: StepController::default(),
+            tableau: ButcherTableau::bogacki_shampine(),
+            breakpoints: vec![],
+            progress: Box::<TqdmProgress>::default(),
+            limits: Limits::default(),
+        }
+    }
+
+    /// Start of the integration, the initial function has to cover all `t <= t0` reached by the delays.
+    pub fn set_t0(&mut self, t0: f64) -> &mut Self {
+        self.t0 = t0;
+        self
+    }
+
+    pub fn set_tolerances(&mut self, atol: f64, rtol: f64) -> &mut Self {
+        self.controller.atol = atol;
+        self.controller.rtol = rtol;
+        self
+    }
+
+    pub fn set_max_step(&mut self, h_max: f64) -> &mut Self {
+        self.controller.h_max = h_max;
+        self
+    }
+
+    /// Additional known discontinuities, e.g. jumps of the initial function before `t0`.
+    pub fn add_breakpoint(&mut self, t: f64) -> &mut Self {
+        self.breakpoints.push(Breakpoint { t, level: 0 });
+        self
+    }
+
+    fn lags(&self, t: f64, x: ArrayView1<f64>, history: &History) -> Vec<Array1<f64>> {
+        self.delays
+            .iter()
+            .map(|delay| {
+                let τ = delay.eval(t, x);
+                assert!(τ >= 0., "Negative delay {τ} at t = {t}");
+                history.eval(t - τ)
+            })
+            .collect()
+    }
+
+    /// Earliest point in the step at which a state dependent delay maps onto a known breakpoint.
+    fn crossing(&self, step: &Segment, passed: &[Breakpoint]) -> Option<Breakpoint> {
+        let (a, b) = (step.t, step.t + step.h);
+        let mut first: Option<Breakpoint> = None;
+        for delay in self.delays.iter().filter(|d| d.is_state_dependent()) {
+            for ξ in passed.iter().filter(|ξ| ξ.level < self.tableau.order) {
+                let g = |s: f64| s - delay.eval(s, step.eval(s).view()) - ξ.t;
+                if g(a) < 0. && g(b) >= 0. {
+                    let t = bisect(g, a, b);
+                    match first {
+                        Some(f) if f.t <= t => {}
+                        _ => {
+                            first = Some(Breakpoint {
+                                t,
+                                level: ξ.level + 1,
+                            })
+                        }
+                    }
+                }
+            }
+        }
+        first
+    }
+
+    /// Registers a reached breakpoint and propagates it along the constant delays.
+    fn pass(&self, ξ: Breakpoint, passed: &mut Vec<Breakpoint>, upcoming: &mut Vec<Breakpoint>) {
+        if ξ.level < self.tableau.order {
+            for delay in self.delays.iter() {
+                if let Delay::Constant(τ) = delay {
+                    upcoming.push(Breakpoint {
+                        t: ξ.t + τ,
+                        level: ξ.level + 1,
+                    });
+                }
+            }
+        }
+        passed.push(ξ);
+    }
+}
+
+impl<Flow, Initial> ODE<Flow> for DdeEx<Flow, Initial>
+where
+    Flow: Fn(f64, ArrayView1<f64>, &[Array1<f64>]) -> Array1<f64>,
+    Initial: Fn(f64) -> Array1<f64>,
+{
+    /// Set the initial step size, afterwards it is adapted to the tolerances.
+    fn set_step_size(&mut self, h: f64) -> &mut Self {
+        self.h = h;
+        self
+    }
+
+    #[allow(non_snake_case)]
+    fn set_t(&mut self, T: f64) -> &mut Self {
+        self.T = T;
+        self
+    }
+
+    fn set_progress<P: Progress + 'static>(&mut self, progress: P) -> &mut Self {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    fn limits_mut(&mut self) -> &mut Limits {
+        &mut self.limits
+    }
+
+    #[allow(non_snake_case)]
+    fn run(mut self) -> Solution {
+        let (t0, T) = (self.t0, self.T);
+        let initial: &dyn Fn(f64) -> Array1<f64> = &self.initial;
+        let mut history = History::new(initial, t0);
+        let mut controller = self.controller;
+        let order = self.tableau.order;
+        let ɛ = 1e-12 * T.abs().max(1.);
+
+        let mut t = t0;
+        let mut x = (self.initial)(t0);
+        let mut f = (self.flow)(t, x.view(), &self.lags(t, x.view(), &history));
+        let mut stats = SolverStats {
+            rhs_evaluations: 1,
+            ..Default::default()
+        };
+        let mut time = vec![t];
+        let mut result: Array2<f64> = Array::zeros((0, x.len()));
+        result.push_row(x.view()).unwrap();
+
+        let mut passed = vec![];
+        let mut upcoming = std::mem::take(&mut self.breakpoints);
+        self.pass(Breakpoint { t: t0, level: 0 }, &mut passed, &mut upcoming);
+
+        let mut h = self.h.min(controller.h_max);
+        self.progress.start(t0, T);
+        self.limits.start();
+        let mut termination = Termination::Completed;
+        while t < T - ɛ {
+            if let Some(stop) = self.limits.check(t) {
+                termination = stop;
+                break;
+            }
+            upcoming.retain(|ξ| ξ.t > t + ɛ);
+            upcoming.sort_by(|a, b| a.t.total_cmp(&b.t));
+            let target = upcoming.first().map_or(T, |ξ| ξ.t.min(T));
+            let landing = h >= target - t - ɛ;
+            let h_step = if landing { target - t } else { h };
+
+            let k = self.tableau.stage_slopes(
+                |c, x_stage| {
+                    let s = t + c * h_step;
+                    (self.flow)(s, x_stage, &self.lags(s, x_stage, &history))
+                },
+                x.view(),
+                h_step,
+            );
+            stats.rhs_evaluations += self.tableau.stages();
+            let x1 = self.tableau.solution(x.view(), &k, h_step);
+            let err = self.tableau.error_estimate(&k, h_step).unwrap();
+            let err = controller.error_norm(x.view(), x1.view(), err.view());
+            if err > 1. || err.is_nan() {
+                if h_step > controller.h_min {
+                    stats.rejected_steps += 1;
+                    h = controller.propose(h_step, err, order - 1);
+                    continue;
+                }
+                termination = Termination::Failed {
+                    t,
+                    reason: format!(
+                        "The error {err} exceeds the tolerances at the smallest step size"
+                    ),
+                };
+                break;
+            }
+
+            let step = Segment {
+                t,
+                h: h_step,
+                x0: x.clone(),
+                x1,
+                f0: f.clone(),
+                f1: k[k.len() - 1].clone(),
+            };
+            if let Some(ξ) = self.crossing(&step, &passed) {
+                if ξ.t < t + h_step - ɛ {
+                    stats.rejected_steps += 1;
+                    upcoming.push(ξ);
+                    h = ξ.t - t;
+                    continue;
+                }
+                self.pass(
+                    Breakpoint {
+                        t: t + h_step, ..ξ
+                    },
+                    &mut passed,
+                    &mut upcoming,
+                );
+            }
+
+            let proposal = controller.propose(h_step, err, order - 1);
+            stats.steps += 1;
+            t += h_step;
+            x = step.x1.clone();
+            f = step.f1.clone();
+            history.push(step);
+            time.push(t);
+            result.push_row(x.view()).unwrap();
+            self.progress.update(t);
+
+            if landing {
+                while let Some(ξ) = upcoming.first().copied().filter(|ξ| ξ.t <= t + ɛ) {
+                    upcoming.remove(0);
+                    self.pass(Breakpoint { t, ..ξ }, &mut passed, &mut upcoming);
+                }
+                h = proposal.max(h);
+            } else {
+                h = proposal;
+            }
+        }
+        self.progress.finish();
+        let mut solution = Solution::new(time, result, stats);
+        solution.termination = termination;
+        solution.events = passed
+            .iter()
+            .filter(|ξ| ξ.t > t0)
+            .filter_map(|ξ| {
+                Some(Event {
+                    name: "breakpoint".to_string(),
+                    t: ξ.t,
+                    state: solution.at(ξ.t)?,
+                })
+            })
+            .collect();
+        solution
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{
+        dde::*,
+        ode::{Termination, ODE},
+    };
+
+    fn negative_feedback(_t: f64, _x: ArrayView1<f64>, lags: &[Array1<f64>]) -> Array1<f64> {
+        -&lags[0]
+    }
+
+    #[test]
+    fn constant_delay_lands_on_breakpoints() {
+        let mut dde = Dde::explicit(
+            negative_feedback,
+            |_| array![1.0],
+            vec![Delay::Constant(1.0)],
+        );
+        dde.set_t(3.0).set_with_progress(false);
+        dde.set_tolerances(1e-10, 1e-8);
+        let solution = dde.run();
+
+        for breakpoint in [1.0, 2.0, 3.0] {
+            assert!(solution
+                .time
+                .iter()
+                .any(|&t| (t - breakpoint).abs() < 1e-12));
+        }
+        // x(t) = 1 - t + (t - 1)²/2 - (t - 2)³/6 on [2, 3]
+        let x = solution.at(3.0).unwrap()[0];
+        assert!((x + 1. / 6.).abs() < 1e-8);
+    }
+
+    #[test]
+    fn state_dependent_delay_lands_on_breakpoints() {
+        // x' = -x(t - x(t)) with x = 1 before 0 is x = 1 - t until t - x(t) reaches 0 at t = 1/2,
+        // afterwards x' = t - 1 - x up to t = 1/2 + ln(4/3), where t - x(t) reaches the breakpoint 1/2
+        let mut dde = Dde::explicit(
+            negative_feedback,
+            |_| array![1.0],
+            vec![Delay::state_dependent(|_, x| x[0])],
+        );
+        dde.set_t(0.75).set_with_progress(false);
+        dde.set_tolerances(1e-10, 1e-8);
+        let solution = dde.run();
+        assert_eq!(solution.termination, Termination::Completed);
+
+        assert!(solution.time.iter().any(|&t| (t - 0.5).abs() < 1e-12));
+        assert!(solution
+            .events
+            .iter()
+            .any(|event| (event.t - 0.5).abs() < 1e-12));
+        let x = solution.at(0.75).unwrap()[0];
+        assert!((x - (2. * (-0.25f64).exp() - 1.25)).abs() < 1e-8, "{x}");
+    }
+
+    #[test]
+    fn not_a_number_fails() {
+        // x = 1 - t until the flow breaks down at x = 1/2
+        let flow = |_t: f64, x: ArrayView1<f64>, lags: &[Array1<f64>]| {
+            if x[0] > 0.5 {
+                -&lags[0]
+            } else {
+                array![f64::NAN]
+            }
+        };
+        let mut dde = Dde::explicit(flow, |_| array![1.0], vec![Delay::Constant(1.0)]);
+        dde.set_with_progress(false);
+        let solution = dde.run();
+        match solution.termination {
+            Termination::Failed { t, .. } => assert!((t - 0.5).abs() < 1e-6, "{t}"),
+            termination => panic!("{termination:?}"),
+        }
+    }
+}
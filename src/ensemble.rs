@@ -0,0 +1,204 @@
+//! Many independent runs of one problem in parallel, e.g. a parameter sweep or a Monte Carlo study.
+//!
+//! Member `i` of an [Ensemble] starts from `generate(i) = (initial, params)` and is integrated by
+//! `solve(initial, &params)`, which sets up any solver and returns its [Solution]. Members run on the
+//! rayon thread pool, so the solvers inside `solve` should not draw progress bars.
+use std::{fs, path::Path};
+
+use ndarray::{Array1, ArrayView1};
+use polars::prelude::{PolarsError, PolarsResult};
+use rayon::prelude::*;
+
+use crate::{ode::Solution, plot};
+
+pub struct Ensemble<Params, Generate, Solve>
+where
+    Params: Send,
+    Generate: Fn(usize) -> (Array1<f64>, Params) + Sync,
+    Solve: Fn(Array1<f64>, &Params) -> Solution + Sync,
+{
+    members: usize,
+    generate: Generate,
+    solve: Solve,
+}
+
+impl<Params, Generate, Solve> Ensemble<Params, Generate, Solve>
+where
+    Params: Send,
+    Generate: Fn(usize) -> (Array1<f64>, Params) + Sync,
+    Solve: Fn(Array1<f64>, &Params) -> Solution + Sync,
+{
+    pub fn new(members: usize, generate: Generate, solve: Solve) -> Self {
+        Ensemble {
+            members,
+            generate,
+            solve,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.members
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members == 0
+    }
+
+    /// Runs every member and keeps only `reduce(index, &params, &solution)`, ordered by the index.
+    ///
+    /// Each trajectory is dropped right after its reduction, see [final_state] and [max_drift] for common ones.
+    pub fn reduce<R, Reduce>(&self, reduce: Reduce) -> Vec<R>
+    where
+        R: Send,
+        Reduce: Fn(usize, &Params, &Solution) -> R + Sync,
+    {
+        (0..self.members)
+            .into_par_iter()
+            .map(|i| {
+                let (params, solution) = self.member(i);
+                reduce(i, &params, &solution)
+            })
+            .collect()
+    }
+
+    /// Runs every member and keeps all trajectories.
+    pub fn run(&self) -> Vec<Solution> {
+        (0..self.members)
+            .into_par_iter()
+            .map(|i| self.member(i).1)
+            .collect()
+    }
+
+    /// The parameters and the solution of member `i`.
+    fn member(&self, i: usize) -> (Params, Solution) {
+        let (initial, params) = (self.generate)(i);
+        let solution = (self.solve)(initial, &params);
+        (params, solution)
+    }
+
+    /// Writes the trajectory of member `i` to `folder/member=i/part-0.parquet`.
+    ///
+    /// The hive style partitions are read as one dataset with a `member` column, e.g. by
+    /// `pyarrow.dataset.dataset(folder, partitioning="hive")`.
+    pub fn store(&self, folder: impl AsRef<Path>) -> PolarsResult<()> {
+        let folder = folder.as_ref();
+        self.reduce(|i, _, solution| {
+            let partition = folder.join(format!("member={i}"));
+            fs::create_dir_all(&partition)?;
+            let mut df = solution.to_dataframe();
+            df.push_metadata("member", &i.to_string());
+            df.store(&mut fs::File::create(partition.join("part-0.parquet"))?)
+        })
+        .into_iter()
+        .collect()
+    }
+
+    /// Writes one row per member to the file at `path`, the column `member` followed by the named values of `reduce`.
+    ///
+    /// Every member has to reduce to the same names in the same order, otherwise nothing is written.
+    pub fn store_reduced<Reduce>(&self, path: impl AsRef<Path>, reduce: Reduce) -> PolarsResult<()>
+    where
+        Reduce: Fn(usize, &Params, &Solution) -> Vec<(String, f64)> + Sync,
+    {
+        let rows = self.reduce(reduce);
+        let names: Vec<String> = rows
+            .first()
+            .map(|row| row.iter().map(|(name, _)| name.clone()).collect())
+            .unwrap_or_default();
+        if let Some(i) = rows
+            .iter()
+            .position(|row| !row.iter().map(|(name, _)| name).eq(names.iter()))
+        {
+            return Err(PolarsError::ComputeError(
+                format!("Member {i} reduces to other names than member 0").into(),
+            ));
+        }
+        let members: Vec<f64> = (0..rows.len()).map(|i| i as f64).collect();
+        let columns: Vec<Vec<f64>> = (0..names.len())
+            .map(|j| rows.iter().map(|row| row[j].1).collect())
+            .collect();
+
+        let mut df = plot::Dataframe::new();
+        df.push(plot::Series::new("member", &members));
+        for (name, column) in names.iter().zip(columns.iter()) {
+            df.push(plot::Series::new(name, column));
+        }
+        df.store(&mut fs::File::create(path)?)
+    }
+}
+
+/// The state at the end of a run.
+pub fn final_state(solution: &Solution) -> Array1<f64> {
+    let n = solution.len();
+    assert!(n > 0, "A solution contains at least the initial state");
+    solution.states.row(n - 1).to_owned()
+}
+
+/// The largest deviation of an `invariant`, e.g. the energy, from its initial value.
+pub fn max_drift(solution: &Solution, invariant: impl Fn(ArrayView1<f64>) -> f64) -> f64 {
+    let mut values = solution.states.rows().into_iter().map(invariant);
+    let Some(initial) = values.next() else {
+        return 0.;
+    };
+    values.fold(0., |drift: f64, value| drift.max((value - initial).abs()))
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1, Axis};
+
+    use crate::{ensemble::*, ode::*};
+
+    #[test]
+    fn sweep_of_decay_rates() {
+        let ensemble = Ensemble::new(
+            8,
+            |i| (array![1.0], 0.1 * i as f64),
+            |initial, &rate| {
+                let flow = move |x: ArrayView1<f64>| -> Array1<f64> { -rate * &x };
+                let tableau = solver::ButcherTableau::rk4();
+                let mut ode = Ode::explicit(solver::RungeKutta::new(0.01, tableau, flow), initial);
+                ode.set_step_size(0.01).set_t(1.0).set_with_progress(false);
+                ode.run()
+            },
+        );
+        let finals = ensemble.reduce(|_, &rate, solution| {
+            let t = solution.time[solution.len() - 1];
+            (final_state(solution)[0], (-rate * t).exp())
+        });
+        assert_eq!(finals.len(), 8);
+        for (x, exact) in finals {
+            assert!((x - exact).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn reduced_values_need_the_same_names() {
+        let ensemble = Ensemble::new(
+            4,
+            |_| (array![1.0], ()),
+            |initial, _| {
+                Solution::new(
+                    vec![0.],
+                    initial.insert_axis(Axis(0)),
+                    SolverStats::default(),
+                )
+            },
+        );
+        let path = std::env::temp_dir().join("ndarray-ode-reduced.parquet");
+        let named = |i: usize| if i == 2 { "y" } else { "x" };
+        let result = ensemble.store_reduced(&path, |i, _, solution| {
+            vec![(named(i).to_string(), final_state(solution)[0])]
+        });
+        assert!(matches!(result, Err(PolarsError::ComputeError(_))));
+        assert!(!path.exists());
+
+        ensemble
+            .store_reduced(&path, |_, _, solution| {
+                vec![("x".to_string(), final_state(solution)[0])]
+            })
+            .unwrap();
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
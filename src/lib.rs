@@ -39,11 +39,18 @@
 //!     let mut ode = Ode::implicit(euler, x0.clone());
 //!     ode.set_step_size(h).set_t(T);
 //!     
-//!     let (time, result) = ode.run();
+//!     let solution = ode.run();
 //! }
 //! ```
-#![allow(uncommon_codepoints)]
+#![allow(uncommon_codepoints, confusable_idents)]
 pub mod ad;
+pub mod bvp;
+pub mod dde;
+pub mod ensemble;
 pub mod ode;
 pub mod plot;
 pub mod prelude;
+pub mod progress;
+pub mod sde;
+#[cfg(test)]
+mod test_support;
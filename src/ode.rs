@@ -2,13 +2,37 @@ use ndarray::Array1;
 
 mod traits;
 pub use traits::*;
+mod controller;
+pub use controller::*;
+mod stats;
+pub use stats::*;
+mod limits;
+pub use limits::*;
+mod checkpoint;
+pub use checkpoint::*;
+mod hamiltonian;
+pub use hamiltonian::*;
+mod lagrangian;
+pub use lagrangian::*;
+mod solution;
+pub use solution::*;
 pub mod root_finder;
 pub use root_finder::*;
 mod one_step;
 pub mod solver;
 use one_step::*;
 
+mod parareal;
+pub use parareal::*;
+mod adaptive;
+pub use adaptive::*;
+mod matrix;
+pub use matrix::*;
+mod multistep;
+pub use multistep::*;
+mod second_order;
 pub mod two_step;
+use second_order::*;
 
 pub struct Ode;
 impl Ode {
@@ -24,4 +48,43 @@ impl Ode {
     {
         OdeIm::new(scheme, initial)
     }
+    pub fn adaptive<Scheme>(scheme: Scheme, initial: Array1<f64>) -> OdeAdaptive<Scheme>
+    where
+        Scheme: Embedded,
+    {
+        OdeAdaptive::new(scheme, initial)
+    }
+    pub fn second_order<Scheme>(
+        scheme: Scheme,
+        q0: Array1<f64>,
+        v0: Array1<f64>,
+    ) -> OdeSecondOrder<Scheme>
+    where
+        Scheme: SecondOrder,
+    {
+        OdeSecondOrder::new(scheme, q0, v0)
+    }
+    pub fn matrix<Scheme>(scheme: Scheme, initial: ndarray::Array2<f64>) -> OdeMatrix<Scheme>
+    where
+        Scheme: MatrixScheme,
+    {
+        OdeMatrix::new(scheme, initial)
+    }
+    pub fn multistep<Scheme>(scheme: Scheme, initial: Array1<f64>) -> OdeMultistep<Scheme>
+    where
+        Scheme: Multistep,
+    {
+        OdeMultistep::new(scheme, initial)
+    }
+    pub fn parareal<Coarse, Fine>(
+        coarse: Coarse,
+        fine: Fine,
+        initial: Array1<f64>,
+    ) -> Parareal<Coarse, Fine>
+    where
+        Coarse: Propagator,
+        Fine: Propagator,
+    {
+        Parareal::new(coarse, fine, initial)
+    }
 }
@@ -0,0 +1,215 @@
+use crate::{ode::*, progress::*};
+use ndarray::*;
+use std::path::Path;
+
+/// Solver for `x' = f(t, x)` with step sizes adapted to the error estimate of an [Embedded] scheme.
+///
+/// The step size set by [ODE::set_step_size] is the first one tried. A step whose linear algebra fails
+/// is repeated with a quarter of the step size. The run fails once a step of the smallest step size of
+/// the [StepController] still misses the tolerances.
+#[allow(non_snake_case)]
+pub struct OdeAdaptive<Scheme>
+where
+    Scheme: Embedded,
+{
+    scheme: Scheme,
+    initial: Array1<f64>,
+    h: f64,
+    T: f64,
+    controller: StepController,
+    progress: Box<dyn Progress>,
+    limits: Limits,
+    checkpoints: Option<Checkpoints>,
+    resume: Option<Checkpoint>,
+}
+
+impl<Scheme> OdeAdaptive<Scheme>
+where
+    Scheme: Embedded,
+{
+    pub fn new(scheme: Scheme, initial: Array1<f64>) -> Self {
+        OdeAdaptive {
+            scheme,
+            initial,
+            h: 0.1,
+            T: 1.0,
+            controller: StepController::default(),
+            progress: Box::<TqdmProgress>::default(),
+            limits: Limits::default(),
+            checkpoints: None,
+            resume: None,
+        }
+    }
+
+    pub fn set_tolerances(&mut self, atol: f64, rtol: f64) -> &mut Self {
+        self.controller = StepController::new(atol, rtol);
+        self
+    }
+
+    /// Replaces the controller, e.g. to bound the step sizes.
+    pub fn set_controller(&mut self, controller: StepController) -> &mut Self {
+        self.controller = controller;
+        self
+    }
+}
+
+impl<Scheme> ODE<Scheme> for OdeAdaptive<Scheme>
+where
+    Scheme: Embedded,
+{
+    fn set_step_size(&mut self, h: f64) -> &mut Self {
+        self.h = h;
+        self
+    }
+
+    #[allow(non_snake_case)]
+    fn set_t(&mut self, T: f64) -> &mut Self {
+        self.T = T;
+        self
+    }
+
+    #[allow(non_snake_case)]
+    fn run(mut self) -> Solution {
+        let T = self.T;
+        let ɛ = 1e-12 * T.abs().max(1.);
+        let order = self.scheme.order().min(self.scheme.embedded_order()).max(1);
+        let (mut t, mut x, mut stats) = match self.resume.take() {
+            Some(checkpoint) => (checkpoint.t, checkpoint.state().clone(), checkpoint.stats),
+            None => (0.0, self.initial.clone(), SolverStats::default()),
+        };
+        let mut states = Array2::zeros((0, x.len()));
+        states.push_row(x.view()).unwrap();
+        let mut time = vec![t];
+
+        let mut h = self.h;
+        let mut controller = self.controller;
+        let mut termination = Termination::Completed;
+        self.progress.start(t, T);
+        self.limits.start();
+        while t < T - ɛ {
+            if let Some(stop) = self.limits.check(t) {
+                termination = stop;
+                break;
+            }
+            let h_step = h.min(T - t);
+            let step = match self.scheme.next(t, x.view(), h_step, &mut stats) {
+                Ok(step) => step,
+                Err(e) => {
+                    if h_step <= controller.h_min {
+                        termination = Termination::Failed {
+                            t,
+                            reason: e.to_string(),
+                        };
+                        break;
+                    }
+                    stats.rejected_steps += 1;
+                    h = (h_step / 4.).max(controller.h_min);
+                    continue;
+                }
+            };
+            let err = controller.error_norm(x.view(), step.x.view(), step.error.view());
+            h = controller.propose(h_step, err, order);
+            if err > 1. || err.is_nan() {
+                if h_step > controller.h_min {
+                    stats.rejected_steps += 1;
+                    continue;
+                }
+                termination = Termination::Failed {
+                    t,
+                    reason: format!(
+                        "The error {err} exceeds the tolerances at the smallest step size"
+                    ),
+                };
+                break;
+            }
+            stats.steps += 1;
+            t += h_step;
+            x = step.x;
+            time.push(t);
+            states.push_row(x.view()).unwrap();
+            self.progress.update(t);
+            if let Some(failed) =
+                Checkpoints::write_due(self.checkpoints.as_ref(), stats.steps, || Checkpoint {
+                    step: stats.steps,
+                    t,
+                    h,
+                    history: vec![x.clone()],
+                    controller: Some(controller),
+                    stats,
+                })
+            {
+                termination = failed;
+                break;
+            }
+        }
+        self.progress.finish();
+        let mut solution = Solution::new(time, states, stats);
+        solution.termination = termination;
+        solution
+    }
+
+    fn set_progress<P: Progress + 'static>(&mut self, progress: P) -> &mut Self {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    fn limits_mut(&mut self) -> &mut Limits {
+        &mut self.limits
+    }
+}
+
+impl<Scheme> Restart for OdeAdaptive<Scheme>
+where
+    Scheme: Embedded,
+{
+    fn set_checkpoints(&mut self, path: impl AsRef<Path>, every: usize) -> &mut Self {
+        self.checkpoints = Some(Checkpoints::new(path, every));
+        self
+    }
+
+    fn resume(&mut self, checkpoint: Checkpoint) -> &mut Self {
+        self.h = checkpoint.h;
+        if let Some(controller) = checkpoint.controller {
+            self.controller = controller;
+        }
+        self.resume = Some(checkpoint);
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{
+        ad::AD,
+        ode::{solver::*, *},
+    };
+
+    #[test]
+    fn not_a_number_fails() {
+        // exponential growth until the flow breaks down at x = 1.5, i.e. t = ln 1.5
+        let flow = |x: ArrayView1<AD>, f: &mut Array1<AD>| {
+            f[0] = if x[0].x() < 1.5 {
+                x[0]
+            } else {
+                AD::AD0(f64::NAN)
+            };
+        };
+        let mut controller = StepController::new(1e-8, 1e-8);
+        controller.h_min = 1e-8;
+        let mut ode = Ode::adaptive(
+            Rosenbrock::new(RosenbrockTableau::rodas4(), flow),
+            array![1.0],
+        );
+        ode.set_controller(controller)
+            .set_step_size(0.1)
+            .set_t(1.0)
+            .set_with_progress(false);
+        let solution = ode.run();
+        match solution.termination {
+            Termination::Failed { t, .. } => assert!((t - 1.5f64.ln()).abs() < 1e-6, "{t}"),
+            termination => panic!("{termination:?}"),
+        }
+    }
+}
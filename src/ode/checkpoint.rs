@@ -0,0 +1,303 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+use ndarray::Array1;
+
+use crate::ode::{SolverStats, StepController, Termination};
+
+/// The state of a run after `step` accepted steps, enough to continue it bit-for-bit.
+///
+/// Floating point numbers are stored with their bit pattern, so nothing is lost by rounding to decimals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub step: usize,
+    pub t: f64,
+    /// The fixed step size, or the next proposed one of an adaptive run.
+    pub h: f64,
+    /// The last states, oldest first, e.g. two of them for [crate::ode::two_step::OdeTwoStep]. For
+    /// [crate::ode::second_order::OdeSecondOrder] the state carried by the scheme, if any, precedes the state.
+    pub history: Vec<Array1<f64>>,
+    pub controller: Option<StepController>,
+    /// The work done up to the checkpoint, a resumed run continues counting from here.
+    pub stats: SolverStats,
+}
+
+const HEADER: &str = "ndarray-ode checkpoint 1";
+
+fn bits(x: f64) -> String {
+    format!("{:016x}", x.to_bits())
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+fn parse_bits(word: &str) -> Result<f64> {
+    u64::from_str_radix(word, 16)
+        .map(f64::from_bits)
+        .map_err(|e| invalid(format!("{word}: {e}")))
+}
+
+fn parse_usize(word: &str) -> Result<usize> {
+    word.parse().map_err(|e| invalid(format!("{word}: {e}")))
+}
+
+impl Checkpoint {
+    /// Writes the checkpoint next to `path` first and renames it, so an interrupted write keeps the last checkpoint.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut text = format!(
+            "{HEADER}\nstep {}\nt {}\nh {}\n",
+            self.step,
+            bits(self.t),
+            bits(self.h)
+        );
+        text += "stats";
+        for (key, value) in self.stats.metadata() {
+            text += &format!(" {key}={value}");
+        }
+        text += "\n";
+        if let Some(c) = self.controller {
+            let values = [
+                c.atol, c.rtol, c.safety, c.fac_min, c.fac_max, c.h_min, c.h_max, c.err_prev,
+            ];
+            text += "controller";
+            for value in values {
+                text += &format!(" {}", bits(value));
+            }
+            text += "\n";
+        }
+        for x in self.history.iter() {
+            text += "x";
+            for &value in x.iter() {
+                text += &format!(" {}", bits(value));
+            }
+            text += "\n";
+        }
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        fs::write(&partial, text)?;
+        fs::rename(partial, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid("Not a checkpoint file".to_string()));
+        }
+        let mut checkpoint = Checkpoint {
+            step: 0,
+            t: 0.,
+            h: 0.,
+            history: vec![],
+            controller: None,
+            stats: SolverStats::default(),
+        };
+        for line in lines {
+            let mut words = line.split_whitespace();
+            let key = words.next().unwrap_or_default();
+            let words: Vec<&str> = words.collect();
+            let single = || {
+                words
+                    .first()
+                    .copied()
+                    .ok_or_else(|| invalid(format!("{key} without a value")))
+            };
+            match key {
+                "step" => checkpoint.step = parse_usize(single()?)?,
+                "t" => checkpoint.t = parse_bits(single()?)?,
+                "h" => checkpoint.h = parse_bits(single()?)?,
+                "stats" => {
+                    let stats = &mut checkpoint.stats;
+                    for word in words.iter() {
+                        let (key, value) = word
+                            .split_once('=')
+                            .ok_or_else(|| invalid(format!("{word} is no counter")))?;
+                        let counter = match key {
+                            "steps" => &mut stats.steps,
+                            "rejected_steps" => &mut stats.rejected_steps,
+                            "rhs_evaluations" => &mut stats.rhs_evaluations,
+                            "jacobian_evaluations" => &mut stats.jacobian_evaluations,
+                            "lu_factorizations" => &mut stats.lu_factorizations,
+                            "newton_iterations" => &mut stats.newton_iterations,
+                            "newton_failures" => &mut stats.newton_failures,
+                            _ => return Err(invalid(format!("Unknown counter {key}"))),
+                        };
+                        *counter = parse_usize(value)?;
+                    }
+                }
+                "controller" => {
+                    let values = words
+                        .iter()
+                        .map(|word| parse_bits(word))
+                        .collect::<Result<Vec<_>>>()?;
+                    let [atol, rtol, safety, fac_min, fac_max, h_min, h_max, err_prev] = values[..]
+                    else {
+                        return Err(invalid("The controller needs 8 values".to_string()));
+                    };
+                    checkpoint.controller = Some(StepController {
+                        atol,
+                        rtol,
+                        safety,
+                        fac_min,
+                        fac_max,
+                        h_min,
+                        h_max,
+                        err_prev,
+                    });
+                }
+                "x" => checkpoint.history.push(
+                    words
+                        .iter()
+                        .map(|word| parse_bits(word))
+                        .collect::<Result<_>>()?,
+                ),
+                "" => {}
+                _ => return Err(invalid(format!("Unknown entry {key}"))),
+            }
+        }
+        if checkpoint.history.is_empty() {
+            return Err(invalid("The checkpoint has no state".to_string()));
+        }
+        Ok(checkpoint)
+    }
+
+    /// The most recent state.
+    pub fn state(&self) -> &Array1<f64> {
+        self.history.last().expect("A checkpoint has a state")
+    }
+}
+
+/// Where and how often a run writes its [Checkpoint].
+#[derive(Debug, Clone)]
+pub(crate) struct Checkpoints {
+    path: PathBuf,
+    every: usize,
+}
+
+impl Checkpoints {
+    pub(crate) fn new(path: impl AsRef<Path>, every: usize) -> Self {
+        Checkpoints {
+            path: path.as_ref().to_path_buf(),
+            every: every.max(1),
+        }
+    }
+
+    pub(crate) fn due(&self, step: usize) -> bool {
+        step.is_multiple_of(self.every)
+    }
+
+    /// Writes the checkpoint after `step` accepted steps if one is due.
+    ///
+    /// A run which cannot write its checkpoint ends with the returned termination.
+    pub(crate) fn write_due(
+        checkpoints: Option<&Self>,
+        step: usize,
+        checkpoint: impl FnOnce() -> Checkpoint,
+    ) -> Option<Termination> {
+        let checkpoints = checkpoints.filter(|c| c.due(step))?;
+        let checkpoint = checkpoint();
+        checkpoint
+            .save(&checkpoints.path)
+            .err()
+            .map(|e| Termination::Failed {
+                t: checkpoint.t,
+                reason: format!("Checkpoint not written: {e}"),
+            })
+    }
+}
+
+/// Solvers which write their state at intervals and continue from it.
+pub trait Restart {
+    /// Writes a [Checkpoint] to `path` after every `every` accepted steps, replacing the previous one.
+    fn set_checkpoints(&mut self, path: impl AsRef<Path>, every: usize) -> &mut Self;
+    /// Continues from `checkpoint` instead of the initial values, the [crate::ode::Solution] starts at its state.
+    ///
+    /// The final time and the scheme have to be set up as for the interrupted run.
+    fn resume(&mut self, checkpoint: Checkpoint) -> &mut Self;
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, s, Array1, ArrayView1};
+
+    use crate::{
+        ad::*,
+        ode::{solver::*, *},
+        progress::*,
+    };
+
+    fn pendulum(_t: f64, q: ArrayView1<f64>, _v: ArrayView1<f64>) -> Array1<f64> {
+        q.mapv(|q| -q.sin())
+    }
+
+    fn pendulum_ad(_t: f64, q: ArrayView1<AD>, _v: ArrayView1<AD>, a: &mut Array1<AD>) {
+        a[0] = -q[0].sin();
+    }
+
+    fn adaptive() -> OdeSecondOrder<impl SecondOrder> {
+        let scheme = RungeKuttaNystrom::new(NystromTableau::rkn54(), pendulum);
+        let mut ode = Ode::second_order(scheme, array![1.0], array![0.0]);
+        ode.set_step_size(0.01)
+            .set_t(10.0)
+            .set_tolerances(1e-9, 1e-9)
+            .set_with_progress(false);
+        ode
+    }
+
+    /// Generalized-α carries the acceleration from step to step.
+    fn generalized_alpha() -> OdeSecondOrder<impl SecondOrder> {
+        let scheme = GeneralizedAlpha::new(0.5, pendulum_ad);
+        let mut ode = Ode::second_order(scheme, array![1.0], array![0.0]);
+        ode.set_step_size(0.01).set_t(10.0).set_with_progress(false);
+        ode
+    }
+
+    fn resume_bit_for_bit<Scheme: SecondOrder>(
+        solver: impl Fn() -> OdeSecondOrder<Scheme>,
+        name: &str,
+    ) {
+        let path = std::env::temp_dir().join(format!("ndarray-ode-{name}.checkpoint"));
+        let complete = solver().run();
+
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        let mut interrupted = solver();
+        interrupted
+            .set_checkpoints(&path, 5)
+            .set_cancellation(token)
+            .set_progress(CallbackProgress::new(move |t, _| {
+                if t > 4.0 {
+                    canceller.cancel()
+                }
+            }));
+        let interrupted = interrupted.run();
+        assert!(matches!(
+            interrupted.termination,
+            Termination::Cancelled { .. }
+        ));
+
+        let checkpoint = Checkpoint::load(&path).unwrap();
+        assert_eq!(checkpoint.step % 5, 0);
+        let mut resumed = solver();
+        resumed.resume(checkpoint.clone());
+        let resumed = resumed.run();
+
+        let first = checkpoint.step;
+        assert_eq!(resumed.time[..], complete.time[first..]);
+        assert_eq!(resumed.states, complete.states.slice(s![first.., ..]));
+        assert_eq!(resumed.stats, complete.stats);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn resumed_run_is_bit_for_bit_identical() {
+        resume_bit_for_bit(adaptive, "resumed-run");
+        resume_bit_for_bit(generalized_alpha, "resumed-generalized-alpha");
+    }
+}
@@ -0,0 +1,80 @@
+use ndarray::ArrayView1;
+
+/// Step size control from an embedded error estimate.
+///
+/// The error is measured in the usual weighted root mean square norm with `atol + rtol * |x|`,
+/// a step is accepted if this norm is below one. New step sizes are proposed by a PI controller
+/// (Gustafsson), which damps the oscillations of the classical controller along stability boundaries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepController {
+    pub atol: f64,
+    pub rtol: f64,
+    pub safety: f64,
+    pub fac_min: f64,
+    pub fac_max: f64,
+    pub h_min: f64,
+    pub h_max: f64,
+    /// Error norm of the last accepted step.
+    pub err_prev: f64,
+}
+
+impl StepController {
+    pub fn new(atol: f64, rtol: f64) -> Self {
+        StepController {
+            atol,
+            rtol,
+            safety: 0.9,
+            fac_min: 0.2,
+            fac_max: 5.0,
+            h_min: 1e-14,
+            h_max: f64::INFINITY,
+            err_prev: 1e-4,
+        }
+    }
+
+    /// Weighted root mean square norm of `err` for a step from `x0` to `x1`.
+    pub fn error_norm(
+        &self,
+        x0: ArrayView1<f64>,
+        x1: ArrayView1<f64>,
+        err: ArrayView1<f64>,
+    ) -> f64 {
+        let sum: f64 = x0
+            .iter()
+            .zip(x1.iter())
+            .zip(err.iter())
+            .map(|((&x0, &x1), &e)| {
+                let scale = self.atol + self.rtol * x0.abs().max(x1.abs());
+                (e / scale).powi(2)
+            })
+            .sum();
+        (sum / err.len().max(1) as f64).sqrt()
+    }
+
+    /// Proposes the next step size after a step of size `h` with error norm `err`.
+    ///
+    /// `order` is the order of the lower order solution of the embedded pair.
+    /// Accepted steps update the memory of the controller, rejected ones only shrink the step.
+    /// An error which is not finite shrinks the step as much as possible.
+    pub fn propose(&mut self, h: f64, err: f64, order: usize) -> f64 {
+        if !err.is_finite() {
+            return (h * self.fac_min).clamp(self.h_min, self.h_max);
+        }
+        let k = (order + 1) as f64;
+        let err = err.max(1e-10);
+        let fac = if err <= 1. {
+            let fac = self.safety * err.powf(-0.7 / k) * self.err_prev.powf(0.4 / k);
+            self.err_prev = err;
+            fac.clamp(self.fac_min, self.fac_max)
+        } else {
+            (self.safety * err.powf(-1. / k)).clamp(self.fac_min, 1.)
+        };
+        (h * fac).clamp(self.h_min, self.h_max)
+    }
+}
+
+impl Default for StepController {
+    fn default() -> Self {
+        StepController::new(1e-6, 1e-3)
+    }
+}
@@ -0,0 +1,157 @@
+use ndarray::{concatenate, Array1, ArrayView1, Axis};
+
+use crate::{ad::*, ensemble::max_drift, ode::Solution};
+
+/// A Hamiltonian system given only by its energy `H(q, p)` with `dof` positions `q` and momenta `p`.
+///
+/// The states are `x = (q, p)` and the canonical equations `q' = ∂H/∂p`, `p' = -∂H/∂q` are derived with
+/// [gradient], so [Hamiltonian::flow] can be handed to any scheme taking a flow on AD, e.g. the symplectic
+/// [crate::ode::solver::SymplecticEuler], [crate::ode::solver::ImplicitMidpoint] or
+/// [crate::ode::solver::GaussLegendre], and [Hamiltonian::flow_f64] to the explicit ones.
+///
+/// # Examples
+/// ```
+/// use ndarray::{array, ArrayView1};
+/// use ndarray_ode::prelude::*;
+/// fn main() {
+///     let kepler = Hamiltonian::new(2, kepler);
+///     let h = 0.01;
+///     let mut ode = Ode::implicit(SymplecticEuler::new(h, kepler.flow()), array![1., 0., 0., 1.]);
+///     ode.set_step_size(h).set_t(1.0).set_with_progress(false);
+///     let solution = ode.run();
+///     assert!(kepler.energy_drift(&solution) < 1e-2);
+/// }
+/// fn kepler(q: ArrayView1<AD>, p: ArrayView1<AD>) -> AD {
+///     0.5 * (p[0] * p[0] + p[1] * p[1]) - 1. / (q[0] * q[0] + q[1] * q[1]).sqrt()
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Hamiltonian<Energy>
+where
+    Energy: Fn(ArrayView1<AD>, ArrayView1<AD>) -> AD,
+{
+    dof: usize,
+    energy: Energy,
+}
+
+impl<Energy> Hamiltonian<Energy>
+where
+    Energy: Fn(ArrayView1<AD>, ArrayView1<AD>) -> AD,
+{
+    pub fn new(dof: usize, energy: Energy) -> Self {
+        Hamiltonian { dof, energy }
+    }
+
+    pub fn dof(&self) -> usize {
+        self.dof
+    }
+
+    /// The state `x = (q, p)`.
+    pub fn state(&self, q: ArrayView1<f64>, p: ArrayView1<f64>) -> Array1<f64> {
+        assert!(
+            q.len() == self.dof && p.len() == self.dof,
+            "Positions and momenta have one entry per degree of freedom"
+        );
+        concatenate![Axis(0), q, p]
+    }
+
+    /// `H` at the state `x = (q, p)`.
+    pub fn hamiltonian(&self, x: ArrayView1<AD>) -> AD {
+        debug_assert_eq!(x.len(), 2 * self.dof);
+        let (q, p) = x.split_at(Axis(0), self.dof);
+        (self.energy)(q, p)
+    }
+
+    /// The canonical equations `(∂H/∂p, -∂H/∂q)`, with derivatives along the first derivatives of `x`
+    /// so that implicit schemes get their Jacobians.
+    pub fn canonical(&self, x: ArrayView1<AD>, update: &mut Array1<AD>) {
+        let n = self.dof;
+        let g = gradient(|x: ArrayView1<AD>| self.hamiltonian(x), x);
+        for i in 0..n {
+            update[i] = g[n + i];
+            update[n + i] = -g[i];
+        }
+    }
+
+    pub fn flow(&self) -> impl Fn(ArrayView1<AD>, &mut Array1<AD>) + Sync + '_
+    where
+        Energy: Sync,
+    {
+        move |x, update| self.canonical(x, update)
+    }
+
+    pub fn flow_f64(&self) -> impl Fn(ArrayView1<f64>) -> Array1<f64> + Sync + '_
+    where
+        Energy: Sync,
+    {
+        move |x| eval_flow(|x, update: &mut Array1<AD>| self.canonical(x, update), x)
+    }
+
+    pub fn energy(&self, x: ArrayView1<f64>) -> f64 {
+        self.hamiltonian(x.to_ad().view()).x()
+    }
+
+    /// The energy at each step of a solution.
+    pub fn energies(&self, solution: &Solution) -> Array1<f64> {
+        solution
+            .states
+            .rows()
+            .into_iter()
+            .map(|x| self.energy(x))
+            .collect()
+    }
+
+    /// The largest deviation of the energy from its initial value.
+    pub fn energy_drift(&self, solution: &Solution) -> f64 {
+        max_drift(solution, |x| self.energy(x))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, ArrayView1};
+
+    use crate::{
+        ad::*,
+        ensemble::max_drift,
+        ode::{solver::*, *},
+    };
+
+    fn kepler(q: ArrayView1<AD>, p: ArrayView1<AD>) -> AD {
+        0.5 * (p[0] * p[0] + p[1] * p[1]) - 1. / (q[0] * q[0] + q[1] * q[1]).sqrt()
+    }
+
+    #[test]
+    fn canonical_equations_of_kepler() {
+        let kepler = Hamiltonian::new(2, kepler);
+        let x = array![0.6, -0.8, 0.3, 0.9];
+        let f = kepler.flow_f64()(x.view());
+        // q' = p and p' = -q / |q|³ with |q| = 1
+        let derived = array![0.3, 0.9, -0.6, 0.8];
+        assert!((&f - &derived).iter().all(|e| e.abs() < 1e-14));
+
+        let h = 0.01;
+        let x0 = kepler.state(array![1., 0.].view(), array![0., 1.2].view());
+        let angular_momentum = |x: ArrayView1<f64>| x[0] * x[3] - x[1] * x[2];
+
+        let mut ode = Ode::implicit(SymplecticEuler::new(h, kepler.flow()), x0.clone());
+        ode.set_step_size(h).set_t(10.0).set_with_progress(false);
+        let solution = ode.run();
+        assert!(kepler.energy_drift(&solution) < 5e-3);
+        assert!(max_drift(&solution, angular_momentum) < 1e-12);
+
+        let mut ode = Ode::implicit(ImplicitMidpoint::new(h, kepler.flow()), x0.clone());
+        ode.set_step_size(h).set_t(10.0).set_with_progress(false);
+        let midpoint = ode.run();
+        assert!(kepler.energy_drift(&midpoint) < 1e-4);
+        assert!(max_drift(&midpoint, angular_momentum) < 1e-12);
+        assert_eq!(midpoint.stats.newton_failures, 0);
+
+        let mut ode = Ode::explicit(ExplicitEuler::new(h, kepler.flow_f64()), x0);
+        ode.set_step_size(h).set_t(10.0).set_with_progress(false);
+        let explicit = ode.run();
+        let energies = kepler.energies(&explicit);
+        assert_eq!(energies.len(), explicit.len());
+        assert!(kepler.energy_drift(&explicit) > 10. * kepler.energy_drift(&solution));
+    }
+}
@@ -0,0 +1,145 @@
+use ndarray::{concatenate, s, Array1, ArrayView1, Axis};
+use ndarray_linalg::Solve;
+
+use crate::{ad::*, ensemble::max_drift, ode::Solution};
+
+/// A mechanical system given only by its Lagrangian `L(q, q')` with `dof` positions `q` and velocities `q'`.
+///
+/// The Euler-Lagrange equations `d/dt ∂L/∂q' = ∂L/∂q` are solved for the acceleration
+/// `q'' = (∂²L/∂q'²)^-1 (∂L/∂q - ∂²L/∂q'∂q q')` with the [gradient] and the [hessian] of `L`, so it has to be
+/// regular. [Lagrangian::acceleration] can be handed to the second order schemes and [Lagrangian::flow_f64]
+/// on the states `x = (q, q')` to the explicit ones. The discrete counterpart is the
+/// [crate::ode::solver::VariationalIntegrator].
+///
+/// # Examples
+/// ```
+/// use ndarray::{array, ArrayView1};
+/// use ndarray_ode::prelude::*;
+/// fn main() {
+///     let pendulum = Lagrangian::new(1, pendulum);
+///     let a = pendulum.acceleration(array![0.5].view(), array![0.].view());
+///     assert!((a[0] + 0.5f64.sin()).abs() < 1e-15);
+/// }
+/// fn pendulum(q: ArrayView1<AD>, v: ArrayView1<AD>) -> AD {
+///     0.5 * v[0] * v[0] + q[0].cos()
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Lagrangian<L>
+where
+    L: Fn(ArrayView1<AD>, ArrayView1<AD>) -> AD,
+{
+    dof: usize,
+    lagrangian: L,
+}
+
+impl<L> Lagrangian<L>
+where
+    L: Fn(ArrayView1<AD>, ArrayView1<AD>) -> AD,
+{
+    pub fn new(dof: usize, lagrangian: L) -> Self {
+        Lagrangian { dof, lagrangian }
+    }
+
+    pub fn dof(&self) -> usize {
+        self.dof
+    }
+
+    /// The state `x = (q, q')`.
+    pub fn state(&self, q: ArrayView1<f64>, v: ArrayView1<f64>) -> Array1<f64> {
+        assert!(
+            q.len() == self.dof && v.len() == self.dof,
+            "Positions and velocities have one entry per degree of freedom"
+        );
+        concatenate![Axis(0), q, v]
+    }
+
+    /// `L` at the positions `q` and velocities `v`.
+    pub fn lagrangian(&self, q: ArrayView1<AD>, v: ArrayView1<AD>) -> AD {
+        (self.lagrangian)(q, v)
+    }
+
+    /// `L` at the state `x = (q, q')`.
+    fn at(&self, x: ArrayView1<AD>) -> AD {
+        let (q, v) = x.split_at(Axis(0), self.dof);
+        (self.lagrangian)(q, v)
+    }
+
+    /// The conjugate momentum `p = ∂L/∂q'`.
+    pub fn momentum(&self, q: ArrayView1<f64>, v: ArrayView1<f64>) -> Array1<f64> {
+        let x = self.state(q, v).to_ad();
+        let g = gradient(|x: ArrayView1<AD>| self.at(x), x.view());
+        g.slice(s![self.dof..]).to_f64()
+    }
+
+    pub fn acceleration(&self, q: ArrayView1<f64>, v: ArrayView1<f64>) -> Array1<f64> {
+        let n = self.dof;
+        let x = self.state(q, v);
+        let g = gradient(|x: ArrayView1<AD>| self.at(x), x.to_ad().view()).to_f64();
+        let h = hessian(|x: ArrayView1<AD>| self.at(x), x.view());
+        let force = &g.slice(s![..n]) - &h.slice(s![n.., ..n]).dot(&v);
+        h.slice(s![n.., n..])
+            .solve(&force)
+            .expect("The Lagrangian is regular, its Hessian in the velocities is invertible")
+    }
+
+    /// The Euler-Lagrange equations as the first order system `(q', q'')`.
+    pub fn flow_f64(&self) -> impl Fn(ArrayView1<f64>) -> Array1<f64> + Sync + '_
+    where
+        L: Sync,
+    {
+        move |x| {
+            let (q, v) = x.split_at(Axis(0), self.dof);
+            concatenate![Axis(0), v, self.acceleration(q, v)]
+        }
+    }
+
+    /// The energy `p q' - L`, conserved as `L` does not depend on the time.
+    pub fn energy(&self, x: ArrayView1<f64>) -> f64 {
+        let (q, v) = x.split_at(Axis(0), self.dof);
+        self.momentum(q, v).dot(&v) - self.at(x.to_ad().view()).x()
+    }
+
+    /// The largest deviation of the energy from its initial value.
+    pub fn energy_drift(&self, solution: &Solution) -> f64 {
+        max_drift(solution, |x| self.energy(x))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, ArrayView1};
+
+    use crate::{
+        ad::*,
+        ode::{solver::*, *},
+    };
+
+    /// Kepler problem in polar coordinates `(r, φ)`, whose mass matrix depends on `r`.
+    fn polar(q: ArrayView1<AD>, v: ArrayView1<AD>) -> AD {
+        0.5 * (v[0] * v[0] + q[0] * q[0] * v[1] * v[1]) + 1. / q[0]
+    }
+
+    #[test]
+    fn euler_lagrange_equations() {
+        let polar = Lagrangian::new(2, polar);
+        let (r, dr, dφ) = (2., 0.5, 0.7);
+        let q = array![r, 0.3];
+        let v = array![dr, dφ];
+        let a = polar.acceleration(q.view(), v.view());
+        // r'' = r φ'² - 1 / r² and φ'' = -2 r' φ' / r
+        let derived = array![r * dφ * dφ - 1. / (r * r), -2. * dr * dφ / r];
+        assert!((&a - &derived).iter().all(|e| e.abs() < 1e-14), "{a}");
+        assert!((polar.momentum(q.view(), v.view())[1] - r * r * dφ).abs() < 1e-14);
+
+        let h = 0.01;
+        let x0 = polar.state(array![1., 0.].view(), array![0., 1.2].view());
+        let mut ode = Ode::explicit(
+            RungeKutta::new(h, ButcherTableau::rk4(), polar.flow_f64()),
+            x0,
+        );
+        ode.set_step_size(h).set_t(10.0).set_with_progress(false);
+        let solution = ode.run();
+        assert!(polar.energy_drift(&solution) < 1e-7);
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::ode::Termination;
+
+/// A flag shared between clones, e.g. with a job scheduler, to stop a run from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every run holding a clone of this token to stop after its current step.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Reasons to end a run before the final time, checked between two steps.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    token: Option<CancellationToken>,
+    time_limit: Option<Duration>,
+    started: Option<Instant>,
+}
+
+impl Limits {
+    pub fn set_cancellation(&mut self, token: CancellationToken) -> &mut Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Wall-clock budget of the run, measured from [Limits::start].
+    pub fn set_time_limit(&mut self, limit: Duration) -> &mut Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    /// Starts the wall clock, called by the solvers before the first step.
+    pub fn start(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    /// The termination of a run which reached the simulated time `t`, if it has to stop now.
+    pub fn check(&self, t: f64) -> Option<Termination> {
+        if self
+            .token
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled())
+        {
+            return Some(Termination::Cancelled { t });
+        }
+        match (self.time_limit, self.started) {
+            (Some(limit), Some(started)) if started.elapsed() >= limit => {
+                Some(Termination::TimedOut { t })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{
+        ode::{solver::*, *},
+        progress::*,
+    };
+
+    fn decay(x: ArrayView1<f64>) -> Array1<f64> {
+        -&x
+    }
+
+    #[test]
+    fn cancelled_run_returns_partial_solution() {
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        let mut ode = Ode::explicit(ExplicitEuler::new(0.1, decay), array![1.0]);
+        ode.set_step_size(0.1)
+            .set_t(10.0)
+            .set_cancellation(token)
+            .set_progress(CallbackProgress::new(move |t, _| {
+                if t >= 1.0 {
+                    canceller.cancel()
+                }
+            }));
+        let solution = ode.run();
+
+        let t = *solution.time.last().unwrap();
+        assert!((1.0..1.1).contains(&t));
+        assert_eq!(solution.termination, Termination::Cancelled { t });
+        assert_eq!(solution.stats.steps, solution.len() - 1);
+
+        let mut ode = Ode::explicit(ExplicitEuler::new(0.1, decay), array![1.0]);
+        ode.set_with_progress(false).set_time_limit(Duration::ZERO);
+        let solution = ode.run();
+        assert_eq!(solution.len(), 1);
+        assert_eq!(solution.termination, Termination::TimedOut { t: 0.0 });
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{ode::*, progress::*};
+use ndarray::*;
+use std::path::Path;
+
+/// Solver for matrix valued problems `Y' = F(t, Y)` with fixed timesteps `h`, e.g. `Y' = A(t) Y` with a
+/// [MatrixScheme] that keeps `Y` on its Lie group.
+///
+/// The states of the [Solution] are the rows of `Y` one after another, labelled `y{i}_{j}`, so the
+/// matrix at step `k` is `solution.states.row(k).into_shape(y0.dim())`.
+#[allow(non_snake_case)]
+pub struct OdeMatrix<Scheme>
+where
+    Scheme: MatrixScheme,
+{
+    scheme: Scheme,
+    initial: Array2<f64>,
+    h: f64,
+    T: f64,
+    progress: Box<dyn Progress>,
+    limits: Limits,
+    checkpoints: Option<Checkpoints>,
+    resume: Option<Checkpoint>,
+}
+
+impl<Scheme> OdeMatrix<Scheme>
+where
+    Scheme: MatrixScheme,
+{
+    pub fn new(scheme: Scheme, initial: Array2<f64>) -> Self {
+        OdeMatrix {
+            scheme,
+            initial,
+            h: 0.1,
+            T: 1.0,
+            progress: Box::<TqdmProgress>::default(),
+            limits: Limits::default(),
+            checkpoints: None,
+            resume: None,
+        }
+    }
+
+    fn flatten(y: &Array2<f64>) -> Array1<f64> {
+        y.iter().copied().collect()
+    }
+}
+
+impl<Scheme> ODE<Scheme> for OdeMatrix<Scheme>
+where
+    Scheme: MatrixScheme,
+{
+    fn set_step_size(&mut self, h: f64) -> &mut Self {
+        self.h = h;
+        self
+    }
+
+    #[allow(non_snake_case)]
+    fn set_t(&mut self, T: f64) -> &mut Self {
+        self.T = T;
+        self
+    }
+
+    fn run(mut self) -> Solution {
+        let dim = self.initial.dim();
+        let n: f64 = self.T / self.h;
+        let (first, mut y, mut stats) = match self.resume.take() {
+            Some(checkpoint) => {
+                let y = checkpoint
+                    .state()
+                    .clone()
+                    .into_shape(dim)
+                    .expect("The checkpoint does not match the problem");
+                (checkpoint.step, y, checkpoint.stats)
+            }
+            None => (0, self.initial.clone(), SolverStats::default()),
+        };
+        let n = (n.floor() as usize).max(first + 1);
+        let mut states = Array2::zeros((n - first, dim.0 * dim.1));
+        states.row_mut(0).assign(&Self::flatten(&y));
+        let mut time: Vec<f64> = (first..n).map(|t| t as f64 * self.h).collect();
+
+        let mut termination = Termination::Completed;
+        let mut rows = n - first;
+        self.progress.start(time[0], self.T);
+        self.limits.start();
+        for t in first + 1..n {
+            let row = t - first;
+            if let Some(stop) = self.limits.check(time[row - 1]) {
+                termination = stop;
+                rows = row;
+                break;
+            }
+            match self
+                .scheme
+                .next(time[row - 1], y.view(), self.h, &mut stats)
+            {
+                Ok(y1) => y = y1,
+                Err(e) => {
+                    termination = Termination::Failed {
+                        t: time[row - 1],
+                        reason: e.to_string(),
+                    };
+                    rows = row;
+                    break;
+                }
+            }
+            stats.steps += 1;
+            states.row_mut(row).assign(&Self::flatten(&y));
+            self.progress.update(time[row]);
+            if let Some(failed) =
+                Checkpoints::write_due(self.checkpoints.as_ref(), t, || Checkpoint {
+                    step: t,
+                    t: time[row],
+                    h: self.h,
+                    history: vec![Self::flatten(&y)],
+                    controller: None,
+                    stats,
+                })
+            {
+                termination = failed;
+                rows = row + 1;
+                break;
+            }
+        }
+        self.progress.finish();
+        time.truncate(rows);
+        let states = states.slice(s![..rows, ..]).to_owned();
+        let mut solution = Solution::new(time, states, stats);
+        solution.termination = termination;
+        solution.labels = (0..dim.0)
+            .flat_map(|i| (0..dim.1).map(move |j| format!("y{i}_{j}")))
+            .collect();
+        solution
+    }
+
+    fn set_progress<P: Progress + 'static>(&mut self, progress: P) -> &mut Self {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    fn limits_mut(&mut self) -> &mut Limits {
+        &mut self.limits
+    }
+}
+
+impl<Scheme> Restart for OdeMatrix<Scheme>
+where
+    Scheme: MatrixScheme,
+{
+    fn set_checkpoints(&mut self, path: impl AsRef<Path>, every: usize) -> &mut Self {
+        self.checkpoints = Some(Checkpoints::new(path, every));
+        self
+    }
+
+    /// The state of the checkpoint holds the rows of the matrix one after another.
+    fn resume(&mut self, checkpoint: Checkpoint) -> &mut Self {
+        self.h = checkpoint.h;
+        self.resume = Some(checkpoint);
+        self
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    ode::{solver::ButcherTableau, *},
+    progress::*,
+};
+use ndarray::*;
+
+/// Solver for `x' = f(x)` with a [Multistep] method, which only needs the initial value.
+///
+/// The first `k - 1` steps, for which the history is too short, are taken by an explicit Runge-Kutta method,
+/// by default the Dormand-Prince method of order five. Its local errors enter only these few times, so it
+/// keeps the order of methods up to order six.
+///
+/// By default it uses fixed timesteps `h`. After [OdeMultistep::set_tolerances] the step size is adapted to
+/// the error estimate of the method, and the coefficients follow the unequal steps of the history.
+#[allow(non_snake_case)]
+pub struct OdeMultistep<Scheme>
+where
+    Scheme: Multistep,
+{
+    scheme: Scheme,
+    initial: Array1<f64>,
+    h: f64,
+    T: f64,
+    startup: ButcherTableau,
+    controller: Option<StepController>,
+    progress: Box<dyn Progress>,
+    limits: Limits,
+}
+
+impl<Scheme> OdeMultistep<Scheme>
+where
+    Scheme: Multistep,
+{
+    pub fn new(scheme: Scheme, initial: Array1<f64>) -> Self {
+        OdeMultistep {
+            scheme,
+            initial,
+            h: 0.1,
+            T: 1.0,
+            startup: ButcherTableau::dormand_prince(),
+            controller: None,
+            progress: Box::<TqdmProgress>::default(),
+            limits: Limits::default(),
+        }
+    }
+
+    /// The Runge-Kutta method of the first steps, adaptive steps need an embedded method or the run fails.
+    pub fn set_startup(&mut self, tableau: ButcherTableau) -> &mut Self {
+        self.startup = tableau;
+        self
+    }
+
+    /// Switches to adaptive steps, which needs a method with an error estimate, e.g. no Adams-Bashforth
+    /// method, otherwise the run ends with [Termination::Failed] at the first step without one.
+    pub fn set_tolerances(&mut self, atol: f64, rtol: f64) -> &mut Self {
+        self.controller = Some(StepController::new(atol, rtol));
+        self
+    }
+
+    /// A step of the Runge-Kutta method from the newest state of the history.
+    fn startup_step(
+        &self,
+        history: &StepHistory,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> MultistepStep {
+        let x = history.states[history.len() - 1].view();
+        let k = self.startup.stage_slopes(|_, x| self.scheme.slope(x), x, h);
+        let x1 = self.startup.solution(x, &k, h);
+        let slope = self.scheme.slope(x1.view());
+        stats.rhs_evaluations += self.startup.stages() + 1;
+        MultistepStep {
+            error: self.startup.error_estimate(&k, h),
+            x: x1,
+            slope,
+        }
+    }
+}
+
+impl<Scheme> ODE<Scheme> for OdeMultistep<Scheme>
+where
+    Scheme: Multistep,
+{
+    fn set_step_size(&mut self, h: f64) -> &mut Self {
+        self.h = h;
+        self
+    }
+
+    #[allow(non_snake_case)]
+    fn set_t(&mut self, T: f64) -> &mut Self {
+        self.T = T;
+        self
+    }
+
+    #[allow(non_snake_case)]
+    fn run(mut self) -> Solution {
+        let T = self.T;
+        let ɛ = 1e-12 * T.abs().max(1.);
+        let order = self.scheme.order().min(self.scheme.embedded_order()).max(1);
+        let k = self.scheme.steps();
+        let mut stats = SolverStats::default();
+        let (mut t, mut x) = (0.0, self.initial.clone());
+        let mut history = StepHistory::default();
+        history.push(t, x.clone(), self.scheme.slope(x.view()), k);
+        stats.rhs_evaluations += 1;
+        let mut states = Array2::zeros((0, x.len()));
+        states.push_row(x.view()).unwrap();
+        let mut time = vec![t];
+
+        let mut h = self.h;
+        let mut termination = Termination::Completed;
+        self.progress.start(t, T);
+        self.limits.start();
+        while t < T - ɛ {
+            if let Some(stop) = self.limits.check(t) {
+                termination = stop;
+                break;
+            }
+            let h_step = h.min(T - t);
+            let step = if history.len() < k {
+                Ok(self.startup_step(&history, h_step, &mut stats))
+            } else {
+                self.scheme.next(&history, h_step, &mut stats)
+            };
+            let step = match (step, &self.controller) {
+                (Ok(step), _) => step,
+                (Err(_), Some(controller)) if h_step > controller.h_min => {
+                    stats.rejected_steps += 1;
+                    h = (h_step / 4.).max(controller.h_min);
+                    continue;
+                }
+                (Err(e), _) => {
+                    termination = Termination::Failed {
+                        t,
+                        reason: e.to_string(),
+                    };
+                    break;
+                }
+            };
+            if let Some(controller) = self.controller.as_mut() {
+                let Some(err) = step.error.as_ref() else {
+                    termination = Termination::Failed {
+                        t,
+                        reason: "Adaptive steps need a method with an error estimate".into(),
+                    };
+                    break;
+                };
+                let err = controller.error_norm(x.view(), step.x.view(), err.view());
+                h = controller.propose(h_step, err, order);
+                if err > 1. || err.is_nan() {
+                    if h_step > controller.h_min {
+                        stats.rejected_steps += 1;
+                        continue;
+                    }
+                    termination = Termination::Failed {
+                        t,
+                        reason: format!(
+                            "The error {err} exceeds the tolerances at the smallest step size"
+                        ),
+                    };
+                    break;
+                }
+            }
+            stats.steps += 1;
+            t += h_step;
+            x = step.x.clone();
+            history.push(t, step.x, step.slope, k);
+            time.push(t);
+            states.push_row(x.view()).unwrap();
+            self.progress.update(t);
+        }
+        self.progress.finish();
+        let mut solution = Solution::new(time, states, stats);
+        solution.termination = termination;
+        solution
+    }
+
+    fn set_progress<P: Progress + 'static>(&mut self, progress: P) -> &mut Self {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    fn limits_mut(&mut self) -> &mut Limits {
+        &mut self.limits
+    }
+}
@@ -1,11 +1,11 @@
-use crate::{ad::*, ode::*};
+use crate::{ad::*, ode::*, progress::*};
 use ndarray::*;
-use tqdm::tqdm;
+use std::path::Path;
 
 /// This is the most classical ode solver.
 /// From the last known step it extrapolates with a residual function, which can be for example any Runge-Kutta-scheme, to the next time step.
 /// Currently it only supports fixed timesteps `h`.
-
+/// A step whose Newton iteration does not converge ends the run with [Termination::Failed].
 #[allow(non_snake_case)]
 pub struct OdeIm<Scheme>
 where
@@ -16,7 +16,11 @@ where
     h: f64,
     T: f64,
     ɛ: f64,
-    with_progress: bool,
+    progress: Box<dyn Progress>,
+    limits: Limits,
+    checkpoints: Option<Checkpoints>,
+    resume: Option<Checkpoint>,
+    stats: SolverStats,
 }
 impl<Scheme> OdeIm<Scheme>
 where
@@ -30,26 +34,12 @@ where
     #[allow(non_snake_case)]
     fn execute(
         &mut self,
-        t: usize,
         x0: Array1<AD>,
-        result: &mut Array2<f64>,
-        time: &mut Vec<f64>,
         J: &mut Array2<f64>,
         slope_buffer: &mut Array1<AD>,
-    ) -> Array1<AD> {
+    ) -> ndarray_linalg::error::Result<Array1<AD>> {
         self.scheme.update(x0.clone());
-        match newton(f64::EPSILON, &self.scheme, x0.clone(), J, slope_buffer) {
-            Ok(x1) => {
-                result
-                    .row_mut(t)
-                    .iter_mut()
-                    .zip(x1.iter())
-                    .for_each(|(x, &y)| *x = y.x());
-                time[t] = t as f64 * self.h;
-                x1
-            }
-            Err(_) => todo!(),
-        }
+        newton_with_stats(self.ɛ, &self.scheme, x0, J, slope_buffer, &mut self.stats)
     }
 }
 
@@ -69,40 +59,100 @@ where
     }
 
     #[allow(non_snake_case)]
-    fn run(mut self) -> (Vec<f64>, Array2<f64>) {
+    fn run(mut self) -> Solution {
         let n: f64 = self.T / self.h;
-        let n = n.floor() as usize;
-        let mut x0 = self.initial.clone();
+        let (first, mut x0) = match self.resume.take() {
+            Some(checkpoint) => {
+                self.stats = checkpoint.stats;
+                (checkpoint.step, checkpoint.state().to_ad())
+            }
+            None => (0, self.initial.clone()),
+        };
+        let n = (n.floor() as usize).max(first + 1);
 
-        let mut result: Array2<f64> = Array::zeros((n, x0.len()));
+        let mut result: Array2<f64> = Array::zeros((n - first, x0.len()));
         result
             .row_mut(0)
             .iter_mut()
-            .zip(self.initial.to_f64().iter())
+            .zip(x0.to_f64().iter())
             .for_each(|(x, &y)| *x = y); //(self.initial.to_f64().view()).unwrap();
-        let mut time = vec![0.0; n];
+        let mut time = vec![first as f64 * self.h; n - first];
 
         let l = x0.len();
         let mut J = Array2::zeros((l, l));
         let mut slope_buffer = x0.clone();
 
-        if self.with_progress {
-            for t in tqdm(1..n).width(Some(100)) {
-                x0 = self.execute(t, x0, &mut result, &mut time, &mut J, &mut slope_buffer);
+        let mut termination = Termination::Completed;
+        let mut rows = n - first;
+        self.progress.start(time[0], self.T);
+        self.limits.start();
+        for t in first + 1..n {
+            let row = t - first;
+            if let Some(stop) = self.limits.check(time[row - 1]) {
+                termination = stop;
+                rows = row;
+                break;
             }
-        } else {
-            for t in 1..n {
-                x0 = self.execute(t, x0, &mut result, &mut time, &mut J, &mut slope_buffer);
+            let failures = self.stats.newton_failures;
+            match self.execute(x0.clone(), &mut J, &mut slope_buffer) {
+                Ok(_) if self.stats.newton_failures > failures => {
+                    termination = Termination::Failed {
+                        t: (t - 1) as f64 * self.h,
+                        reason: "The Newton iteration did not converge".into(),
+                    };
+                    rows = row;
+                    break;
+                }
+                Ok(x1) => {
+                    result
+                        .row_mut(row)
+                        .iter_mut()
+                        .zip(x1.iter())
+                        .for_each(|(x, &y)| *x = y.x());
+                    time[row] = t as f64 * self.h;
+                    self.stats.steps += 1;
+                    x0 = x1;
+                    self.progress.update(time[row]);
+                }
+                Err(e) => {
+                    termination = Termination::Failed {
+                        t: (t - 1) as f64 * self.h,
+                        reason: e.to_string(),
+                    };
+                    rows = row;
+                    break;
+                }
+            }
+            if let Some(failed) =
+                Checkpoints::write_due(self.checkpoints.as_ref(), t, || Checkpoint {
+                    step: t,
+                    t: time[row],
+                    h: self.h,
+                    history: vec![x0.to_f64()],
+                    controller: None,
+                    stats: self.stats,
+                })
+            {
+                termination = failed;
+                rows = row + 1;
+                break;
             }
         }
+        self.progress.finish();
 
-        (time, result)
+        let mut solution = Solution::new(time, result, self.stats);
+        solution.truncate(rows).termination = termination;
+        solution
     }
 
-    fn set_with_progress(&mut self, with_progress: bool) -> &mut Self {
-        self.with_progress = with_progress;
+    fn set_progress<P: Progress + 'static>(&mut self, progress: P) -> &mut Self {
+        self.progress = Box::new(progress);
         self
     }
+
+    fn limits_mut(&mut self) -> &mut Limits {
+        &mut self.limits
+    }
 }
 impl<Scheme> OneStep for OdeIm<Scheme>
 where
@@ -117,10 +167,30 @@ where
             h: 0.1,
             T: 1.0,
             ɛ: f64::EPSILON,
-            with_progress: true,
+            progress: Box::<TqdmProgress>::default(),
+            limits: Limits::default(),
+            checkpoints: None,
+            resume: None,
+            stats: SolverStats::default(),
         }
     }
 }
+impl<Scheme> Restart for OdeIm<Scheme>
+where
+    Scheme: Implicit + std::marker::Sync + Residual1Step,
+{
+    fn set_checkpoints(&mut self, path: impl AsRef<Path>, every: usize) -> &mut Self {
+        self.checkpoints = Some(Checkpoints::new(path, every));
+        self
+    }
+
+    fn resume(&mut self, checkpoint: Checkpoint) -> &mut Self {
+        self.h = checkpoint.h;
+        self.resume = Some(checkpoint);
+        self
+    }
+}
+
 #[allow(non_snake_case)]
 pub struct OdeEx<Scheme>
 where
@@ -130,7 +200,10 @@ where
     initial: Array1<f64>,
     h: f64,
     T: f64,
-    with_progress: bool,
+    progress: Box<dyn Progress>,
+    limits: Limits,
+    checkpoints: Option<Checkpoints>,
+    resume: Option<Checkpoint>,
 }
 
 impl<Scheme> ODE<Scheme> for OdeEx<Scheme>
@@ -148,35 +221,81 @@ where
         self
     }
 
-    fn run(self) -> (Vec<f64>, Array2<f64>) {
+    fn run(mut self) -> Solution {
         let n: f64 = self.T / self.h;
-        let n = n.floor() as usize;
-        let mut x0 = self.initial.clone();
+        let (first, mut x0, mut stats) = match self.resume.take() {
+            Some(checkpoint) => (
+                checkpoint.step,
+                checkpoint.state().clone(),
+                checkpoint.stats,
+            ),
+            None => (0, self.initial.clone(), SolverStats::default()),
+        };
+        let n = (n.floor() as usize).max(first + 1);
 
-        let mut result: Array2<f64> = Array::zeros((n, x0.len()));
+        let mut result: Array2<f64> = Array::zeros((n - first, x0.len()));
         result
             .row_mut(0)
             .iter_mut()
             .zip(x0.iter())
             .for_each(|(x, &y)| *x = y);
-        let mut time = vec![0.0; n];
+        let mut time = vec![first as f64 * self.h; n - first];
 
-        if self.with_progress {
-            for t in tqdm(1..n) {
-                x0 = self.execute(t, x0, &mut result, &mut time);
+        let mut termination = Termination::Completed;
+        let mut rows = n - first;
+        self.progress.start(time[0], self.T);
+        self.limits.start();
+        for t in first + 1..n {
+            let row = t - first;
+            if let Some(stop) = self.limits.check(time[row - 1]) {
+                termination = stop;
+                rows = row;
+                break;
             }
-        } else {
-            for t in 1..n {
-                x0 = self.execute(t, x0, &mut result, &mut time);
+            x0 = match self.execute(t, row, x0, &mut result, &mut time) {
+                Ok(x1) => x1,
+                Err(e) => {
+                    termination = Termination::Failed {
+                        t: time[row - 1],
+                        reason: e.to_string(),
+                    };
+                    rows = row;
+                    break;
+                }
+            };
+            stats.steps += 1;
+            stats.rhs_evaluations += self.scheme.evaluations();
+            stats.newton_failures += self.scheme.newton_failures();
+            self.progress.update(time[row]);
+            if let Some(failed) =
+                Checkpoints::write_due(self.checkpoints.as_ref(), t, || Checkpoint {
+                    step: t,
+                    t: time[row],
+                    h: self.h,
+                    history: vec![x0.clone()],
+                    controller: None,
+                    stats,
+                })
+            {
+                termination = failed;
+                rows = row + 1;
+                break;
             }
         }
-        (time, result)
+        self.progress.finish();
+        let mut solution = Solution::new(time, result, stats);
+        solution.truncate(rows).termination = termination;
+        solution
     }
 
-    fn set_with_progress(&mut self, with_progress: bool) -> &mut Self {
-        self.with_progress = with_progress;
+    fn set_progress<P: Progress + 'static>(&mut self, progress: P) -> &mut Self {
+        self.progress = Box::new(progress);
         self
     }
+
+    fn limits_mut(&mut self) -> &mut Limits {
+        &mut self.limits
+    }
 }
 
 impl<Scheme> OdeEx<Scheme>
@@ -187,22 +306,23 @@ where
     fn execute(
         &self,
         t: usize,
+        row: usize,
         x0: Array1<f64>,
         result: &mut Array2<f64>,
-        time: &mut Vec<f64>,
-    ) -> Array1<f64> {
+        time: &mut [f64],
+    ) -> ndarray_linalg::error::Result<Array1<f64>> {
         // let mut x1 = result.row_mut(t);
 
         // self.scheme.next1(x0.view(), x1);
 
-        let x1 = self.scheme.next(x0.view());
+        let x1 = self.scheme.next(x0.view())?;
         result
-            .row_mut(t)
+            .row_mut(row)
             .iter_mut()
             .zip(x1.iter())
             .for_each(|(x, &y)| *x = y);
-        time[t] = t as f64 * self.h;
-        x1
+        time[row] = t as f64 * self.h;
+        Ok(x1)
     }
 }
 
@@ -217,7 +337,26 @@ where
             initial,
             h: 0.1,
             T: 1.0,
-            with_progress: true,
+            progress: Box::<TqdmProgress>::default(),
+            limits: Limits::default(),
+            checkpoints: None,
+            resume: None,
         }
     }
 }
+
+impl<Scheme> Restart for OdeEx<Scheme>
+where
+    Scheme: Explicit + std::marker::Sync,
+{
+    fn set_checkpoints(&mut self, path: impl AsRef<Path>, every: usize) -> &mut Self {
+        self.checkpoints = Some(Checkpoints::new(path, every));
+        self
+    }
+
+    fn resume(&mut self, checkpoint: Checkpoint) -> &mut Self {
+        self.h = checkpoint.h;
+        self.resume = Some(checkpoint);
+        self
+    }
+}
@@ -0,0 +1,418 @@
+use ndarray::{Array1, Array2, ArrayView1};
+use ndarray_linalg::{error::Result, Norm};
+use rayon::prelude::*;
+
+use crate::{ad::*, ode::*};
+
+/// Advances a state over a time slice of Parareal with a scheme of fixed step size.
+pub trait Propagator: std::marker::Sync {
+    /// The step size `h`, the time slices have to be multiples of it.
+    fn step_size(&self) -> f64;
+    /// Advances `x` by `dt` and adds the work to `stats`.
+    fn propagate(
+        &self,
+        x: ArrayView1<f64>,
+        dt: f64,
+        stats: &mut SolverStats,
+    ) -> Result<Array1<f64>>;
+}
+
+/// Number of steps of size `h` which cover `dt`, a multiple of `h` up to rounding, see [covers].
+fn steps(dt: f64, h: f64) -> usize {
+    (dt / h).round() as usize
+}
+
+/// Whether `dt` is a positive multiple of `h` up to rounding.
+fn covers(dt: f64, h: f64) -> bool {
+    let n = (dt / h).round();
+    n >= 1. && (n * h - dt).abs() <= 1e-9 * dt
+}
+
+/// Runs an [Explicit] scheme with the step size `h`.
+pub struct ExplicitPropagator<Scheme>
+where
+    Scheme: Explicit + std::marker::Sync,
+{
+    scheme: Scheme,
+    h: f64,
+}
+
+impl<Scheme> ExplicitPropagator<Scheme>
+where
+    Scheme: Explicit + std::marker::Sync,
+{
+    /// The scheme is set up by `scheme` with the step size `h` of the propagator.
+    pub fn new(h: f64, scheme: impl FnOnce(f64) -> Scheme) -> Self {
+        ExplicitPropagator {
+            scheme: scheme(h),
+            h,
+        }
+    }
+}
+
+impl<Scheme> Propagator for ExplicitPropagator<Scheme>
+where
+    Scheme: Explicit + std::marker::Sync,
+{
+    fn step_size(&self) -> f64 {
+        self.h
+    }
+
+    fn propagate(
+        &self,
+        x: ArrayView1<f64>,
+        dt: f64,
+        stats: &mut SolverStats,
+    ) -> Result<Array1<f64>> {
+        let n = steps(dt, self.h);
+        let mut x = x.to_owned();
+        for _ in 0..n {
+            x = self.scheme.next(x.view())?;
+            stats.newton_failures += self.scheme.newton_failures();
+        }
+        stats.steps += n;
+        stats.rhs_evaluations += n * self.scheme.evaluations();
+        Ok(x)
+    }
+}
+
+/// Runs an [Implicit] scheme with the step size `h` and [newton].
+///
+/// Every slice works on its own clone of the scheme, as the scheme keeps the last state. A Newton iteration
+/// which does not converge fails the Parareal run.
+pub struct ImplicitPropagator<Scheme>
+where
+    Scheme: Implicit + Residual1Step + Clone + std::marker::Sync,
+{
+    scheme: Scheme,
+    h: f64,
+    ɛ: f64,
+}
+
+impl<Scheme> ImplicitPropagator<Scheme>
+where
+    Scheme: Implicit + Residual1Step + Clone + std::marker::Sync,
+{
+    /// The scheme is set up by `scheme` with the step size `h` of the propagator.
+    pub fn new(h: f64, scheme: impl FnOnce(f64) -> Scheme) -> Self {
+        ImplicitPropagator {
+            scheme: scheme(h),
+            h,
+            ɛ: f64::EPSILON,
+        }
+    }
+
+    pub fn set_epsilon(&mut self, ɛ: f64) -> &mut Self {
+        self.ɛ = ɛ;
+        self
+    }
+}
+
+impl<Scheme> Propagator for ImplicitPropagator<Scheme>
+where
+    Scheme: Implicit + Residual1Step + Clone + std::marker::Sync,
+{
+    fn step_size(&self) -> f64 {
+        self.h
+    }
+
+    #[allow(non_snake_case)]
+    fn propagate(
+        &self,
+        x: ArrayView1<f64>,
+        dt: f64,
+        stats: &mut SolverStats,
+    ) -> Result<Array1<f64>> {
+        let mut scheme = self.scheme.clone();
+        let l = x.len();
+        let mut J = Array2::zeros((l, l));
+        let mut slope_buffer = x.to_ad();
+        let mut x = x.to_ad();
+        for _ in 0..steps(dt, self.h) {
+            scheme.update(x.clone());
+            x = newton_with_stats(self.ɛ, &scheme, x, &mut J, &mut slope_buffer, stats)?;
+            stats.steps += 1;
+        }
+        Ok(x.to_f64())
+    }
+}
+
+/// [Propagator::propagate], which also fails once a Newton iteration of the propagator did not converge.
+fn checked(
+    propagator: &impl Propagator,
+    x: ArrayView1<f64>,
+    dt: f64,
+    stats: &mut SolverStats,
+) -> std::result::Result<Array1<f64>, String> {
+    let failures = stats.newton_failures;
+    let x = propagator
+        .propagate(x, dt, stats)
+        .map_err(|e| e.to_string())?;
+    if stats.newton_failures > failures {
+        return Err("The Newton iteration did not converge".into());
+    }
+    Ok(x)
+}
+
+/// How the Parareal iteration went.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Convergence {
+    pub iterations: usize,
+    /// Largest change of a slice boundary in each iteration.
+    pub increments: Vec<f64>,
+    pub converged: bool,
+}
+
+/// Parallel-in-time integration of one trajectory.
+///
+/// The time span is cut into slices. A cheap `coarse` propagator predicts the states at the slice boundaries
+/// sequentially, the accurate `fine` propagator runs on all slices in parallel, and the correction
+/// `U_n+1 = G(U_n) + F(U_n_old) - G(U_n_old)` is repeated until the boundaries stop changing.
+/// After `k` iterations the first `k` slices equal the fine solution, so at most one iteration per slice is needed.
+#[allow(non_snake_case)]
+pub struct Parareal<Coarse, Fine>
+where
+    Coarse: Propagator,
+    Fine: Propagator,
+{
+    coarse: Coarse,
+    fine: Fine,
+    initial: Array1<f64>,
+    T: f64,
+    slices: usize,
+    tolerance: f64,
+    max_iterations: usize,
+}
+
+impl<Coarse, Fine> Parareal<Coarse, Fine>
+where
+    Coarse: Propagator,
+    Fine: Propagator,
+{
+    pub fn new(coarse: Coarse, fine: Fine, initial: Array1<f64>) -> Self {
+        Parareal {
+            coarse,
+            fine,
+            initial,
+            T: 1.0,
+            slices: rayon::current_num_threads(),
+            tolerance: 1e-10,
+            max_iterations: usize::MAX,
+        }
+    }
+
+    #[allow(non_snake_case)]
+    pub fn set_t(&mut self, T: f64) -> &mut Self {
+        self.T = T;
+        self
+    }
+
+    /// Number of time slices, by default one per rayon thread.
+    ///
+    /// The slices `T / slices` have to be multiples of the step sizes of both propagators, otherwise the run fails.
+    pub fn set_slices(&mut self, slices: usize) -> &mut Self {
+        self.slices = slices.max(1);
+        self
+    }
+
+    /// The iteration stops once no slice boundary changes by more than `tolerance`.
+    pub fn set_tolerance(&mut self, tolerance: f64) -> &mut Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn set_max_iterations(&mut self, max_iterations: usize) -> &mut Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// The states at the slice boundaries and the course of the iteration.
+    ///
+    /// The stats count the work of both propagators in all iterations.
+    #[allow(non_snake_case)]
+    pub fn run(&self) -> (Solution, Convergence) {
+        let m = self.slices;
+        let dt = self.T / m as f64;
+        let time: Vec<f64> = (0..=m).map(|n| n as f64 * dt).collect();
+        let mut stats = SolverStats::default();
+        let mut convergence = Convergence {
+            iterations: 0,
+            increments: vec![],
+            converged: false,
+        };
+        let finish = |U: &[Array1<f64>], stats, termination, convergence| {
+            let mut states = Array2::zeros((U.len(), self.initial.len()));
+            for (mut row, u) in states.rows_mut().into_iter().zip(U) {
+                row.assign(u);
+            }
+            let mut solution = Solution::new(time[..U.len()].to_vec(), states, stats);
+            solution.termination = termination;
+            (solution, convergence)
+        };
+
+        let mut U = vec![self.initial.clone()];
+        for h in [self.coarse.step_size(), self.fine.step_size()] {
+            if !covers(dt, h) {
+                let termination = Termination::Failed {
+                    t: 0.,
+                    reason: format!(
+                        "The time slices of length {dt} are no multiple of the step size {h}"
+                    ),
+                };
+                return finish(&U, stats, termination, convergence);
+            }
+        }
+        let mut G = vec![];
+        for n in 0..m {
+            match checked(&self.coarse, U[n].view(), dt, &mut stats) {
+                Ok(g) => {
+                    G.push(g.clone());
+                    U.push(g);
+                }
+                Err(e) => return finish(&U, stats, self.failed(time[n], e), convergence),
+            }
+        }
+
+        // Slices before `done` are exact and are not propagated again.
+        let mut done = 0;
+        while done < m && convergence.iterations < self.max_iterations {
+            let fine: Vec<_> = (done..m)
+                .into_par_iter()
+                .map(|n| {
+                    let mut stats = SolverStats::default();
+                    let x = checked(&self.fine, U[n].view(), dt, &mut stats);
+                    (x, stats)
+                })
+                .collect();
+            let mut F = Vec::with_capacity(fine.len());
+            for (n, (x, fine_stats)) in (done..m).zip(fine) {
+                stats += fine_stats;
+                match x {
+                    Ok(x) => F.push(x),
+                    Err(e) => return finish(&U, stats, self.failed(time[n], e), convergence),
+                }
+            }
+
+            let mut increment: f64 = 0.;
+            for n in done..m {
+                let g = if n == done {
+                    G[n].clone()
+                } else {
+                    match checked(&self.coarse, U[n].view(), dt, &mut stats) {
+                        Ok(g) => g,
+                        Err(e) => return finish(&U, stats, self.failed(time[n], e), convergence),
+                    }
+                };
+                let u = &g + &F[n - done] - &G[n];
+                increment = increment.max((&u - &U[n + 1]).norm());
+                G[n] = g;
+                U[n + 1] = u;
+            }
+            done += 1;
+            convergence.iterations += 1;
+            convergence.increments.push(increment);
+            if increment <= self.tolerance {
+                convergence.converged = true;
+                break;
+            }
+        }
+        convergence.converged |= done == m;
+
+        let termination = if convergence.converged {
+            Termination::Completed
+        } else {
+            Termination::Failed {
+                t: time[done],
+                reason: format!(
+                    "Parareal did not converge within {} iterations",
+                    convergence.iterations
+                ),
+            }
+        };
+        finish(&U, stats, termination, convergence)
+    }
+
+    fn failed(&self, t: f64, reason: String) -> Termination {
+        Termination::Failed { t, reason }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{
+        ad::AD,
+        ode::{solver::*, *},
+    };
+
+    fn oscillator(x: ArrayView1<f64>) -> Array1<f64> {
+        array![x[1], -x[0]]
+    }
+
+    #[test]
+    fn converges_to_fine_solution() {
+        let (h_coarse, h_fine) = (0.1, 0.001);
+        let coarse = ExplicitPropagator::new(h_coarse, |h| ExplicitEuler::new(h, oscillator));
+        let fine = ExplicitPropagator::new(h_fine, |h| {
+            RungeKutta::new(h, ButcherTableau::rk4(), oscillator)
+        });
+        let mut parareal = Ode::parareal(coarse, fine, array![1.0, 0.0]);
+        parareal.set_t(4.0).set_slices(8).set_tolerance(1e-9);
+        let (solution, convergence) = parareal.run();
+
+        assert!(convergence.converged);
+        assert!(convergence.iterations < 8);
+        assert_eq!(solution.len(), 9);
+        for (t, x) in solution.iter() {
+            assert!((x[0] - t.cos()).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn slices_have_to_be_multiples_of_the_step_sizes() {
+        let h = 0.1;
+        let parareal = |slices: usize| {
+            let coarse = ExplicitPropagator::new(h, |h| ExplicitEuler::new(h, oscillator));
+            let fine = ExplicitPropagator::new(h, |h| {
+                RungeKutta::new(h, ButcherTableau::rk4(), oscillator)
+            });
+            let mut parareal = Ode::parareal(coarse, fine, array![1.0, 0.0]);
+            parareal.set_t(1.0).set_slices(slices);
+            parareal.run()
+        };
+
+        // slices of 1 / 3 would be integrated over 0.3
+        let (solution, convergence) = parareal(3);
+        assert!(matches!(
+            solution.termination,
+            Termination::Failed { t, .. } if t == 0.
+        ));
+        assert_eq!(solution.len(), 1);
+        assert_eq!(convergence.iterations, 0);
+
+        let (solution, _) = parareal(5);
+        assert_eq!(solution.termination, Termination::Completed);
+        let (t, x) = solution.iter().last().unwrap();
+        assert!((t - 1.).abs() < 1e-12 && (x[0] - t.cos()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn unconverged_newton_fails() {
+        // the implicit Euler step x1 - 1 - 0.6 x1² = 0 of x' = x² from 1 has no solution
+        fn blow_up(x: ArrayView1<AD>, f: &mut Array1<AD>) {
+            f[0] = x[0] * x[0];
+        }
+        let propagator = || ImplicitPropagator::new(0.6, |h| ThetaMethod::new(h, 1., blow_up));
+        let mut parareal = Ode::parareal(propagator(), propagator(), array![1.0]);
+        parareal.set_t(1.2).set_slices(2);
+        let (solution, convergence) = parareal.run();
+        assert!(matches!(
+            solution.termination,
+            Termination::Failed { t, .. } if t == 0.
+        ));
+        assert_eq!(solution.len(), 1);
+        assert_eq!(convergence.iterations, 0);
+        assert_eq!(solution.stats.newton_failures, 1);
+    }
+}
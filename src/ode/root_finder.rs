@@ -1,16 +1,43 @@
 use crate::prelude::*;
-use ndarray::{Array1, Array2};
-use ndarray_linalg::{error::Result, Inverse, Norm};
+use ndarray::{Array1, Array2, OwnedRepr};
+use ndarray_linalg::{error::Result, Inverse, LUFactorized, Norm, Solve};
 
 /// Newton method for iteratively finding the next state for our problem.
 #[allow(non_snake_case)]
 #[inline]
 pub fn newton<Res>(
+    rtol: f64,
+    residual: &Res,
+    x1: Array1<AD>,
+    J: &mut Array2<f64>,
+    slope_buffer: &mut Array1<AD>,
+) -> Result<Array1<AD>>
+where
+    Res: Residual + std::marker::Sync,
+{
+    newton_with_stats(
+        rtol,
+        residual,
+        x1,
+        J,
+        slope_buffer,
+        &mut SolverStats::default(),
+    )
+}
+
+/// [newton], which adds its residual evaluations, Jacobians, inversions and iterations to `stats`.
+///
+/// A residual still above `rtol`, or above the rounding level of the state for a smaller `rtol`, or not a
+/// number after the iterations counts as a failure, the last iterate is returned anyway.
+#[allow(non_snake_case)]
+#[inline]
+pub fn newton_with_stats<Res>(
     rtol: f64,
     residual: &Res,
     mut x1: Array1<AD>,
     J: &mut Array2<f64>,
     slope_buffer: &mut Array1<AD>,
+    stats: &mut SolverStats,
 ) -> Result<Array1<AD>>
 where
     Res: Residual + std::marker::Sync,
@@ -24,11 +51,17 @@ where
     // x1 - (x0+h*f(x1)) = g(x1) != 0
     let mut G = x1.clone();
     residual.eval(x1.view(), &mut G);
+    stats.jacobian_evaluations += 1;
+    stats.lu_factorizations += 1;
+    stats.rhs_evaluations += 1;
     // println!("G: {:?}", G);
     let mut num_iter: usize = 0;
     let mut err = G.to_f64().norm();
+    // a residual at the rounding level of the state is converged, even for a smaller rtol, but never NaN
+    let converged =
+        |err: f64, x1: &Array1<AD>| err < rtol.max(64. * f64::EPSILON * (1. + x1.to_f64().norm()));
     // 3. Iteration
-    while err >= rtol && num_iter <= 10 {
+    while !converged(err, &x1) && num_iter <= 10 {
         // prod = f'^-1 * f(x1) = f(x1)/ f'(x1)
         // Newton manipulation
         let DGG = DG_inv.dot(&G.to_f64());
@@ -47,7 +80,56 @@ where
         // println!();
         err = G.to_f64().norm();
         num_iter += 1;
+        stats.jacobian_evaluations += 1;
+        stats.lu_factorizations += 1;
+        stats.rhs_evaluations += 1;
+    }
+    stats.newton_iterations += num_iter;
+    if !converged(err, &x1) {
+        stats.newton_failures += 1;
+    }
+    Ok(x1)
+}
+
+/// Newton method with a fixed iteration matrix `lu`, the factorized approximation of the Jacobian of the residual.
+///
+/// It stops once an increment is below `rtol (1 + |x1|)`. Running out of `max_iter` iterations or increments
+/// which stop shrinking count as a failure in `stats`, the last iterate is returned anyway.
+#[allow(non_snake_case)]
+#[inline]
+pub fn simplified_newton_with_stats<Res>(
+    rtol: f64,
+    residual: &Res,
+    mut x1: Array1<AD>,
+    lu: &LUFactorized<OwnedRepr<f64>>,
+    max_iter: usize,
+    stats: &mut SolverStats,
+) -> Result<Array1<AD>>
+where
+    Res: Residual,
+{
+    let mut G = x1.clone();
+    let mut previous = f64::INFINITY;
+    let mut converged = false;
+    for _ in 0..max_iter {
+        residual.eval(x1.view(), &mut G);
+        stats.rhs_evaluations += 1;
+        stats.newton_iterations += 1;
+        let δ = lu.solve(&G.to_f64())?;
+        let norm = δ.iter().fold(0., |m: f64, δ| m.max(δ.abs()));
+        x1 = x1 - δ;
+        let scale = 1. + x1.iter().fold(0., |m: f64, x| m.max(x.x().abs()));
+        if norm <= rtol * scale {
+            converged = true;
+            break;
+        }
+        if norm >= previous {
+            break;
+        }
+        previous = norm;
+    }
+    if !converged {
+        stats.newton_failures += 1;
     }
-    println!();
     Ok(x1)
 }
@@ -0,0 +1,323 @@
+use crate::{ode::*, progress::*};
+use ndarray::*;
+use std::path::Path;
+
+/// Solver for second order problems `q'' = f(t, q, q')` with initial positions `q0` and velocities `v0`.
+///
+/// By default it uses fixed timesteps `h`. After [OdeSecondOrder::set_tolerances] the step size is
+/// adapted to the error estimate of an embedded scheme, starting from `h`.
+#[allow(non_snake_case)]
+pub struct OdeSecondOrder<Scheme>
+where
+    Scheme: SecondOrder,
+{
+    scheme: Scheme,
+    q0: Array1<f64>,
+    v0: Array1<f64>,
+    h: f64,
+    T: f64,
+    controller: Option<StepController>,
+    progress: Box<dyn Progress>,
+    limits: Limits,
+    checkpoints: Option<Checkpoints>,
+    resume: Option<Checkpoint>,
+}
+
+impl<Scheme> OdeSecondOrder<Scheme>
+where
+    Scheme: SecondOrder,
+{
+    pub fn new(scheme: Scheme, q0: Array1<f64>, v0: Array1<f64>) -> Self {
+        assert_eq!(
+            q0.len(),
+            v0.len(),
+            "Positions and velocities differ in size"
+        );
+        OdeSecondOrder {
+            scheme,
+            q0,
+            v0,
+            h: 0.1,
+            T: 1.0,
+            controller: None,
+            progress: Box::<TqdmProgress>::default(),
+            limits: Limits::default(),
+            checkpoints: None,
+            resume: None,
+        }
+    }
+
+    pub fn set_step_size(&mut self, h: f64) -> &mut Self {
+        self.h = h;
+        self
+    }
+
+    #[allow(non_snake_case)]
+    pub fn set_t(&mut self, T: f64) -> &mut Self {
+        self.T = T;
+        self
+    }
+
+    /// Switches between a [TqdmProgress] bar and [NoProgress].
+    pub fn set_with_progress(&mut self, with_progress: bool) -> &mut Self {
+        if with_progress {
+            self.set_progress(TqdmProgress::default())
+        } else {
+            self.set_progress(NoProgress)
+        }
+    }
+
+    pub fn set_progress<P: Progress + 'static>(&mut self, progress: P) -> &mut Self {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    /// Stops the run between two steps once the token is cancelled.
+    pub fn set_cancellation(&mut self, token: CancellationToken) -> &mut Self {
+        self.limits.set_cancellation(token);
+        self
+    }
+
+    /// Stops the run between two steps once the wall-clock time `limit` has passed.
+    pub fn set_time_limit(&mut self, limit: std::time::Duration) -> &mut Self {
+        self.limits.set_time_limit(limit);
+        self
+    }
+
+    /// Switches to adaptive steps, which needs a scheme with an embedded error estimate,
+    /// otherwise the run ends with [Termination::Failed].
+    pub fn set_tolerances(&mut self, atol: f64, rtol: f64) -> &mut Self {
+        self.controller = Some(StepController::new(atol, rtol));
+        self
+    }
+
+    /// Consumes the solver and runs the simulation.
+    ///
+    /// The states of the [Solution] are the positions `q0, q1, ...` followed by the velocities `v0, v1, ...`,
+    /// see [Solution::positions] and [Solution::velocities].
+    pub fn run(self) -> Solution {
+        let l = self.q0.len();
+        let (time, positions, velocities, stats, termination) = match self.controller {
+            Some(controller) => self.run_adaptive(controller),
+            None => self.run_fixed(),
+        };
+        let states = concatenate![Axis(1), positions, velocities];
+        let mut solution = Solution::new(time, states, stats);
+        solution.termination = termination;
+        solution.labels = (0..l)
+            .map(|i| format!("q{i}"))
+            .chain((0..l).map(|i| format!("v{i}")))
+            .collect();
+        solution
+    }
+
+    /// The step count, time, positions, velocities and work to start from, see [Restart::resume].
+    fn start(&mut self) -> (usize, f64, Array1<f64>, Array1<f64>, SolverStats) {
+        match self.resume.take() {
+            Some(checkpoint) => {
+                let x = checkpoint.state();
+                let l = self.q0.len();
+                assert_eq!(x.len(), 2 * l, "The checkpoint does not match the problem");
+                if let [carried, _] = &checkpoint.history[..] {
+                    self.scheme.restore_state(carried.clone());
+                }
+                let (q, v) = (x.slice(s![..l]).to_owned(), x.slice(s![l..]).to_owned());
+                (checkpoint.step, checkpoint.t, q, v, checkpoint.stats)
+            }
+            None => (
+                0,
+                0.0,
+                self.q0.clone(),
+                self.v0.clone(),
+                SolverStats::default(),
+            ),
+        }
+    }
+
+    fn run_fixed(mut self) -> Trajectory {
+        let n: f64 = self.T / self.h;
+        let (first, _, mut q, mut v, mut stats) = self.start();
+        let n = (n.floor() as usize).max(first + 1);
+        let l = self.q0.len();
+        let mut positions = Array2::zeros((n - first, l));
+        let mut velocities = Array2::zeros((n - first, l));
+        positions.row_mut(0).assign(&q);
+        velocities.row_mut(0).assign(&v);
+        let mut time: Vec<f64> = (first..n).map(|t| t as f64 * self.h).collect();
+
+        let mut termination = Termination::Completed;
+        let mut rows = n - first;
+        self.progress.start(time[0], self.T);
+        self.limits.start();
+        for t in first + 1..n {
+            let row = t - first;
+            if let Some(stop) = self.limits.check(time[row - 1]) {
+                termination = stop;
+                rows = row;
+                break;
+            }
+            let step = match self
+                .scheme
+                .next(time[row - 1], q.view(), v.view(), self.h, &mut stats)
+            {
+                Ok(step) => step,
+                Err(e) => {
+                    termination = Termination::Failed {
+                        t: time[row - 1],
+                        reason: e.to_string(),
+                    };
+                    rows = row;
+                    break;
+                }
+            };
+            stats.steps += 1;
+            positions.row_mut(row).assign(&step.q);
+            velocities.row_mut(row).assign(&step.v);
+            (q, v) = (step.q, step.v);
+            self.progress.update(time[row]);
+            if let Some(failed) =
+                Checkpoints::write_due(self.checkpoints.as_ref(), t, || Checkpoint {
+                    step: t,
+                    t: time[row],
+                    h: self.h,
+                    history: self.history(q.view(), v.view()),
+                    controller: None,
+                    stats,
+                })
+            {
+                termination = failed;
+                rows = row + 1;
+                break;
+            }
+        }
+        self.progress.finish();
+        let positions = positions.slice(s![..rows, ..]).to_owned();
+        let velocities = velocities.slice(s![..rows, ..]).to_owned();
+        time.truncate(rows);
+        (time, positions, velocities, stats, termination)
+    }
+
+    #[allow(non_snake_case)]
+    fn run_adaptive(mut self, mut controller: StepController) -> Trajectory {
+        let T = self.T;
+        let ɛ = 1e-12 * T.abs().max(1.);
+        let order = self.scheme.order().min(self.scheme.embedded_order()).max(1);
+        let (_, mut t, mut q, mut v, mut stats) = self.start();
+        let l = q.len();
+        let mut positions = Array2::zeros((0, l));
+        let mut velocities = Array2::zeros((0, l));
+        positions.push_row(q.view()).unwrap();
+        velocities.push_row(v.view()).unwrap();
+        let mut time = vec![t];
+
+        let mut h = self.h;
+        let mut termination = Termination::Completed;
+        self.progress.start(t, T);
+        self.limits.start();
+        while t < T - ɛ {
+            if let Some(stop) = self.limits.check(t) {
+                termination = stop;
+                break;
+            }
+            let h_step = h.min(T - t);
+            let step = match self.scheme.next(t, q.view(), v.view(), h_step, &mut stats) {
+                Ok(step) => step,
+                Err(e) => {
+                    if h_step <= controller.h_min {
+                        termination = Termination::Failed {
+                            t,
+                            reason: e.to_string(),
+                        };
+                        break;
+                    }
+                    stats.rejected_steps += 1;
+                    h = (h_step / 4.).max(controller.h_min);
+                    continue;
+                }
+            };
+            let Some(err) = step.error else {
+                termination = Termination::Failed {
+                    t,
+                    reason: "Adaptive steps need a scheme with an embedded error estimate".into(),
+                };
+                break;
+            };
+            let x0 = concatenate![Axis(0), q, v];
+            let x1 = concatenate![Axis(0), step.q, step.v];
+            let err = controller.error_norm(x0.view(), x1.view(), err.view());
+            h = controller.propose(h_step, err, order);
+            if err > 1. || err.is_nan() {
+                if h_step > controller.h_min {
+                    stats.rejected_steps += 1;
+                    continue;
+                }
+                termination = Termination::Failed {
+                    t,
+                    reason: format!(
+                        "The error {err} exceeds the tolerances at the smallest step size"
+                    ),
+                };
+                break;
+            }
+            stats.steps += 1;
+            t += h_step;
+            (q, v) = (step.q, step.v);
+            time.push(t);
+            positions.push_row(q.view()).unwrap();
+            velocities.push_row(v.view()).unwrap();
+            self.progress.update(t);
+            if let Some(failed) =
+                Checkpoints::write_due(self.checkpoints.as_ref(), stats.steps, || Checkpoint {
+                    step: stats.steps,
+                    t,
+                    h,
+                    history: self.history(q.view(), v.view()),
+                    controller: Some(controller),
+                    stats,
+                })
+            {
+                termination = failed;
+                break;
+            }
+        }
+        self.progress.finish();
+        (time, positions, velocities, stats, termination)
+    }
+}
+
+impl<Scheme> OdeSecondOrder<Scheme>
+where
+    Scheme: SecondOrder,
+{
+    /// The history of a checkpoint, the state carried by the scheme followed by the positions and velocities.
+    fn history(&self, q: ArrayView1<f64>, v: ArrayView1<f64>) -> Vec<Array1<f64>> {
+        self.scheme
+            .carried_state()
+            .into_iter()
+            .chain([concatenate![Axis(0), q, v]])
+            .collect()
+    }
+}
+
+/// Time, positions, velocities, work and termination of a run.
+type Trajectory = (Vec<f64>, Array2<f64>, Array2<f64>, SolverStats, Termination);
+
+impl<Scheme> Restart for OdeSecondOrder<Scheme>
+where
+    Scheme: SecondOrder,
+{
+    fn set_checkpoints(&mut self, path: impl AsRef<Path>, every: usize) -> &mut Self {
+        self.checkpoints = Some(Checkpoints::new(path, every));
+        self
+    }
+
+    /// The state of the checkpoint holds the positions followed by the velocities.
+    fn resume(&mut self, checkpoint: Checkpoint) -> &mut Self {
+        self.h = checkpoint.h;
+        if let Some(controller) = checkpoint.controller {
+            self.controller = Some(controller);
+        }
+        self.resume = Some(checkpoint);
+        self
+    }
+}
@@ -0,0 +1,220 @@
+use std::{fs::File, path::Path};
+
+use ndarray::{concatenate, s, Array1, Array2, ArrayView1, ArrayView2, Axis};
+
+use crate::{ode::SolverStats, plot};
+
+/// Why a run ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Termination {
+    /// The final time was reached.
+    Completed,
+    /// The solver could not continue after time `t`.
+    Failed { t: f64, reason: String },
+    /// A [CancellationToken](crate::ode::CancellationToken) stopped the run at time `t`.
+    Cancelled { t: f64 },
+    /// The wall-clock budget was used up at time `t`.
+    TimedOut { t: f64 },
+}
+
+/// Something the solver noticed during a run, e.g. a breakpoint of a delay differential equation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub name: String,
+    pub t: f64,
+    pub state: Array1<f64>,
+}
+
+/// The result of a run.
+///
+/// The rows of `states` belong to the entries of `time`, the columns to the entries of `labels`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    pub labels: Vec<String>,
+    pub time: Vec<f64>,
+    pub states: Array2<f64>,
+    pub stats: SolverStats,
+    pub termination: Termination,
+    pub events: Vec<Event>,
+}
+
+impl Solution {
+    /// A completed run with the labels `x0, x1, ...`.
+    pub fn new(time: Vec<f64>, states: Array2<f64>, stats: SolverStats) -> Self {
+        assert_eq!(
+            time.len(),
+            states.nrows(),
+            "Every time step needs exactly one state"
+        );
+        let labels = (0..states.ncols()).map(|i| format!("x{i}")).collect();
+        Solution {
+            labels,
+            time,
+            states,
+            stats,
+            termination: Termination::Completed,
+            events: vec![],
+        }
+    }
+
+    pub fn set_labels(&mut self, labels: &[&str]) -> &mut Self {
+        assert_eq!(
+            labels.len(),
+            self.states.ncols(),
+            "Every state needs exactly one label"
+        );
+        self.labels = labels.iter().map(|label| label.to_string()).collect();
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+
+    /// The course of the state with the given label.
+    pub fn component(&self, label: &str) -> Option<ArrayView1<'_, f64>> {
+        let i = self.labels.iter().position(|l| l == label)?;
+        Some(self.states.column(i))
+    }
+
+    /// The positions of a run of [crate::ode::Ode::second_order], the first half of the states.
+    pub fn positions(&self) -> ArrayView2<'_, f64> {
+        self.states.slice(s![.., ..self.states.ncols() / 2])
+    }
+
+    /// The velocities of a run of [crate::ode::Ode::second_order], the second half of the states.
+    pub fn velocities(&self) -> ArrayView2<'_, f64> {
+        self.states.slice(s![.., self.states.ncols() / 2..])
+    }
+
+    /// The state at time `t` by linear interpolation between the time steps, `None` outside the computed range.
+    pub fn at(&self, t: f64) -> Option<Array1<f64>> {
+        let (first, last) = (*self.time.first()?, *self.time.last()?);
+        if t < first || t > last {
+            return None;
+        }
+        let i = self.time.partition_point(|&s| s <= t);
+        if i == self.time.len() {
+            return Some(self.states.row(i - 1).to_owned());
+        }
+        let (t0, t1) = (self.time[i - 1], self.time[i]);
+        let w = (t - t0) / (t1 - t0);
+        Some((1. - w) * &self.states.row(i - 1) + w * &self.states.row(i))
+    }
+
+    /// Iterates over the pairs `(t, x(t))`.
+    pub fn iter(&self) -> impl Iterator<Item = (f64, ArrayView1<'_, f64>)> {
+        self.time.iter().copied().zip(self.states.rows())
+    }
+
+    /// The first `n` time steps, e.g. for a run which ended early.
+    pub fn truncate(&mut self, n: usize) -> &mut Self {
+        let n = n.min(self.len());
+        self.time.truncate(n);
+        self.states = self.states.slice(s![..n, ..]).to_owned();
+        self
+    }
+
+    /// The time column `t` followed by one column per label, the stats end up in the metadata.
+    pub fn to_dataframe(&self) -> plot::Dataframe<'_> {
+        let mut df = plot::Dataframe::new();
+        df.push(plot::Series::new("t", &self.time));
+        for (label, column) in self.labels.iter().zip(self.states.axis_iter(Axis(1))) {
+            df.push(plot::Series::new(label, &column.to_vec()));
+        }
+        for (key, value) in self.stats.metadata() {
+            df.push_metadata(&key, &value);
+        }
+        df
+    }
+
+    /// The time column `t` followed by one column per label.
+    pub fn to_polars(&self) -> polars::prelude::PolarsResult<polars::prelude::DataFrame> {
+        use polars::prelude::*;
+
+        let mut columns = vec![Series::new("t", self.time.clone())];
+        for (label, column) in self.labels.iter().zip(self.states.axis_iter(Axis(1))) {
+            columns.push(Series::new(label, column.to_vec()));
+        }
+        DataFrame::new(columns)
+    }
+
+    /// Reads a file written from [Solution::to_dataframe], the stats and the termination are not restored.
+    pub fn read_parquet(path: impl AsRef<Path>) -> polars::prelude::PolarsResult<Self> {
+        use polars::prelude::*;
+
+        let df = ParquetReader::new(File::open(path)?).finish()?;
+        let values = |series: &Series| -> PolarsResult<Vec<f64>> {
+            Ok(series
+                .f64()?
+                .into_iter()
+                .map(|x| x.unwrap_or(f64::NAN))
+                .collect())
+        };
+        let time = values(df.column("t")?)?;
+        let columns: Vec<&Series> = df
+            .get_columns()
+            .iter()
+            .filter(|series| series.name() != "t")
+            .collect();
+        let mut states = Array2::zeros((time.len(), columns.len()));
+        for (mut state, series) in states.axis_iter_mut(Axis(1)).zip(columns.iter()) {
+            state.assign(&Array1::from(values(series)?));
+        }
+        let mut solution = Solution::new(time, states, SolverStats::default());
+        solution.labels = columns
+            .iter()
+            .map(|series| series.name().to_string())
+            .collect();
+        Ok(solution)
+    }
+
+    /// Appends the steps after the last stored time to the file at `path`, e.g. of a run resumed from a
+    /// [Checkpoint](crate::ode::Checkpoint), or creates the file.
+    ///
+    /// Parquet files cannot grow in place, so the file is rewritten with the stats of this run in its metadata.
+    pub fn append_parquet(&self, path: impl AsRef<Path>) -> polars::prelude::PolarsResult<()> {
+        use polars::prelude::PolarsError;
+
+        let path = path.as_ref();
+        if !path.exists() {
+            return self.to_dataframe().store(&mut File::create(path)?);
+        }
+        let mut stored = Solution::read_parquet(path)?;
+        if stored.labels != self.labels {
+            return Err(PolarsError::ComputeError(
+                "The stored columns differ from the labels of the solution".into(),
+            ));
+        }
+        let last = stored.time.last().copied().unwrap_or(f64::NEG_INFINITY);
+        let first = self.time.partition_point(|&t| t <= last);
+        stored.time.extend_from_slice(&self.time[first..]);
+        stored.states = concatenate![Axis(0), stored.states, self.states.slice(s![first.., ..])];
+        stored.stats = self.stats;
+        stored.to_dataframe().store(&mut File::create(path)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::array;
+
+    use crate::ode::*;
+
+    #[test]
+    fn interpolates_between_steps() {
+        let states = array![[0., 1.], [2., 1.], [4., 0.]];
+        let mut solution = Solution::new(vec![0., 1., 2.], states, SolverStats::default());
+        solution.set_labels(&["q", "p"]);
+
+        assert_eq!(solution.at(1.5), Some(array![3., 0.5]));
+        assert_eq!(solution.at(2.0), Some(array![4., 0.]));
+        assert_eq!(solution.at(2.5), None);
+        assert_eq!(solution.component("p").unwrap(), array![1., 1., 0.]);
+        assert!(solution.component("x0").is_none());
+    }
+}
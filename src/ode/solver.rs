@@ -1,3 +1,35 @@
 //! This module defines all preimplemented residuals for calculating the next step in an ode.
+mod adams;
+pub use adams::*;
+mod discrete_gradient;
+pub use discrete_gradient::*;
 mod euler;
 pub use euler::*;
+mod exponential;
+pub use exponential::*;
+mod gauss;
+pub use gauss::*;
+mod imex;
+pub use imex::*;
+mod lie_group;
+pub use lie_group::*;
+mod magnus;
+pub use magnus::*;
+mod midpoint;
+pub use midpoint::*;
+mod phi;
+pub use phi::*;
+mod theta;
+pub use theta::*;
+mod rosenbrock;
+pub use rosenbrock::*;
+mod sdirk;
+pub use sdirk::*;
+mod runge_kutta;
+pub use runge_kutta::*;
+mod nystrom;
+pub use nystrom::*;
+mod newmark;
+pub use newmark::*;
+mod variational;
+pub use variational::*;
@@ -0,0 +1,320 @@
+use ndarray::{Array1, Array2, ArrayView1};
+use ndarray_linalg::{error::Result, Factorize, Solve};
+
+use crate::{ad::*, ode::*};
+
+use super::sdirk::Stage;
+
+/// Weights `w_j` with `∫_0^1 p(s) ds = Σ_j w_j p(τ_j)` for all polynomials `p` below the number of nodes.
+///
+/// With the nodes `τ_j = (t_j - t_n) / h` of the past steps they are the coefficients of an Adams method
+/// from `t_n` to `t_n + h` for any spacing of the steps, equidistant ones give the classical coefficients.
+pub fn integration_weights(nodes: &[f64]) -> Array1<f64> {
+    let k = nodes.len();
+    let vandermonde = Array2::from_shape_fn((k, k), |(m, j)| nodes[j].powi(m as i32));
+    let moments = Array1::from_shape_fn(k, |m| 1. / (m + 1) as f64);
+    vandermonde
+        .solve(&moments)
+        .expect("The nodes of the steps are distinct")
+}
+
+/// How an [Adams] method takes its steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdamsMode {
+    /// Explicit Adams-Bashforth method, one evaluation of `f` per step and no error estimate.
+    Bashforth,
+    /// Implicit Adams-Moulton method, solved by simplified Newton iterations from an Adams-Bashforth predictor.
+    Moulton,
+    /// Adams-Bashforth predictor, one Adams-Moulton corrector and the evaluation at the corrected state.
+    Pece,
+}
+
+/// Adams methods of order one to six for `x' = f(x)` in the [AdamsMode] of choice, driven by [Ode::multistep].
+///
+/// The coefficients are computed by [integration_weights] from the times of the history in each step.
+/// The implicit and the predictor-corrector modes estimate the error by the difference of predictor and
+/// corrector after Milne.
+pub struct Adams<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    flow: Flow,
+    order: usize,
+    mode: AdamsMode,
+    ɛ: f64,
+    max_iter: usize,
+    /// The last Jacobian and the state it belongs to, kept for a repeated step.
+    jacobian: Option<(Array1<f64>, Array2<f64>)>,
+}
+
+/// The error constants of the Adams-Bashforth and Adams-Moulton methods of the same order.
+const ERROR_CONSTANTS: [(f64, f64); 6] = [
+    (1. / 2., -1. / 2.),
+    (5. / 12., -1. / 12.),
+    (3. / 8., -1. / 24.),
+    (251. / 720., -19. / 720.),
+    (95. / 288., -3. / 160.),
+    (19087. / 60480., -863. / 60480.),
+];
+
+impl<Flow> Adams<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    pub fn new(order: usize, mode: AdamsMode, flow: Flow) -> Self {
+        assert!(
+            (1..=6).contains(&order),
+            "Adams methods are implemented up to order six"
+        );
+        Adams {
+            flow,
+            order,
+            mode,
+            ɛ: 1e-10,
+            max_iter: 10,
+            jacobian: None,
+        }
+    }
+
+    pub fn bashforth(order: usize, flow: Flow) -> Self {
+        Adams::new(order, AdamsMode::Bashforth, flow)
+    }
+
+    pub fn moulton(order: usize, flow: Flow) -> Self {
+        Adams::new(order, AdamsMode::Moulton, flow)
+    }
+
+    pub fn pece(order: usize, flow: Flow) -> Self {
+        Adams::new(order, AdamsMode::Pece, flow)
+    }
+
+    /// Relative tolerance of the iterations of the implicit mode.
+    pub fn set_epsilon(&mut self, ɛ: f64) -> &mut Self {
+        self.ɛ = ɛ;
+        self
+    }
+
+    pub fn set_max_iter(&mut self, max_iter: usize) -> &mut Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn mode(&self) -> AdamsMode {
+        self.mode
+    }
+
+    /// `x_n + h Σ_j w_j f_j` over the last `m` slopes of the history and, if `new` is set, the unknown slope
+    /// at `t_n + h` with the returned weight.
+    fn integrate(&self, history: &StepHistory, h: f64, m: usize, new: bool) -> (Array1<f64>, f64) {
+        let n = history.len();
+        let t_n = history.time[n - 1];
+        let mut nodes: Vec<f64> = history.time[n - m..]
+            .iter()
+            .map(|t| (t - t_n) / h)
+            .collect();
+        if new {
+            nodes.push(1.);
+        }
+        let weights = integration_weights(&nodes);
+        let mut x = history.states[n - 1].clone();
+        for (slope, &w) in history.slopes[n - m..].iter().zip(weights.iter()) {
+            x.scaled_add(h * w, slope);
+        }
+        (x, if new { weights[m] } else { 0. })
+    }
+
+    #[allow(non_snake_case)]
+    fn jacobian(&mut self, x: &Array1<f64>, stats: &mut SolverStats) -> Array2<f64> {
+        if let Some((x_J, J)) = &self.jacobian {
+            if x_J == x {
+                return J.clone();
+            }
+        }
+        stats.jacobian_evaluations += 1;
+        let J = flow_jacobian(&self.flow, x.view());
+        self.jacobian = Some((x.clone(), J.clone()));
+        J
+    }
+}
+
+impl<Flow> Multistep for Adams<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Milne's estimate is the local error of the corrector itself.
+    fn embedded_order(&self) -> usize {
+        self.order
+    }
+
+    /// The predictor of the order needs as many slopes as the order.
+    fn steps(&self) -> usize {
+        self.order
+    }
+
+    fn slope(&self, x: ArrayView1<f64>) -> Array1<f64> {
+        eval_flow(&self.flow, x)
+    }
+
+    #[allow(non_snake_case)]
+    fn next(
+        &mut self,
+        history: &StepHistory,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> Result<MultistepStep> {
+        let p = self.order;
+        let (predictor, _) = self.integrate(history, h, p, false);
+        if self.mode == AdamsMode::Bashforth {
+            let slope = self.slope(predictor.view());
+            stats.rhs_evaluations += 1;
+            return Ok(MultistepStep {
+                x: predictor,
+                slope,
+                error: None,
+            });
+        }
+
+        let (known, γ) = self.integrate(history, h, p - 1, true);
+        let failures = stats.newton_failures;
+        let x = match self.mode {
+            AdamsMode::Pece => {
+                stats.rhs_evaluations += 1;
+                &known + &(h * γ * self.slope(predictor.view()))
+            }
+            _ => {
+                let x_n = &history.states[history.len() - 1];
+                let J = self.jacobian(x_n, stats);
+                let lu = (Array2::eye(x_n.len()) - h * γ * J).factorize()?;
+                stats.lu_factorizations += 1;
+                let stage = Stage {
+                    known: known.to_ad(),
+                    hγ: AD::AD0(h * γ),
+                    flow: &self.flow,
+                };
+                simplified_newton_with_stats(
+                    self.ɛ,
+                    &stage,
+                    predictor.to_ad(),
+                    &lu,
+                    self.max_iter,
+                    stats,
+                )?
+                .to_f64()
+            }
+        };
+        let slope = self.slope(x.view());
+        stats.rhs_evaluations += 1;
+
+        let (c_predictor, c_corrector) = ERROR_CONSTANTS[p - 1];
+        let mut error = c_corrector / (c_predictor - c_corrector) * (&x - &predictor);
+        if stats.newton_failures > failures {
+            // rejects the step, the controller shrinks it as far as possible
+            error.fill(f64::INFINITY);
+        }
+        Ok(MultistepStep {
+            x,
+            slope,
+            error: Some(error),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{
+        ad::AD,
+        ensemble::final_state,
+        ode::{solver::*, *},
+        test_support::*,
+    };
+
+    fn fixed(mode: AdamsMode, order: usize, h: f64) -> Array1<f64> {
+        let mut ode = Ode::multistep(Adams::new(order, mode, pendulum), array![1.0, 0.0]);
+        ode.set_step_size(h).set_t(2.0).set_with_progress(false);
+        let solution = ode.run();
+        assert_eq!(solution.termination, Termination::Completed);
+        final_state(&solution)
+    }
+
+    #[test]
+    fn coefficients_and_orders() {
+        let weights = integration_weights(&[-3., -2., -1., 0.]) * 24.;
+        let classical = array![-9., 37., -59., 55.];
+        assert!((weights - classical).iter().all(|e| e.abs() < 1e-12));
+        let weights = integration_weights(&[-2., -1., 0., 1.]) * 24.;
+        let classical = array![1., -5., 19., 9.];
+        assert!((weights - classical).iter().all(|e| e.abs() < 1e-12));
+
+        let reference = fixed(AdamsMode::Moulton, 6, 1e-3);
+        for mode in [AdamsMode::Bashforth, AdamsMode::Moulton, AdamsMode::Pece] {
+            for order in 1..=6 {
+                let p = order as f64;
+                // the higher orders need steps in their asymptotic range above the rounding errors
+                let h = if order > 3 { 0.05 } else { 0.02 };
+                let observed = observed_order(|h| max_error(&fixed(mode, order, h), &reference), h);
+                assert!((observed - p).abs() < 0.3, "{mode:?} {p}: {observed}");
+            }
+        }
+
+        let mut ode = Ode::multistep(Adams::pece(5, pendulum), array![1.0, 0.0]);
+        ode.set_tolerances(1e-9, 1e-9)
+            .set_step_size(1e-3)
+            .set_t(2.0)
+            .set_with_progress(false);
+        let solution = ode.run();
+        assert_eq!(solution.termination, Termination::Completed);
+        assert!(solution.stats.steps < 300, "{}", solution.stats.steps);
+        assert!(max_error(&final_state(&solution), &reference) < 1e-7);
+    }
+
+    #[test]
+    fn adaptive_steps_need_an_error_estimate() {
+        let run = |mode: AdamsMode, startup: ButcherTableau| {
+            let mut ode = Ode::multistep(Adams::new(4, mode, pendulum), array![1.0, 0.0]);
+            ode.set_startup(startup)
+                .set_tolerances(1e-9, 1e-9)
+                .set_step_size(1e-3)
+                .set_with_progress(false);
+            ode.run()
+        };
+        // the Adams-Bashforth method itself, after the three startup steps
+        let solution = run(AdamsMode::Bashforth, ButcherTableau::dormand_prince());
+        assert!(matches!(solution.termination, Termination::Failed { .. }));
+        assert_eq!(solution.len(), 4);
+        // the startup method
+        let solution = run(AdamsMode::Pece, ButcherTableau::rk4());
+        assert!(matches!(
+            solution.termination,
+            Termination::Failed { t, .. } if t == 0.
+        ));
+        assert_eq!(solution.len(), 1);
+    }
+
+    #[test]
+    fn not_a_number_fails() {
+        // exponential growth until the flow breaks down at x = 1.5, i.e. t = ln 1.5
+        let flow = |x: ArrayView1<AD>, f: &mut Array1<AD>| {
+            f[0] = if x[0].x() < 1.5 {
+                x[0]
+            } else {
+                AD::AD0(f64::NAN)
+            };
+        };
+        let mut ode = Ode::multistep(Adams::pece(4, flow), array![1.0]);
+        ode.set_tolerances(1e-8, 1e-8)
+            .set_step_size(1e-3)
+            .set_t(1.0)
+            .set_with_progress(false);
+        let solution = ode.run();
+        match solution.termination {
+            Termination::Failed { t, .. } => assert!((t - 1.5f64.ln()).abs() < 1e-6, "{t}"),
+            termination => panic!("{termination:?}"),
+        }
+    }
+}
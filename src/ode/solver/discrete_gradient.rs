@@ -0,0 +1,236 @@
+use ndarray::{array, Array1, Array2, ArrayView1, Zip};
+
+use crate::{ad::*, ode::*};
+
+use super::GaussTableau;
+
+/// The canonical structure `[[0, I], [-I, 0]]` of `dof` positions followed by `dof` momenta.
+pub fn canonical_structure(dof: usize) -> Array2<f64> {
+    Array2::from_shape_fn((2 * dof, 2 * dof), |(i, j)| {
+        if j == i + dof {
+            1.
+        } else if i == j + dof {
+            -1.
+        } else {
+            0.
+        }
+    })
+}
+
+/// The discrete gradient `∇̄H(x0, x1)` of a [DiscreteGradient] method, which satisfies
+/// `H(x1) - H(x0) = ∇̄H(x0, x1) · (x1 - x0)`.
+#[derive(Debug, Clone)]
+pub enum Gradient {
+    /// The midpoint discrete gradient of Gonzalez, `∇H` at the midpoint corrected along `x1 - x0`, second order.
+    Gonzalez,
+    /// The coordinate increment discrete gradient of Itoh and Abe, which only evaluates `H`, first order.
+    ItohAbe,
+    /// The averaged vector field `∫_0^1 ∇H(x0 + ξ (x1 - x0)) dξ`, second order, by Gauss-Legendre quadrature
+    /// which is exact for polynomial `H` of degree up to twice the number of nodes.
+    AveragedVectorField(GaussTableau),
+}
+
+/// Discrete gradient method `x1 = x0 + h S ∇̄H(x0, x1)` for `x' = S ∇H(x)` with a scalar energy `H`.
+///
+/// The gradients come from the AD of `H`. For a skew symmetric structure `S`, e.g. [canonical_structure],
+/// the energy is conserved exactly up to the tolerance of [newton]. A structure whose symmetric part is
+/// negative semidefinite dissipates the energy in every step, as the continuous problem does.
+#[derive(Clone)]
+pub struct DiscreteGradient<Energy>
+where
+    Energy: Fn(ArrayView1<AD>) -> AD,
+{
+    x0_owned: Array1<AD>,
+    hamiltonian: Energy,
+    structure: Array2<f64>,
+    gradient: Gradient,
+    h: AD,
+}
+
+impl<Energy> DiscreteGradient<Energy>
+where
+    Energy: Fn(ArrayView1<AD>) -> AD,
+{
+    pub fn new(h: f64, gradient: Gradient, structure: Array2<f64>, hamiltonian: Energy) -> Self {
+        assert!(
+            structure.is_square(),
+            "The structure matrix has to be square"
+        );
+        DiscreteGradient {
+            x0_owned: array![],
+            hamiltonian,
+            structure,
+            gradient,
+            h: AD::AD0(h),
+        }
+    }
+
+    pub fn gonzalez(h: f64, structure: Array2<f64>, hamiltonian: Energy) -> Self {
+        DiscreteGradient::new(h, Gradient::Gonzalez, structure, hamiltonian)
+    }
+
+    pub fn itoh_abe(h: f64, structure: Array2<f64>, hamiltonian: Energy) -> Self {
+        DiscreteGradient::new(h, Gradient::ItohAbe, structure, hamiltonian)
+    }
+
+    /// The averaged vector field method with three quadrature nodes.
+    pub fn averaged_vector_field(h: f64, structure: Array2<f64>, hamiltonian: Energy) -> Self {
+        let quadrature = Gradient::AveragedVectorField(GaussTableau::new(3));
+        DiscreteGradient::new(h, quadrature, structure, hamiltonian)
+    }
+
+    pub fn energy(&self, x: ArrayView1<f64>) -> f64 {
+        (self.hamiltonian)(x.to_ad().view()).x()
+    }
+
+    fn discrete_gradient(&self, x0: ArrayView1<AD>, x1: ArrayView1<AD>) -> Array1<AD> {
+        let h = &self.hamiltonian;
+        let δ: Array1<AD> = Zip::from(x1).and(x0).map_collect(|&x1, &x0| x1 - x0);
+        // below this squared increment the discrete gradient is the gradient up to rounding
+        let tiny = 1e-24 * (1. + x0.iter().map(|x| x.x() * x.x()).sum::<f64>());
+        match &self.gradient {
+            Gradient::Gonzalez => {
+                let mid: Array1<AD> = Zip::from(x1)
+                    .and(x0)
+                    .map_collect(|&x1, &x0| 0.5 * (x0 + x1));
+                let mut g = gradient(h, mid.view());
+                let norm = dot(&δ, &δ);
+                if norm.x() > tiny {
+                    let correction = (h(x1) - h(x0) - dot(&g, &δ)) / norm;
+                    Zip::from(&mut g)
+                        .and(&δ)
+                        .for_each(|g, &δ| *g = *g + correction * δ);
+                }
+                g
+            }
+            Gradient::ItohAbe => {
+                let mut y = x0.to_owned();
+                let mut h_previous = h(y.view());
+                let mut g = Array1::from_elem(x0.len(), AD::AD0(0.));
+                for i in 0..x0.len() {
+                    y[i] = x1[i];
+                    let h_next = h(y.view());
+                    g[i] = if δ[i].x() * δ[i].x() > tiny {
+                        (h_next - h_previous) / δ[i]
+                    } else {
+                        gradient(h, y.view())[i]
+                    };
+                    h_previous = h_next;
+                }
+                g
+            }
+            Gradient::AveragedVectorField(quadrature) => {
+                let mut g = Array1::from_elem(x0.len(), AD::AD0(0.));
+                for (&ξ, &w) in quadrature.c.iter().zip(quadrature.b.iter()) {
+                    let y: Array1<AD> = Zip::from(x0).and(&δ).map_collect(|&x0, &δ| x0 + ξ * δ);
+                    let gradient = gradient(h, y.view());
+                    Zip::from(&mut g)
+                        .and(&gradient)
+                        .for_each(|g, &dg| *g = *g + w * dg);
+                }
+                g
+            }
+        }
+    }
+}
+
+fn dot(a: &Array1<AD>, b: &Array1<AD>) -> AD {
+    a.iter()
+        .zip(b.iter())
+        .fold(AD::AD0(0.), |s, (&a, &b)| s + a * b)
+}
+
+impl<Energy> Residual for DiscreteGradient<Energy>
+where
+    Energy: Fn(ArrayView1<AD>) -> AD,
+{
+    #[inline]
+    fn eval(&self, x1: ArrayView1<AD>, update: &mut Array1<AD>) {
+        let h = self.h;
+        let x0 = self.x0_owned.view();
+        let g = self.discrete_gradient(x0, x1);
+        for (f, row) in update.iter_mut().zip(self.structure.rows()) {
+            *f = row
+                .iter()
+                .zip(g.iter())
+                .fold(AD::AD0(0.), |s, (&s_ij, &g)| s + s_ij * g);
+        }
+        Zip::from(x1)
+            .and(x0)
+            .and(update)
+            .for_each(|&x1, &x0, f| *f = x1 - x0 - h * *f);
+    }
+}
+
+impl<Energy> Residual1Step for DiscreteGradient<Energy>
+where
+    Energy: Fn(ArrayView1<AD>) -> AD,
+{
+    #[inline]
+    fn update(&mut self, x0: Array1<AD>) {
+        self.x0_owned = x0;
+    }
+}
+
+impl<Energy> Implicit for DiscreteGradient<Energy> where Energy: Fn(ArrayView1<AD>) -> AD {}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2, ArrayView1};
+
+    use crate::{
+        ad::*,
+        ode::{solver::*, *},
+        test_support::*,
+    };
+
+    /// A pendulum whose angle and momentum are coupled, `H = p² / 2 - cos q + q p / 4`, the Itoh-Abe
+    /// method coincides with the averaged vector field method for separable energies.
+    fn pendulum(x: ArrayView1<AD>) -> AD {
+        0.5 * x[1] * x[1] - x[0].cos() + 0.25 * x[0] * x[1]
+    }
+
+    fn run(gradient: &Gradient, structure: &Array2<f64>, h: f64, t: f64) -> Solution {
+        let scheme = DiscreteGradient::new(h, gradient.clone(), structure.clone(), pendulum);
+        let mut ode = Ode::implicit(scheme, array![2.0, 0.0]);
+        ode.set_step_size(h)
+            .set_t(ending_at(t, h))
+            .set_with_progress(false);
+        ode.run()
+    }
+
+    #[test]
+    fn conserves_and_dissipates_energy() {
+        let h = 0.1;
+        let energy = |x: ArrayView1<f64>| pendulum(x.to_ad().view()).x();
+        let canonical = canonical_structure(1);
+        let avf = Gradient::AveragedVectorField(GaussTableau::new(3));
+        let reference = state_at(&run(&avf, &canonical, 1e-3, 1.0), 1.0);
+        let gradients = [(Gradient::Gonzalez, 2.), (Gradient::ItohAbe, 1.), (avf, 2.)];
+        for (gradient, p) in &gradients {
+            let solution = run(gradient, &canonical, h, 20.);
+            let e0 = energy(solution.states.row(0));
+            for (_, x) in solution.iter() {
+                assert!((energy(x) - e0).abs() < 1e-10, "{}", energy(x) - e0);
+            }
+
+            let error = |h| {
+                max_error(
+                    &state_at(&run(gradient, &canonical, h, 1.0), 1.0),
+                    &reference,
+                )
+            };
+            let order = observed_order(error, 0.01);
+            assert!((order - p).abs() < 0.2, "{gradient:?}: {order}");
+        }
+
+        // friction on the momentum
+        let damped = array![[0., 1.], [-1., -0.3]];
+        for (gradient, _) in &gradients {
+            let solution = run(gradient, &damped, h, 10.);
+            let energies: Vec<f64> = solution.iter().map(|(_, x)| energy(x)).collect();
+            assert!(energies.windows(2).all(|e| e[1] <= e[0] + 1e-12));
+            assert!(energies[energies.len() - 1] < energies[0] - 1.);
+        }
+    }
+}
@@ -22,7 +22,7 @@ where
     Flow: Fn(ArrayView1<f64>) -> Array1<f64>,
 {
     #[inline]
-    fn next(&self, x: ArrayView1<f64>) -> Array1<f64> {
+    fn next(&self, x: ArrayView1<f64>) -> ndarray_linalg::error::Result<Array1<f64>> {
         let h = self.h;
         let mut flow = (self.flow)(x);
         // Zip::from(x)
@@ -31,6 +31,6 @@ where
         flow.iter_mut()
             .zip(x.iter())
             .for_each(|(f, &x)| *f = x + h * *f);
-        flow
+        Ok(flow)
     }
 }
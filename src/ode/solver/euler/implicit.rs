@@ -2,6 +2,7 @@ use ndarray::{array, Array1, ArrayView1, Zip};
 
 use crate::{ad::*, ode::*};
 
+#[derive(Clone)]
 pub struct ImplicitEuler<Flow>
 where
     Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
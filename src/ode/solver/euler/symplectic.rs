@@ -1,6 +1,7 @@
 use ndarray::{array, s, Array1, ArrayView1, Axis, Zip};
 
 use crate::{ad::*, ode::*};
+#[derive(Clone)]
 pub struct SymplecticEuler<Flow>
 where
     Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
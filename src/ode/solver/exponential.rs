@@ -0,0 +1,532 @@
+use std::sync::Mutex;
+
+use ndarray::{Array1, Array2, ArrayView1};
+use ndarray_linalg::error::Result;
+
+use crate::{ad::*, ode::*};
+
+use super::{phi::*, ButcherTableau};
+
+/// Products with a large matrix.
+pub type MatrixProduct = Box<dyn Fn(ArrayView1<f64>) -> Array1<f64> + Send + Sync>;
+
+enum Representation {
+    Dense(Array2<f64>),
+    Krylov(MatrixProduct),
+}
+
+/// The stiff linear part `A` of a semilinear problem `x' = A x + N(t, x)`, which applies `φ_k(τ A)` to vectors.
+///
+/// A small `A` is stored dense and its φ-functions are computed by [phi_functions] and kept for the last
+/// few `τ`, a large `A` is given by its products and the φ-functions act in Krylov spaces by [phi_krylov].
+/// A Schrödinger equation enters with the real and imaginary parts stacked, `A = [[0, -H], [H, 0]]`.
+pub struct LinearPart {
+    representation: Representation,
+    max_dim: usize,
+    tolerance: f64,
+    cache: Mutex<Vec<(f64, Vec<Array2<f64>>)>>,
+}
+
+/// Number of `τ` whose dense φ-functions are kept.
+const CACHED: usize = 16;
+
+impl LinearPart {
+    pub fn dense(a: Array2<f64>) -> Self {
+        assert!(a.is_square(), "The linear part has to be square");
+        LinearPart::new(Representation::Dense(a))
+    }
+
+    pub fn krylov(apply: impl Fn(ArrayView1<f64>) -> Array1<f64> + Send + Sync + 'static) -> Self {
+        LinearPart::new(Representation::Krylov(Box::new(apply)))
+    }
+
+    fn new(representation: Representation) -> Self {
+        LinearPart {
+            representation,
+            max_dim: 60,
+            tolerance: 1e-12,
+            cache: Mutex::new(vec![]),
+        }
+    }
+
+    /// Largest dimension of the Krylov spaces.
+    pub fn set_krylov_dimension(&mut self, max_dim: usize) -> &mut Self {
+        self.max_dim = max_dim;
+        self
+    }
+
+    /// Relative tolerance of the Krylov approximations.
+    pub fn set_krylov_tolerance(&mut self, tolerance: f64) -> &mut Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn apply(&self, v: ArrayView1<f64>) -> Array1<f64> {
+        match &self.representation {
+            Representation::Dense(a) => a.dot(&v),
+            Representation::Krylov(apply) => apply(v),
+        }
+    }
+
+    /// `Σ_k φ_k(τ A) v_k`, skipping vanishing vectors.
+    pub fn phi(&self, τ: f64, v: &[&Array1<f64>]) -> Array1<f64> {
+        let p = v.len() - 1;
+        let mut result = Array1::zeros(v[0].len());
+        if τ == 0. {
+            // φ_k(0) = 1 / k!
+            let mut factorial = 1.;
+            for (k, v) in v.iter().enumerate() {
+                factorial *= k.max(1) as f64;
+                result.scaled_add(1. / factorial, *v);
+            }
+            return result;
+        }
+        match &self.representation {
+            Representation::Dense(a) => {
+                let mut cache = self.cache.lock().unwrap();
+                let index = match cache.iter().position(|(σ, φ)| *σ == τ && φ.len() > p) {
+                    Some(index) => index,
+                    None => {
+                        if cache.len() == CACHED {
+                            cache.remove(0);
+                        }
+                        cache.push((τ, phi_functions((τ * a).view(), p)));
+                        cache.len() - 1
+                    }
+                };
+                for (φ, v) in cache[index].1.iter().zip(v) {
+                    result += &φ.dot(*v);
+                }
+            }
+            Representation::Krylov(apply) => {
+                for (k, v) in v.iter().enumerate() {
+                    if v.iter().any(|&v| v != 0.) {
+                        let φ = phi_krylov(apply, v.view(), τ, k, self.max_dim, self.tolerance);
+                        result += &φ[k];
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+/// The exponential Euler method or ETD1 `x1 = e^(h A) x0 + h φ_1(h A) N(t0, x0)`, exact for constant `N`.
+///
+/// The correction of the second order ETD2RK method of Cox and Matthews serves as error estimate.
+pub struct Etd1<Nonlinear>
+where
+    Nonlinear: Fn(f64, ArrayView1<f64>) -> Array1<f64>,
+{
+    linear: LinearPart,
+    nonlinear: Nonlinear,
+}
+
+impl<Nonlinear> Etd1<Nonlinear>
+where
+    Nonlinear: Fn(f64, ArrayView1<f64>) -> Array1<f64>,
+{
+    pub fn new(linear: LinearPart, nonlinear: Nonlinear) -> Self {
+        Etd1 { linear, nonlinear }
+    }
+}
+
+impl<Nonlinear> Embedded for Etd1<Nonlinear>
+where
+    Nonlinear: Fn(f64, ArrayView1<f64>) -> Array1<f64>,
+{
+    fn order(&self) -> usize {
+        1
+    }
+
+    fn embedded_order(&self) -> usize {
+        2
+    }
+
+    fn next(
+        &mut self,
+        t: f64,
+        x: ArrayView1<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> Result<EmbeddedStep> {
+        let x = x.to_owned();
+        let n0 = (self.nonlinear)(t, x.view());
+        let x1 = self.linear.phi(h, &[&x, &(h * &n0)]);
+        let n1 = (self.nonlinear)(t + h, x1.view());
+        stats.rhs_evaluations += 2;
+        let zero = Array1::zeros(x.len());
+        let error = self.linear.phi(h, &[&zero, &zero, &(h * (n1 - n0))]);
+        Ok(EmbeddedStep { x: x1, error })
+    }
+}
+
+/// The fourth order exponential time differencing method ETDRK4 of Cox and Matthews.
+///
+/// The error estimate is the difference to ETD2RK with the last stage, which is only of second order,
+/// so the step size control uses that order.
+pub struct Etdrk4<Nonlinear>
+where
+    Nonlinear: Fn(f64, ArrayView1<f64>) -> Array1<f64>,
+{
+    linear: LinearPart,
+    nonlinear: Nonlinear,
+}
+
+impl<Nonlinear> Etdrk4<Nonlinear>
+where
+    Nonlinear: Fn(f64, ArrayView1<f64>) -> Array1<f64>,
+{
+    pub fn new(linear: LinearPart, nonlinear: Nonlinear) -> Self {
+        Etdrk4 { linear, nonlinear }
+    }
+}
+
+impl<Nonlinear> Embedded for Etdrk4<Nonlinear>
+where
+    Nonlinear: Fn(f64, ArrayView1<f64>) -> Array1<f64>,
+{
+    fn order(&self) -> usize {
+        4
+    }
+
+    fn embedded_order(&self) -> usize {
+        2
+    }
+
+    fn next(
+        &mut self,
+        t: f64,
+        x: ArrayView1<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> Result<EmbeddedStep> {
+        let (linear, nonlinear) = (&self.linear, &self.nonlinear);
+        let x = x.to_owned();
+        let n_x = nonlinear(t, x.view());
+        let a = linear.phi(h / 2., &[&x, &(h / 2. * &n_x)]);
+        let n_a = nonlinear(t + h / 2., a.view());
+        let b = linear.phi(h / 2., &[&x, &(h / 2. * &n_a)]);
+        let n_b = nonlinear(t + h / 2., b.view());
+        let c = linear.phi(h / 2., &[&a, &(h / 2. * (2. * &n_b - &n_x))]);
+        let n_c = nonlinear(t + h, c.view());
+        stats.rhs_evaluations += 4;
+
+        let n_ab = &n_a + &n_b;
+        let v2 = h * (-3. * &n_x + 2. * &n_ab - &n_c);
+        let v3 = 4. * h * (&n_x - &n_ab + &n_c);
+        let x1 = linear.phi(h, &[&x, &(h * &n_x), &v2, &v3]);
+        let zero = Array1::zeros(x.len());
+        let error = linear.phi(h, &[&zero, &zero, &(v2 - h * (&n_c - &n_x)), &v3]);
+        Ok(EmbeddedStep { x: x1, error })
+    }
+}
+
+/// Lawson or integrating factor Runge-Kutta method, an explicit [ButcherTableau] applied to `e^(-t A) x`.
+///
+/// It keeps the order of the tableau for smooth solutions and is exact for `N = 0`. The nodes of the
+/// tableau have to increase, so that only exponentials of `A` for positive times are needed.
+/// A tableau without embedded method gives a vanishing error estimate, then the steps are fixed to
+/// the maximal step size of the [StepController].
+pub struct Lawson<Nonlinear>
+where
+    Nonlinear: Fn(f64, ArrayView1<f64>) -> Array1<f64>,
+{
+    tableau: ButcherTableau,
+    linear: LinearPart,
+    nonlinear: Nonlinear,
+}
+
+impl<Nonlinear> Lawson<Nonlinear>
+where
+    Nonlinear: Fn(f64, ArrayView1<f64>) -> Array1<f64>,
+{
+    pub fn new(tableau: ButcherTableau, linear: LinearPart, nonlinear: Nonlinear) -> Self {
+        assert!(
+            tableau.c.windows(2).into_iter().all(|c| c[0] <= c[1]),
+            "Lawson methods need increasing nodes"
+        );
+        Lawson {
+            tableau,
+            linear,
+            nonlinear,
+        }
+    }
+}
+
+impl<Nonlinear> Embedded for Lawson<Nonlinear>
+where
+    Nonlinear: Fn(f64, ArrayView1<f64>) -> Array1<f64>,
+{
+    fn order(&self) -> usize {
+        self.tableau.order
+    }
+
+    fn next(
+        &mut self,
+        t: f64,
+        x: ArrayView1<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> Result<EmbeddedStep> {
+        let ButcherTableau { a, b, b_hat, c, .. } = &self.tableau;
+        let x = x.to_owned();
+        // e^(τ h A) v
+        let exp = |τ: f64, v: &Array1<f64>| self.linear.phi(τ * h, &[v]);
+        let mut k: Vec<Array1<f64>> = Vec::with_capacity(c.len());
+        for i in 0..c.len() {
+            let mut stage = exp(c[i], &x);
+            for (j, k) in k.iter().enumerate() {
+                if a[[i, j]] != 0. {
+                    stage.scaled_add(h * a[[i, j]], &exp(c[i] - c[j], k));
+                }
+            }
+            k.push((self.nonlinear)(t + c[i] * h, stage.view()));
+        }
+        stats.rhs_evaluations += c.len();
+
+        let mut x1 = exp(1., &x);
+        let mut error = Array1::zeros(x.len());
+        for (j, k) in k.iter().enumerate() {
+            let k = exp(1. - c[j], k);
+            x1.scaled_add(h * b[j], &k);
+            if let Some(b_hat) = b_hat {
+                error.scaled_add(h * (b[j] - b_hat[j]), &k);
+            }
+        }
+        Ok(EmbeddedStep { x: x1, error })
+    }
+}
+
+/// The exponential Rosenbrock method exprb32 of Hochbruck, Ostermann and Schweitzer for autonomous problems.
+///
+/// The problem is linearized in each step at `x0` with `J = A + N'(x0)` from automatic differentiation,
+/// so it needs no splitting into a stiff linear part at all:
+/// `u = x0 + h φ_1(h J) f(x0)`, `x1 = u + 2 h φ_3(h J) (g(u) - g(x0))` with the remainder `g(x) = f(x) - J x`.
+/// The exponential Rosenbrock-Euler solution `u` of second order serves as embedded solution.
+/// For a dense linear part the φ-functions of `h J` are computed anew in every step, otherwise the products
+/// with `N'(x0)` are directional derivatives.
+pub struct ExponentialRosenbrock<Nonlinear>
+where
+    Nonlinear: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    linear: LinearPart,
+    nonlinear: Nonlinear,
+}
+
+impl<Nonlinear> ExponentialRosenbrock<Nonlinear>
+where
+    Nonlinear: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    pub fn new(linear: LinearPart, nonlinear: Nonlinear) -> Self {
+        ExponentialRosenbrock { linear, nonlinear }
+    }
+
+    /// `N'(x) v` as derivative of `N` in the direction `v`.
+    fn directional(&self, x: ArrayView1<f64>, v: ArrayView1<f64>) -> Array1<f64> {
+        let x: Array1<AD> = x
+            .iter()
+            .zip(v.iter())
+            .map(|(&x, &v)| AD::AD1(x, v))
+            .collect();
+        let mut f = x.clone();
+        (self.nonlinear)(x.view(), &mut f);
+        f.iter().map(|f| f.dx()).collect()
+    }
+}
+
+impl<Nonlinear> Embedded for ExponentialRosenbrock<Nonlinear>
+where
+    Nonlinear: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    fn order(&self) -> usize {
+        3
+    }
+
+    #[allow(non_snake_case)]
+    fn next(
+        &mut self,
+        _t: f64,
+        x: ArrayView1<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> Result<EmbeddedStep> {
+        let f = |x: ArrayView1<f64>| self.linear.apply(x) + eval_flow(&self.nonlinear, x);
+        let f0 = f(x);
+        let (u, d) = match &self.linear.representation {
+            Representation::Dense(a) => {
+                stats.jacobian_evaluations += 1;
+                let J = a + &flow_jacobian(&self.nonlinear, x);
+                let φ = phi_functions((h * &J).view(), 3);
+                let u = &x + &(h * φ[1].dot(&f0));
+                // g(u) - g(x0) = f(u) - f(x0) - J (u - x0)
+                let d = f(u.view()) - &f0 - J.dot(&(&u - &x));
+                (u, 2. * h * φ[3].dot(&d))
+            }
+            Representation::Krylov(apply) => {
+                let J = |v: ArrayView1<f64>| apply(v) + self.directional(x, v);
+                let (max_dim, tolerance) = (self.linear.max_dim, self.linear.tolerance);
+                let u = &x + &(h * &phi_krylov(J, f0.view(), h, 1, max_dim, tolerance)[1]);
+                let d = f(u.view()) - &f0 - J((&u - &x).view());
+                let φ = phi_krylov(J, d.view(), h, 3, max_dim, tolerance);
+                (u, 2. * h * &φ[3])
+            }
+        };
+        stats.rhs_evaluations += 2;
+        Ok(EmbeddedStep {
+            x: &u + &d,
+            error: d,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, Array2, ArrayView1};
+
+    use crate::{
+        ad::{TrigOps, AD},
+        ode::{solver::*, *},
+        test_support::*,
+    };
+
+    /// The pendulum `q'' = -sin q` split into the harmonic oscillator and the remainder.
+    fn oscillator() -> Array2<f64> {
+        array![[0., 1.], [-1., 0.]]
+    }
+
+    fn remainder(_t: f64, x: ArrayView1<f64>) -> Array1<f64> {
+        array![0., x[0] - x[0].sin()]
+    }
+
+    fn remainder_ad(x: ArrayView1<AD>, f: &mut Array1<AD>) {
+        f[0] = AD::AD0(0.);
+        f[1] = x[0] - x[0].sin();
+    }
+
+    fn fixed(mut scheme: impl Embedded, h: f64) -> Array1<f64> {
+        fixed_steps(&mut scheme, array![1.0, 0.0].view(), h).0
+    }
+
+    impl Embedded for Box<dyn Embedded> {
+        fn order(&self) -> usize {
+            (**self).order()
+        }
+
+        fn embedded_order(&self) -> usize {
+            (**self).embedded_order()
+        }
+
+        fn next(
+            &mut self,
+            t: f64,
+            x: ArrayView1<f64>,
+            h: f64,
+            stats: &mut SolverStats,
+        ) -> ndarray_linalg::error::Result<EmbeddedStep> {
+            (**self).next(t, x, h, stats)
+        }
+    }
+
+    type Constructor<'a> = Box<dyn Fn() -> Box<dyn Embedded> + 'a>;
+
+    #[test]
+    fn phi_functions_and_orders() {
+        // e^(t A) of the oscillator is the rotation, φ_1 its integral
+        let phi = phi_functions(oscillator().view(), 1);
+        let (c, s) = (1f64.cos(), 1f64.sin());
+        assert!((&phi[0] - &array![[c, s], [-s, c]])
+            .iter()
+            .all(|e| e.abs() < 1e-14));
+        assert!((&phi[1] - &array![[s, 1. - c], [c - 1., s]])
+            .iter()
+            .all(|e| e.abs() < 1e-14));
+
+        let dense = || LinearPart::dense(oscillator());
+        let krylov = || LinearPart::krylov(|x| oscillator().dot(&x));
+        let reference = fixed(
+            Lawson::new(ButcherTableau::dormand_prince(), dense(), remainder),
+            1e-3,
+        );
+        let cases: [(Constructor<'_>, f64, f64); 5] = [
+            (
+                Box::new(|| Box::new(Etd1::new(dense(), remainder))),
+                0.01,
+                1.,
+            ),
+            (
+                Box::new(|| Box::new(Etdrk4::new(dense(), remainder))),
+                0.1,
+                4.,
+            ),
+            (
+                Box::new(|| Box::new(Etdrk4::new(krylov(), remainder))),
+                0.1,
+                4.,
+            ),
+            (
+                Box::new(|| Box::new(Lawson::new(ButcherTableau::rk4(), dense(), remainder))),
+                0.1,
+                4.,
+            ),
+            (
+                Box::new(|| Box::new(ExponentialRosenbrock::new(krylov(), remainder_ad))),
+                0.1,
+                3.,
+            ),
+        ];
+        for (scheme, h, p) in cases {
+            let order = observed_order(|h| max_error(&fixed(scheme(), h), &reference), h);
+            assert!((order - p).abs() < 0.3, "{p}: {order}");
+            // the estimate is the local error of the lower order solution, a Lawson method has none
+            let estimate = |h| fixed_steps(&mut scheme(), array![1.0, 0.0].view(), h).1;
+            if estimate(h) > 0. {
+                let q = scheme().order().min(scheme().embedded_order()) as f64;
+                let order = observed_order(estimate, h);
+                assert!((order - q - 1.).abs() < 0.3, "{q}: {order}");
+            }
+        }
+    }
+
+    const N: usize = 20;
+
+    /// The Laplacian on `[0, 1]` with `u = 0` at both ends by central differences.
+    fn laplacian(u: ArrayView1<f64>) -> Array1<f64> {
+        let m = (N + 1) as f64;
+        Array1::from_shape_fn(N, |i| {
+            let left = if i > 0 { u[i - 1] } else { 0. };
+            let right = if i + 1 < N { u[i + 1] } else { 0. };
+            (left - 2. * u[i] + right) * m * m
+        })
+    }
+
+    #[test]
+    fn stiff_heat_equation() {
+        let initial = Array1::from_shape_fn(N, |i| {
+            let x = (i + 1) as f64 / (N + 1) as f64;
+            (std::f64::consts::PI * x).sin()
+        });
+        let m = (N + 1) as f64;
+        let λ = 2. * m * m * (1. - (std::f64::consts::PI / m).cos());
+        // the sine mode decays with λ, which the exponential of the linear part captures exactly
+        let dense = Array2::from_shape_fn((N, N), |(i, j)| match i.abs_diff(j) {
+            0 => -2. * m * m,
+            1 => m * m,
+            _ => 0.,
+        });
+        for linear in [LinearPart::dense(dense), LinearPart::krylov(laplacian)] {
+            let scheme = Etdrk4::new(linear, |_, u: ArrayView1<f64>| 0.5 * &u);
+            let mut ode = Ode::adaptive(scheme, initial.clone());
+            ode.set_tolerances(1e-6, 1e-6)
+                .set_step_size(0.1)
+                .set_t(0.5)
+                .set_with_progress(false);
+            let solution = ode.run();
+            assert_eq!(solution.termination, Termination::Completed);
+            assert!(solution.stats.steps < 100, "{}", solution.stats.steps);
+            let (t, u) = solution.iter().last().unwrap();
+            let exact = &initial * (-(λ - 0.5) * t).exp();
+            assert!((&u - &exact).iter().all(|e| e.abs() < 1e-6));
+        }
+    }
+}
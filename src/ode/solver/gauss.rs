@@ -0,0 +1,311 @@
+use std::sync::Mutex;
+
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use ndarray_linalg::{error::Result, Factorize, Inverse, Solve};
+
+use crate::{ad::*, ode::*};
+
+/// Nodes, weights and coefficients of the `s` stage Gauss-Legendre method of order `2s`.
+#[derive(Debug, Clone)]
+pub struct GaussTableau {
+    pub a: Array2<f64>,
+    pub b: Array1<f64>,
+    pub c: Array1<f64>,
+}
+
+impl GaussTableau {
+    /// The nodes are the roots of the Legendre polynomial `P_s` mapped to `[0, 1]`, `a` and `b` integrate
+    /// the Lagrange polynomials of the nodes over `[0, c_i]` and `[0, 1]`.
+    pub fn new(s: usize) -> Self {
+        assert!(s > 0, "Gauss-Legendre methods need at least one stage");
+        let c: Array1<f64> = legendre_roots(s).mapv(|ξ| (1. + ξ) / 2.);
+        // Σ_j a_ij c_j^k = c_i^(k+1) / (k+1) for k < s, which is exact for polynomials of degree s - 1
+        let vandermonde = Array2::from_shape_fn((s, s), |(j, k)| c[j].powi(k as i32));
+        let inverse = vandermonde
+            .inv()
+            .expect("The Gauss nodes are distinct, so the Vandermonde matrix is regular");
+        let integrals =
+            Array2::from_shape_fn((s, s), |(i, k)| c[i].powi(k as i32 + 1) / (k + 1) as f64);
+        let a = integrals.dot(&inverse);
+        let moments = Array1::from_shape_fn(s, |k| 1. / (k + 1) as f64);
+        let b = inverse.t().dot(&moments);
+        GaussTableau { a, b, c }
+    }
+
+    pub fn stages(&self) -> usize {
+        self.c.len()
+    }
+
+    pub fn order(&self) -> usize {
+        2 * self.stages()
+    }
+}
+
+/// Roots of the Legendre polynomial `P_s` in increasing order, by Newton's method from Chebyshev like guesses.
+fn legendre_roots(s: usize) -> Array1<f64> {
+    let mut roots: Vec<f64> = (0..s)
+        .map(|i| {
+            let mut ξ = (std::f64::consts::PI * (i as f64 + 0.75) / (s as f64 + 0.5)).cos();
+            for _ in 0..100 {
+                // P_k from the three term recurrence, dP from the derivative identity
+                let (mut p, mut p_prev) = (ξ, 1.);
+                for k in 2..=s {
+                    let k = k as f64;
+                    (p, p_prev) = (((2. * k - 1.) * ξ * p - (k - 1.) * p_prev) / k, p);
+                }
+                let dp = s as f64 * (ξ * p - p_prev) / (ξ * ξ - 1.);
+                let δ = p / dp;
+                ξ -= δ;
+                if δ.abs() < 1e-16 {
+                    break;
+                }
+            }
+            ξ
+        })
+        .collect();
+    roots.sort_by(f64::total_cmp);
+    Array1::from(roots)
+}
+
+/// The collocation polynomial of the last step, as the stage increments `z_i = Z_i - x0`.
+struct LastStep {
+    x1: Array1<f64>,
+    h: f64,
+    z: Array2<f64>,
+}
+
+/// The `s` stage Gauss-Legendre collocation method of order `2s`, symplectic and A-stable.
+///
+/// The stage equations `z_i = h Σ_j a_ij f(x0 + z_j)` are solved by a simplified Newton iteration with the
+/// Jacobian of the flow at `x0`, so one Jacobian and one LU factorization of size `s n` are needed per step.
+/// A step which continues the last one starts from the extrapolated collocation polynomial of that step.
+///
+/// Each step is a self-contained map from `x0` to `x1`, so it is driven by [Ode::explicit]. A singular
+/// iteration matrix fails the step.
+pub struct GaussLegendre<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    tableau: GaussTableau,
+    /// `d = b^T A^-1` gives `x1 = x0 + Σ d_i z_i` without another evaluation of the flow.
+    d: Array1<f64>,
+    flow: Flow,
+    h: f64,
+    ɛ: f64,
+    max_iter: usize,
+    last: Mutex<Option<LastStep>>,
+    evaluations: Mutex<usize>,
+    failures: Mutex<usize>,
+}
+
+impl<Flow> GaussLegendre<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    pub fn new(h: f64, stages: usize, flow: Flow) -> Self {
+        let tableau = GaussTableau::new(stages);
+        let d = tableau.a.t().inv().expect("A is regular").dot(&tableau.b);
+        GaussLegendre {
+            tableau,
+            d,
+            flow,
+            h,
+            ɛ: 1e-14,
+            max_iter: 50,
+            last: Mutex::new(None),
+            evaluations: Mutex::new(0),
+            failures: Mutex::new(0),
+        }
+    }
+
+    /// Relative tolerance of the stage increments, near the machine precision to keep the method symplectic.
+    pub fn set_epsilon(&mut self, ɛ: f64) -> &mut Self {
+        self.ɛ = ɛ;
+        self
+    }
+
+    pub fn set_max_iter(&mut self, max_iter: usize) -> &mut Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn tableau(&self) -> &GaussTableau {
+        &self.tableau
+    }
+
+    /// Stage increments from the collocation polynomial of the last step, if this step continues it.
+    fn initial_guess(&self, x0: ArrayView1<f64>) -> Option<Array2<f64>> {
+        let last = self.last.lock().unwrap();
+        let last = last.as_ref().filter(|last| last.x1 == x0)?;
+        let c = &self.tableau.c;
+        let s = c.len();
+        // Lagrange interpolation of the increments through τ = 0 and the nodes of the last step
+        let nodes: Vec<f64> = std::iter::once(0.).chain(c.iter().copied()).collect();
+        let values: Vec<ArrayView1<f64>> = last.z.axis_iter(Axis(0)).collect();
+        let interpolate = |τ: f64| {
+            let mut w = Array1::zeros(x0.len());
+            for j in 1..=s {
+                let l: f64 = (0..=s)
+                    .filter(|&m| m != j)
+                    .map(|m| (τ - nodes[m]) / (nodes[j] - nodes[m]))
+                    .product();
+                w.scaled_add(l, &values[j - 1]);
+            }
+            w
+        };
+        let r = self.h / last.h;
+        let end = interpolate(1.);
+        let mut z = Array2::zeros((s, x0.len()));
+        for (i, mut row) in z.axis_iter_mut(Axis(0)).enumerate() {
+            row.assign(&(interpolate(1. + c[i] * r) - &end));
+        }
+        Some(z)
+    }
+}
+
+impl<Flow> Explicit for GaussLegendre<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    #[allow(non_snake_case)]
+    fn next(&self, x0: ArrayView1<f64>) -> Result<Array1<f64>> {
+        let (h, n, s) = (self.h, x0.len(), self.tableau.stages());
+        let a = &self.tableau.a;
+        let J = flow_jacobian(&self.flow, x0);
+        // I - h A ⊗ J
+        let M = Array2::from_shape_fn((s * n, s * n), |(p, q)| {
+            let (i, k, j, l) = (p / n, p % n, q / n, q % n);
+            let identity = if p == q { 1. } else { 0. };
+            identity - h * a[[i, j]] * J[[k, l]]
+        });
+        let lu = M.factorize()?;
+
+        let mut z = self
+            .initial_guess(x0)
+            .unwrap_or_else(|| Array2::zeros((s, n)));
+        let scale = 1. + x0.iter().fold(0., |m: f64, x| m.max(x.abs()));
+        let mut evaluations = 0;
+        let mut previous = f64::INFINITY;
+        let mut converged = false;
+        for _ in 0..self.max_iter {
+            let f = Array2::from_shape_vec(
+                (s, n),
+                z.axis_iter(Axis(0))
+                    .flat_map(|z| eval_flow(&self.flow, (&x0 + &z).view()))
+                    .collect(),
+            )
+            .unwrap();
+            evaluations += s;
+            let residual = h * a.dot(&f) - &z;
+            let δ = lu.solve(&Array1::from_iter(residual.iter().copied()))?;
+            let norm = δ.iter().fold(0., |m: f64, δ| m.max(δ.abs()));
+            z += &δ.into_shape((s, n)).unwrap();
+            // increments at the rounding level of the state cannot get below an even smaller ɛ
+            if norm <= self.ɛ.max(64. * f64::EPSILON) * scale {
+                converged = true;
+                break;
+            }
+            // stagnation above it, the iteration does not converge
+            if norm >= previous {
+                break;
+            }
+            previous = norm;
+        }
+
+        let x1 = &x0 + &self.d.dot(&z);
+        *self.evaluations.lock().unwrap() = evaluations;
+        *self.failures.lock().unwrap() = usize::from(!converged);
+        *self.last.lock().unwrap() = Some(LastStep {
+            x1: x1.clone(),
+            h,
+            z,
+        });
+        Ok(x1)
+    }
+
+    /// Evaluations in the last call of [Explicit::next], which depend on the iterations of the stage solve.
+    fn evaluations(&self) -> usize {
+        *self.evaluations.lock().unwrap()
+    }
+
+    /// One if the stage iteration of the last call ran out of `max_iter` iterations or stagnated.
+    fn newton_failures(&self) -> usize {
+        *self.failures.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{
+        ad::AD,
+        ode::{solver::*, *},
+        test_support::*,
+    };
+
+    fn oscillator(x: ArrayView1<AD>, f: &mut Array1<AD>) {
+        f[0] = x[1];
+        f[1] = -x[0];
+    }
+
+    fn run(stages: usize, h: f64) -> Solution {
+        let mut ode = Ode::explicit(GaussLegendre::new(h, stages, oscillator), array![1.0, 0.0]);
+        ode.set_step_size(h)
+            .set_t(2.0 + h / 2.)
+            .set_with_progress(false);
+        ode.run()
+    }
+
+    fn error(stages: usize, h: f64) -> f64 {
+        let solution = run(stages, h);
+        let (t, x) = solution.iter().last().unwrap();
+        (x[0] - t.cos()).abs()
+    }
+
+    #[test]
+    fn order_and_quadratic_invariant() {
+        let tableau = GaussTableau::new(3);
+        assert!((tableau.b.sum() - 1.).abs() < 1e-15);
+        assert!((tableau.c[1] - 0.5).abs() < 1e-15);
+
+        for (stages, h) in [(1, 0.02), (2, 0.1), (3, 0.4)] {
+            let order = observed_order(|h| error(stages, h), h);
+            assert!(
+                (order - (2 * stages) as f64).abs() < 0.2,
+                "{stages}: {order}"
+            );
+        }
+
+        let solution = run(4, 0.1);
+        for (_, x) in solution.iter() {
+            assert!((x[0] * x[0] + x[1] * x[1] - 1.).abs() < 1e-13);
+        }
+        assert_eq!(solution.stats.newton_failures, 0);
+    }
+
+    #[test]
+    fn singular_newton_matrix_fails() {
+        // I - h a_11 J = 1 - 0.5 · 0.5 · 4 vanishes for the midpoint rule
+        let growth = |x: ArrayView1<AD>, f: &mut Array1<AD>| f[0] = x[0] * 4.;
+        let mut ode = Ode::explicit(GaussLegendre::new(0.5, 1, growth), array![1.0]);
+        ode.set_step_size(0.5).set_t(2.0).set_with_progress(false);
+        let solution = ode.run();
+        assert!(matches!(
+            solution.termination,
+            Termination::Failed { t, .. } if t == 0.
+        ));
+        assert_eq!(solution.len(), 1);
+    }
+
+    #[test]
+    fn unconverged_stages_are_counted() {
+        let mut gauss = GaussLegendre::new(0.5, 2, pendulum);
+        gauss.set_max_iter(1);
+        let mut ode = Ode::explicit(gauss, array![1.0, 0.0]);
+        ode.set_step_size(0.5).set_t(2.25).set_with_progress(false);
+        let solution = ode.run();
+        // a single iteration cannot converge in any of the three steps
+        assert_eq!(solution.stats.newton_failures, 3);
+    }
+}
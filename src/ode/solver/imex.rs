@@ -0,0 +1,437 @@
+use ndarray::{array, Array1, Array2, ArrayView1};
+use ndarray_linalg::{error::Result, Factorize};
+
+use crate::{ad::*, ode::*};
+
+use super::sdirk::Stage;
+
+/// Pair of Butcher tableaus of an additive Runge-Kutta method for `x' = f_E(t, x) + f_I(t, x)`.
+///
+/// The explicit tableau is applied to the non stiff part `f_E`, the ESDIRK tableau to the stiff part `f_I`.
+/// Both share the nodes `c`, the weights `b_hat` give an embedded method for the error estimate.
+#[derive(Debug, Clone)]
+pub struct ArkTableau {
+    pub a_explicit: Array2<f64>,
+    pub a_implicit: Array2<f64>,
+    pub b_explicit: Array1<f64>,
+    pub b_implicit: Array1<f64>,
+    pub b_hat_explicit: Array1<f64>,
+    pub b_hat_implicit: Array1<f64>,
+    pub c: Array1<f64>,
+    pub order: usize,
+}
+
+impl ArkTableau {
+    /// `x1 = x0 + h f_E(t0, x0) + h f_I(t1, x1)`, the difference to the explicit Euler for both parts
+    /// serves as error estimate.
+    pub fn imex_euler() -> Self {
+        ArkTableau {
+            a_explicit: array![[0., 0.], [1., 0.]],
+            a_implicit: array![[0., 0.], [0., 1.]],
+            b_explicit: array![1., 0.],
+            b_implicit: array![0., 1.],
+            b_hat_explicit: array![1., 0.],
+            b_hat_implicit: array![1., 0.],
+            c: array![0., 1.],
+            order: 1,
+        }
+    }
+
+    /// ARK3(2)4L[2]SA of Kennedy and Carpenter, third order with four stages, the implicit part is L-stable.
+    pub fn ark324l() -> Self {
+        let γ = 1767732205903. / 4055673282236.;
+        let c2 = 1767732205903. / 2027836641118.;
+        let b = array![
+            1471266399579. / 7840856788654.,
+            -4482444167858. / 7529755066697.,
+            11266239266428. / 11593286722821.,
+            γ
+        ];
+        let b_hat = array![
+            2756255671327. / 12835298489170.,
+            -10771552573575. / 22201958757719.,
+            9247589265047. / 10645013368117.,
+            2193209047091. / 5459859503100.
+        ];
+        ArkTableau {
+            a_explicit: array![
+                [0., 0., 0., 0.],
+                [c2, 0., 0., 0.],
+                [
+                    5535828885825. / 10492691773637.,
+                    788022342437. / 10882634858940.,
+                    0.,
+                    0.
+                ],
+                [
+                    6485989280629. / 16251701735622.,
+                    -4246266847089. / 9704473918619.,
+                    10755448449292. / 10357097424841.,
+                    0.
+                ]
+            ],
+            a_implicit: array![
+                [0., 0., 0., 0.],
+                [γ, γ, 0., 0.],
+                [
+                    2746238789719. / 10658868560708.,
+                    -640167445237. / 6845629431997.,
+                    γ,
+                    0.
+                ],
+                [b[0], b[1], b[2], b[3]]
+            ],
+            b_explicit: b.clone(),
+            b_implicit: b,
+            b_hat_explicit: b_hat.clone(),
+            b_hat_implicit: b_hat,
+            c: array![0., c2, 3. / 5., 1.],
+            order: 3,
+        }
+    }
+
+    /// ARK4(3)6L[2]SA of Kennedy and Carpenter, fourth order with six stages, the implicit part is L-stable.
+    pub fn ark436l() -> Self {
+        let b = array![
+            82889. / 524892.,
+            0.,
+            15625. / 83664.,
+            69875. / 102672.,
+            -2260. / 8211.,
+            1. / 4.
+        ];
+        let b_hat = array![
+            4586570599. / 29645900160.,
+            0.,
+            178811875. / 945068544.,
+            814220225. / 1159782912.,
+            -3700637. / 11593932.,
+            61727. / 225920.
+        ];
+        ArkTableau {
+            a_explicit: array![
+                [0., 0., 0., 0., 0., 0.],
+                [1. / 2., 0., 0., 0., 0., 0.],
+                [13861. / 62500., 6889. / 62500., 0., 0., 0., 0.],
+                [
+                    -116923316275. / 2393684061468.,
+                    -2731218467317. / 15368042101831.,
+                    9408046702089. / 11113171139209.,
+                    0.,
+                    0.,
+                    0.
+                ],
+                [
+                    -451086348788. / 2902428689909.,
+                    -2682348792572. / 7519795681897.,
+                    12662868775082. / 11960479115383.,
+                    3355817975965. / 11060851509271.,
+                    0.,
+                    0.
+                ],
+                [
+                    647845179188. / 3216320057751.,
+                    73281519250. / 8382639484533.,
+                    552539513391. / 3454668386233.,
+                    3354512671639. / 8306763924573.,
+                    4040. / 17871.,
+                    0.
+                ]
+            ],
+            a_implicit: array![
+                [0., 0., 0., 0., 0., 0.],
+                [1. / 4., 1. / 4., 0., 0., 0., 0.],
+                [8611. / 62500., -1743. / 31250., 1. / 4., 0., 0., 0.],
+                [
+                    5012029. / 34652500.,
+                    -654441. / 2922500.,
+                    174375. / 388108.,
+                    1. / 4.,
+                    0.,
+                    0.
+                ],
+                [
+                    15267082809. / 155376265600.,
+                    -71443401. / 120774400.,
+                    730878875. / 902184768.,
+                    2285395. / 8070912.,
+                    1. / 4.,
+                    0.
+                ],
+                [b[0], b[1], b[2], b[3], b[4], b[5]]
+            ],
+            b_explicit: b.clone(),
+            b_implicit: b,
+            b_hat_explicit: b_hat.clone(),
+            b_hat_implicit: b_hat,
+            c: array![0., 1. / 2., 83. / 250., 31. / 50., 17. / 20., 1.],
+            order: 4,
+        }
+    }
+
+    pub fn stages(&self) -> usize {
+        self.c.len()
+    }
+
+    /// The diagonal coefficient shared by the implicit stages.
+    pub fn gamma(&self) -> f64 {
+        self.a_implicit[[self.stages() - 1, self.stages() - 1]]
+    }
+
+    /// Whether the explicit slope of `stage` enters a later stage or the solution.
+    fn uses_explicit(&self, stage: usize) -> bool {
+        self.a_explicit.column(stage).iter().any(|&a| a != 0.)
+            || self.b_explicit[stage] != 0.
+            || self.b_hat_explicit[stage] != 0.
+    }
+}
+
+/// Implicit-explicit additive Runge-Kutta method for `x' = f_E(t, x) + f_I(t, x)` with adaptive steps,
+/// driven by [Ode::adaptive].
+///
+/// The non stiff part `f_E` is evaluated on [f64], e.g. a reaction term, the stiff part `f_I`, e.g. a diffusion term,
+/// on [AD] for its Jacobian. The implicit stages are solved as for [Sdirk], with one Jacobian of `f_I` and one
+/// LU factorization per step.
+pub struct Imex<FlowE, FlowI>
+where
+    FlowE: Fn(f64, ArrayView1<f64>) -> Array1<f64>,
+    FlowI: Fn(f64, ArrayView1<AD>, &mut Array1<AD>),
+{
+    tableau: ArkTableau,
+    explicit: FlowE,
+    implicit: FlowI,
+    ɛ: f64,
+    max_iter: usize,
+    /// The last Jacobian of `f_I` and the time and state it belongs to, kept for a repeated step.
+    jacobian: Option<(f64, Array1<f64>, Array2<f64>)>,
+}
+
+impl<FlowE, FlowI> Imex<FlowE, FlowI>
+where
+    FlowE: Fn(f64, ArrayView1<f64>) -> Array1<f64>,
+    FlowI: Fn(f64, ArrayView1<AD>, &mut Array1<AD>),
+{
+    pub fn new(tableau: ArkTableau, explicit: FlowE, implicit: FlowI) -> Self {
+        let γ = tableau.gamma();
+        let s = tableau.stages();
+        assert!(
+            γ > 0. && (1..s).all(|i| tableau.a_implicit[[i, i]] == γ),
+            "The implicit tableau is not singly diagonally implicit"
+        );
+        assert!(
+            tableau.a_implicit[[0, 0]] == 0.,
+            "The first stage has to be explicit"
+        );
+        Imex {
+            tableau,
+            explicit,
+            implicit,
+            ɛ: 1e-10,
+            max_iter: 10,
+            jacobian: None,
+        }
+    }
+
+    /// Relative tolerance of the stage iterations.
+    pub fn set_epsilon(&mut self, ɛ: f64) -> &mut Self {
+        self.ɛ = ɛ;
+        self
+    }
+
+    pub fn set_max_iter(&mut self, max_iter: usize) -> &mut Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn tableau(&self) -> &ArkTableau {
+        &self.tableau
+    }
+
+    fn eval_implicit(&self, t: f64, x: ArrayView1<f64>) -> Array1<f64> {
+        eval_flow(|x, f: &mut Array1<AD>| (self.implicit)(t, x, f), x)
+    }
+
+    #[allow(non_snake_case)]
+    fn jacobian(&mut self, t: f64, x: ArrayView1<f64>, stats: &mut SolverStats) -> Array2<f64> {
+        if let Some((t_J, x_J, J)) = &self.jacobian {
+            if *t_J == t && *x_J == x {
+                return J.clone();
+            }
+        }
+        stats.jacobian_evaluations += 1;
+        let J = flow_jacobian(|x, f: &mut Array1<AD>| (self.implicit)(t, x, f), x);
+        self.jacobian = Some((t, x.to_owned(), J.clone()));
+        J
+    }
+}
+
+impl<FlowE, FlowI> Embedded for Imex<FlowE, FlowI>
+where
+    FlowE: Fn(f64, ArrayView1<f64>) -> Array1<f64>,
+    FlowI: Fn(f64, ArrayView1<AD>, &mut Array1<AD>),
+{
+    fn order(&self) -> usize {
+        self.tableau.order
+    }
+
+    #[allow(non_snake_case)]
+    fn next(
+        &mut self,
+        t: f64,
+        x: ArrayView1<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> Result<EmbeddedStep> {
+        let J = self.jacobian(t, x, stats);
+        let n = x.len();
+        let γ = self.tableau.gamma();
+        let lu = (Array2::eye(n) - h * γ * J).factorize()?;
+        stats.lu_factorizations += 1;
+
+        let failures = stats.newton_failures;
+        let tableau = &self.tableau;
+        let s = tableau.stages();
+        let mut k_explicit: Vec<Array1<f64>> = Vec::with_capacity(s);
+        let mut k_implicit: Vec<Array1<f64>> = Vec::with_capacity(s);
+        for i in 0..s {
+            let t_i = t + tableau.c[i] * h;
+            let mut known = x.to_owned();
+            for j in 0..i {
+                known.scaled_add(h * tableau.a_explicit[[i, j]], &k_explicit[j]);
+                known.scaled_add(h * tableau.a_implicit[[i, j]], &k_implicit[j]);
+            }
+            let z = if i == 0 {
+                k_implicit.push(self.eval_implicit(t_i, x));
+                stats.rhs_evaluations += 1;
+                known
+            } else {
+                let mut predictor = known.clone();
+                predictor.scaled_add(h * γ, &k_implicit[i - 1]);
+                let flow = |x: ArrayView1<AD>, f: &mut Array1<AD>| (self.implicit)(t_i, x, f);
+                let stage = Stage {
+                    known: known.to_ad(),
+                    hγ: AD::AD0(h * γ),
+                    flow: &flow,
+                };
+                let z = simplified_newton_with_stats(
+                    self.ɛ,
+                    &stage,
+                    predictor.to_ad(),
+                    &lu,
+                    self.max_iter,
+                    stats,
+                )?
+                .to_f64();
+                k_implicit.push((&z - &known) / (h * γ));
+                z
+            };
+            k_explicit.push(if tableau.uses_explicit(i) {
+                stats.rhs_evaluations += 1;
+                (self.explicit)(t_i, z.view())
+            } else {
+                Array1::zeros(n)
+            });
+        }
+
+        let mut x1 = x.to_owned();
+        let mut error = Array1::zeros(n);
+        let parts = [
+            (&k_explicit, &tableau.b_explicit, &tableau.b_hat_explicit),
+            (&k_implicit, &tableau.b_implicit, &tableau.b_hat_implicit),
+        ];
+        for (k, b, b_hat) in parts {
+            for ((k, &b), &b_hat) in k.iter().zip(b.iter()).zip(b_hat.iter()) {
+                x1.scaled_add(h * b, k);
+                error.scaled_add(h * (b - b_hat), k);
+            }
+        }
+        if stats.newton_failures > failures {
+            // rejects the step, the controller shrinks it as far as possible
+            error.fill(f64::INFINITY);
+        }
+        Ok(EmbeddedStep { x: x1, error })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{
+        ad::AD,
+        ode::{solver::*, *},
+        test_support::*,
+    };
+
+    /// The pendulum split into the kinetic part `q' = p` for the implicit and the force for the explicit tableau.
+    fn force(_t: f64, x: ArrayView1<f64>) -> Array1<f64> {
+        array![0., -x[0].sin()]
+    }
+
+    fn kinetic(_t: f64, x: ArrayView1<AD>, f: &mut Array1<AD>) {
+        f[0] = x[1];
+        f[1] = AD::AD0(0.);
+    }
+
+    fn fixed(tableau: ArkTableau, h: f64) -> Array1<f64> {
+        let mut scheme = Imex::new(tableau, force, kinetic);
+        scheme.set_epsilon(1e-14);
+        fixed_steps(&mut scheme, array![1.0, 0.0].view(), h).0
+    }
+
+    const N: usize = 40;
+
+    /// Fisher-KPP `u_t = u_xx + u (1 - u)` on `[0, 1]` with `u = 0` at both ends, by central differences.
+    fn diffusion(_t: f64, u: ArrayView1<AD>, f: &mut Array1<AD>) {
+        let dx2 = 1. / ((N + 1) * (N + 1)) as f64;
+        for i in 0..N {
+            let left = if i > 0 { u[i - 1] } else { AD::AD0(0.) };
+            let right = if i + 1 < N { u[i + 1] } else { AD::AD0(0.) };
+            f[i] = (left - u[i] * 2. + right) * (1. / dx2);
+        }
+    }
+
+    fn reaction(_t: f64, u: ArrayView1<f64>) -> Array1<f64> {
+        u.mapv(|u| u * (1. - u))
+    }
+
+    #[test]
+    fn order_and_reaction_diffusion() {
+        let reference = fixed(ArkTableau::ark436l(), 1e-3);
+        for (tableau, h) in [
+            (ArkTableau::imex_euler(), 0.01),
+            (ArkTableau::ark324l(), 0.05),
+            (ArkTableau::ark436l(), 0.1),
+        ] {
+            let p = tableau.order as f64;
+            let order = observed_order(|h| max_error(&fixed(tableau.clone(), h), &reference), h);
+            assert!((order - p).abs() < 0.3, "{p}: {order}");
+        }
+
+        let initial = Array1::from_shape_fn(N, |i| {
+            let x = (i + 1) as f64 / (N + 1) as f64;
+            (std::f64::consts::PI * x).sin()
+        });
+        let mut ode = Ode::adaptive(
+            Imex::new(ArkTableau::ark324l(), reaction, diffusion),
+            initial.clone(),
+        );
+        ode.set_tolerances(1e-6, 1e-6)
+            .set_step_size(1e-3)
+            .set_t(1.0)
+            .set_with_progress(false);
+        let solution = ode.run();
+        assert_eq!(solution.termination, Termination::Completed);
+        // the explicit Euler alone would need more than 1700 steps for stability
+        assert!(solution.stats.steps < 200, "{}", solution.stats.steps);
+
+        // the sine mode of the discrete Laplacian decays with λ, the reaction adds at most a growth rate of one
+        let (t, u) = solution.iter().last().unwrap();
+        let m = (N + 1) as f64;
+        let λ = 2. * m * m * (1. - (std::f64::consts::PI / m).cos());
+        let (lower, upper) = ((-λ * t).exp(), (-(λ - 1.) * t).exp());
+        for (&u, &u0) in u.iter().zip(initial.iter()) {
+            assert!(lower * u0 < u && u < upper * u0, "{u} {u0}");
+        }
+    }
+}
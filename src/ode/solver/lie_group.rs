@@ -0,0 +1,505 @@
+use std::marker::PhantomData;
+
+use ndarray::{array, s, Array1, Array2, ArrayView1, ArrayView2};
+use ndarray_linalg::error::Result;
+
+use crate::ode::*;
+
+use super::{magnus::commutator, phi::expm, ButcherTableau};
+
+/// `ω̂`, the skew symmetric matrix with `ω̂ v = ω × v`.
+pub fn hat(ω: ArrayView1<f64>) -> Array2<f64> {
+    array![[0., -ω[2], ω[1]], [ω[2], 0., -ω[0]], [-ω[1], ω[0], 0.]]
+}
+
+/// The vector `ω` of the skew symmetric part of `a`, the inverse of [hat].
+pub fn vee(a: ArrayView2<f64>) -> Array1<f64> {
+    array![
+        a[[2, 1]] - a[[1, 2]],
+        a[[0, 2]] - a[[2, 0]],
+        a[[1, 0]] - a[[0, 1]]
+    ] / 2.
+}
+
+/// The element `[[ω̂, v], [0, 0]]` of se(3), which moves with velocity `v` and turns with `ω`.
+pub fn se3_hat(ω: ArrayView1<f64>, v: ArrayView1<f64>) -> Array2<f64> {
+    let mut a = Array2::zeros((4, 4));
+    a.slice_mut(s![..3, ..3]).assign(&hat(ω));
+    a.slice_mut(s![..3, 3]).assign(&v);
+    a
+}
+
+/// The rotation `exp(ω̂)` by the angle `|ω|` about `ω` after Rodrigues.
+pub fn so3_exp(ω: ArrayView1<f64>) -> Array2<f64> {
+    let θ = ω.dot(&ω).sqrt();
+    let w = hat(ω);
+    let (a, b) = if θ < 1e-4 {
+        (1. - θ * θ / 6., 0.5 - θ * θ / 24.)
+    } else {
+        (θ.sin() / θ, (1. - θ.cos()) / (θ * θ))
+    };
+    Array2::eye(3) + a * &w + b * w.dot(&w)
+}
+
+/// The rigid motion `exp(se3_hat(ω, v))` in closed form.
+pub fn se3_exp(ω: ArrayView1<f64>, v: ArrayView1<f64>) -> Array2<f64> {
+    let θ = ω.dot(&ω).sqrt();
+    let w = hat(ω);
+    let (b, c) = if θ < 1e-4 {
+        (0.5 - θ * θ / 24., 1. / 6. - θ * θ / 120.)
+    } else {
+        ((1. - θ.cos()) / (θ * θ), (θ - θ.sin()) / (θ * θ * θ))
+    };
+    let translation = (Array2::eye(3) + b * &w + c * w.dot(&w)).dot(&v);
+    let mut g = Array2::eye(4);
+    g.slice_mut(s![..3, ..3]).assign(&so3_exp(ω));
+    g.slice_mut(s![..3, 3]).assign(&translation);
+    g
+}
+
+/// `dexp_u^-1 v = Σ_k B_k / k! ad_u^k v` with the Bernoulli numbers `B_k`, up to the `q`-fold commutator.
+pub fn dexp_inv(u: &Array2<f64>, v: &Array2<f64>, q: usize) -> Array2<f64> {
+    const COEFFICIENTS: [f64; 7] = [1., -0.5, 1. / 12., 0., -1. / 720., 0., 1. / 30240.];
+    let mut result = v.clone();
+    let mut ad = v.clone();
+    for &c in COEFFICIENTS.iter().take(q + 1).skip(1) {
+        ad = commutator(u, &ad);
+        if c != 0. {
+            result.scaled_add(c, &ad);
+        }
+    }
+    result
+}
+
+/// Runge-Kutta-Munthe-Kaas method for `Y' = A(t, Y) Y` on a matrix Lie group like SO(3) or SE(3).
+///
+/// The explicit [ButcherTableau] integrates `u' = dexp_u^-1 A(t, exp(u) Y0)` in the Lie algebra, and
+/// `Y1 = exp(u(h)) Y0` stays on the group for any step size. The inverse of `dexp` keeps the commutators
+/// up to `order - 2`, so the order of the tableau carries over.
+pub struct RungeKuttaMuntheKaas<Generator>
+where
+    Generator: Fn(f64, ArrayView2<f64>) -> Array2<f64>,
+{
+    tableau: ButcherTableau,
+    generator: Generator,
+}
+
+impl<Generator> RungeKuttaMuntheKaas<Generator>
+where
+    Generator: Fn(f64, ArrayView2<f64>) -> Array2<f64>,
+{
+    pub fn new(tableau: ButcherTableau, generator: Generator) -> Self {
+        assert!(tableau.order <= 6, "dexp^-1 is truncated after order six");
+        RungeKuttaMuntheKaas { tableau, generator }
+    }
+}
+
+impl<Generator> MatrixScheme for RungeKuttaMuntheKaas<Generator>
+where
+    Generator: Fn(f64, ArrayView2<f64>) -> Array2<f64>,
+{
+    fn order(&self) -> usize {
+        self.tableau.order
+    }
+
+    fn next(
+        &mut self,
+        t: f64,
+        y: ArrayView2<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> Result<Array2<f64>> {
+        let ButcherTableau { a, b, c, order, .. } = &self.tableau;
+        let q = order.saturating_sub(2);
+        let mut k: Vec<Array2<f64>> = Vec::with_capacity(b.len());
+        for i in 0..b.len() {
+            let mut u = Array2::zeros(y.dim());
+            for (k, &a) in k.iter().zip(a.row(i)) {
+                u.scaled_add(a, k);
+            }
+            let y_i = if i == 0 {
+                y.to_owned()
+            } else {
+                expm(u.view()).dot(&y)
+            };
+            let v = h * (self.generator)(t + c[i] * h, y_i.view());
+            k.push(dexp_inv(&u, &v, q));
+        }
+        stats.rhs_evaluations += b.len();
+        let mut u = Array2::zeros(y.dim());
+        for (k, &b) in k.iter().zip(b) {
+            u.scaled_add(b, k);
+        }
+        Ok(expm(u.view()).dot(&y))
+    }
+}
+
+impl ButcherTableau {
+    /// The three stage method of Crouch and Grossman, of order three only as [CrouchGrossman] method.
+    pub fn crouch_grossman3() -> Self {
+        ButcherTableau {
+            a: array![
+                [0., 0., 0.],
+                [3. / 4., 0., 0.],
+                [119. / 216., 17. / 108., 0.]
+            ],
+            b: array![13. / 51., -2. / 3., 24. / 17.],
+            c: array![0., 3. / 4., 17. / 24.],
+            b_hat: None,
+            order: 3,
+        }
+    }
+}
+
+/// Crouch-Grossman method for `Y' = A(t, Y) Y`, which composes exponentials of the frozen stage generators,
+/// `Y_i = exp(h a_i,i-1 A_i-1) ... exp(h a_i1 A_1) Y0` and `Y1 = exp(h b_s A_s) ... exp(h b_1 A_1) Y0`.
+///
+/// No commutators are needed, but beyond order two the coefficients have to satisfy the order conditions
+/// of Crouch and Grossman, e.g. [ButcherTableau::crouch_grossman3].
+pub struct CrouchGrossman<Generator>
+where
+    Generator: Fn(f64, ArrayView2<f64>) -> Array2<f64>,
+{
+    tableau: ButcherTableau,
+    generator: Generator,
+}
+
+impl<Generator> CrouchGrossman<Generator>
+where
+    Generator: Fn(f64, ArrayView2<f64>) -> Array2<f64>,
+{
+    pub fn new(tableau: ButcherTableau, generator: Generator) -> Self {
+        CrouchGrossman { tableau, generator }
+    }
+}
+
+impl<Generator> MatrixScheme for CrouchGrossman<Generator>
+where
+    Generator: Fn(f64, ArrayView2<f64>) -> Array2<f64>,
+{
+    fn order(&self) -> usize {
+        self.tableau.order
+    }
+
+    fn next(
+        &mut self,
+        t: f64,
+        y: ArrayView2<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> Result<Array2<f64>> {
+        let ButcherTableau { a, b, c, .. } = &self.tableau;
+        let compose = |weights: ArrayView1<f64>, k: &[Array2<f64>]| {
+            let mut y = y.to_owned();
+            for (k, &w) in k.iter().zip(weights) {
+                if w != 0. {
+                    y = expm((h * w * k).view()).dot(&y);
+                }
+            }
+            y
+        };
+        let mut k: Vec<Array2<f64>> = Vec::with_capacity(b.len());
+        for i in 0..b.len() {
+            let y_i = compose(a.row(i), &k);
+            k.push((self.generator)(t + c[i] * h, y_i.view()));
+        }
+        stats.rhs_evaluations += b.len();
+        Ok(compose(b.view(), &k))
+    }
+}
+
+/// A representation of rotations at the start of a flat state vector.
+pub trait Attitude {
+    /// Number of entries of the attitude.
+    const LEN: usize;
+    fn identity() -> Array1<f64>;
+    /// The attitude `R exp(ω̂)` after turning by `ω` about an axis fixed in the body.
+    fn rotate(r: ArrayView1<f64>, ω: ArrayView1<f64>) -> Array1<f64>;
+    /// The rotation matrix `R` from the body to the space frame.
+    fn matrix(r: ArrayView1<f64>) -> Array2<f64>;
+}
+
+/// Rotation matrices stored row by row.
+pub struct RotationMatrix;
+
+impl Attitude for RotationMatrix {
+    const LEN: usize = 9;
+
+    fn identity() -> Array1<f64> {
+        Array2::<f64>::eye(3).into_shape(9).unwrap()
+    }
+
+    fn rotate(r: ArrayView1<f64>, ω: ArrayView1<f64>) -> Array1<f64> {
+        let r = RotationMatrix::matrix(r);
+        r.dot(&so3_exp(ω)).into_shape(9).unwrap()
+    }
+
+    fn matrix(r: ArrayView1<f64>) -> Array2<f64> {
+        r.to_owned().into_shape((3, 3)).unwrap()
+    }
+}
+
+/// Unit quaternions `(w, x, y, z)`, which need less storage and arithmetic than rotation matrices.
+pub struct Quaternion;
+
+impl Quaternion {
+    /// The Hamilton product `p q`.
+    pub fn product(p: ArrayView1<f64>, q: ArrayView1<f64>) -> Array1<f64> {
+        array![
+            p[0] * q[0] - p[1] * q[1] - p[2] * q[2] - p[3] * q[3],
+            p[0] * q[1] + p[1] * q[0] + p[2] * q[3] - p[3] * q[2],
+            p[0] * q[2] - p[1] * q[3] + p[2] * q[0] + p[3] * q[1],
+            p[0] * q[3] + p[1] * q[2] - p[2] * q[1] + p[3] * q[0]
+        ]
+    }
+
+    /// The unit quaternion of the rotation by `|ω|` about `ω`.
+    pub fn exp(ω: ArrayView1<f64>) -> Array1<f64> {
+        let θ = ω.dot(&ω).sqrt();
+        let s = if θ < 1e-4 {
+            0.5 - θ * θ / 48.
+        } else {
+            (θ / 2.).sin() / θ
+        };
+        array![(θ / 2.).cos(), s * ω[0], s * ω[1], s * ω[2]]
+    }
+}
+
+impl Attitude for Quaternion {
+    const LEN: usize = 4;
+
+    fn identity() -> Array1<f64> {
+        array![1., 0., 0., 0.]
+    }
+
+    fn rotate(r: ArrayView1<f64>, ω: ArrayView1<f64>) -> Array1<f64> {
+        Quaternion::product(r, Quaternion::exp(ω).view())
+    }
+
+    fn matrix(r: ArrayView1<f64>) -> Array2<f64> {
+        let (w, x, y, z) = (r[0], r[1], r[2], r[3]);
+        array![
+            [
+                1. - 2. * (y * y + z * z),
+                2. * (x * y - w * z),
+                2. * (x * z + w * y)
+            ],
+            [
+                2. * (x * y + w * z),
+                1. - 2. * (x * x + z * z),
+                2. * (y * z - w * x)
+            ],
+            [
+                2. * (x * z - w * y),
+                2. * (y * z + w * x),
+                1. - 2. * (x * x + y * y)
+            ]
+        ]
+    }
+}
+
+/// The Euler equations of the free rigid body, `m' = m × ω` for the angular momentum `m` in the body frame
+/// with the angular velocity `ω = I^-1 m` and the principal moments of inertia `I`.
+pub fn rigid_body(inertia: [f64; 3]) -> impl Fn(ArrayView1<f64>) -> Array1<f64> {
+    move |m| {
+        let ω = array![m[0] / inertia[0], m[1] / inertia[1], m[2] / inertia[2]];
+        hat(m).dot(&ω)
+    }
+}
+
+/// Symplectic splitting of the free rigid body into the rotations about its principal axes,
+/// composed as `1 2 3 2 1` after Strang (McLachlan, Dullweber, Leimkuhler).
+///
+/// The state is an [Attitude] `R` followed by the body angular momentum `m`. Each part `H_k = m_k² / (2 I_k)`
+/// is solved exactly, so the attitude stays a rotation, `|m|` and the spatial angular momentum `R m` are
+/// conserved up to rounding and the energy oscillates without drift.
+pub struct RigidBodySplitting<R>
+where
+    R: Attitude,
+{
+    h: f64,
+    inertia: [f64; 3],
+    attitude: PhantomData<R>,
+}
+
+impl<R> RigidBodySplitting<R>
+where
+    R: Attitude,
+{
+    pub fn new(h: f64, inertia: [f64; 3]) -> Self {
+        RigidBodySplitting {
+            h,
+            inertia,
+            attitude: PhantomData,
+        }
+    }
+
+    /// The initial state at rest in the space frame with angular momentum `m`.
+    pub fn initial(m: ArrayView1<f64>) -> Array1<f64> {
+        let mut x = R::identity().to_vec();
+        x.extend(m.iter());
+        Array1::from(x)
+    }
+
+    /// The energy `Σ_k m_k² / (2 I_k)` of the state `x`.
+    pub fn energy(&self, x: ArrayView1<f64>) -> f64 {
+        let m = x.slice(s![R::LEN..]);
+        (0..3).map(|k| m[k] * m[k] / (2. * self.inertia[k])).sum()
+    }
+
+    /// The exact flow of `H_k` over `τ`.
+    fn rotate(&self, x: &mut Array1<f64>, k: usize, τ: f64) {
+        let m = x.slice(s![R::LEN..]).to_owned();
+        let mut ω = Array1::zeros(3);
+        ω[k] = τ * m[k] / self.inertia[k];
+        let r = R::rotate(x.slice(s![..R::LEN]), ω.view());
+        x.slice_mut(s![..R::LEN]).assign(&r);
+        x.slice_mut(s![R::LEN..])
+            .assign(&so3_exp((-&ω).view()).dot(&m));
+    }
+}
+
+impl<R> Explicit for RigidBodySplitting<R>
+where
+    R: Attitude,
+{
+    fn next(&self, x: ArrayView1<f64>) -> ndarray_linalg::error::Result<Array1<f64>> {
+        let h = self.h;
+        let mut x = x.to_owned();
+        for (k, τ) in [(0, h / 2.), (1, h / 2.), (2, h), (1, h / 2.), (0, h / 2.)] {
+            self.rotate(&mut x, k, τ);
+        }
+        Ok(x)
+    }
+
+    fn evaluations(&self) -> usize {
+        0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, s, Array2, ArrayView2};
+
+    use crate::{
+        ode::{solver::*, *},
+        test_support::*,
+    };
+
+    const INERTIA: [f64; 3] = [2., 1., 2. / 3.];
+
+    /// The attitude `Y = R^T` of the free rigid body with spatial angular momentum `π`, `Y' = -(I^-1 Y π)^ Y`.
+    fn attitude(_: f64, y: ArrayView2<f64>) -> Array2<f64> {
+        let m = y.dot(&array![0.3, 0.8, -0.5]);
+        let ω = array![m[0] / INERTIA[0], m[1] / INERTIA[1], m[2] / INERTIA[2]];
+        -hat(ω.view())
+    }
+
+    /// A screw motion with time dependent pitch on SE(3).
+    fn screw(t: f64, g: ArrayView2<f64>) -> Array2<f64> {
+        let ω = array![1., t.sin(), 0.5];
+        let v = array![g[[0, 3]], 1., t];
+        se3_hat(ω.view(), v.view())
+    }
+
+    #[test]
+    fn orders_on_so3_and_se3() {
+        let (ω, v) = (array![0.3, -1.2, 2.], array![1., 2., 3.]);
+        assert_eq!(vee(hat(ω.view()).view()), ω);
+        let closed_form = se3_exp(ω.view(), v.view());
+        assert!(max_error(&closed_form, &expm(se3_hat(ω.view(), v.view()).view())) < 1e-12);
+
+        let mut stats = SolverStats::default();
+        type Generator = fn(f64, ArrayView2<f64>) -> Array2<f64>;
+        for (generator, y0) in [
+            (attitude as Generator, Array2::eye(3)),
+            (screw as Generator, Array2::eye(4)),
+        ] {
+            let reference = fixed_matrix_steps(
+                &mut RungeKuttaMuntheKaas::new(ButcherTableau::butcher6(), generator),
+                y0.view(),
+                1e-3,
+                &mut stats,
+            );
+            for tableau in [
+                ButcherTableau::euler(),
+                ButcherTableau::rk4(),
+                ButcherTableau::crouch_grossman3(),
+            ] {
+                let p = tableau.order as f64;
+                let error = |scheme: &mut dyn MatrixScheme, h| {
+                    let y = fixed_matrix_steps(scheme, y0.view(), h, &mut SolverStats::default());
+                    max_error(&y, &reference)
+                };
+                let rkmk = |h| {
+                    error(
+                        &mut RungeKuttaMuntheKaas::new(tableau.clone(), generator),
+                        h,
+                    )
+                };
+                let cg = |h| error(&mut CrouchGrossman::new(tableau.clone(), generator), h);
+                if tableau.stages() != 3 {
+                    let order = observed_order(rkmk, 0.1);
+                    assert!((order - p).abs() < 0.3, "RKMK {p}: {order}");
+                }
+                if tableau.stages() != 4 {
+                    let order = observed_order(cg, 0.1);
+                    assert!((order - p).abs() < 0.3, "Crouch-Grossman {p}: {order}");
+                }
+            }
+        }
+
+        let y = fixed_matrix_steps(
+            &mut RungeKuttaMuntheKaas::new(ButcherTableau::rk4(), attitude),
+            Array2::eye(3).view(),
+            0.5,
+            &mut stats,
+        );
+        assert!(max_error(&y.t().dot(&y), &Array2::eye(3)) < 1e-13);
+    }
+
+    #[test]
+    fn rigid_body_splitting() {
+        let m0 = array![0.3, 0.8, -0.5];
+        let h = 0.05;
+        let quaternion = RigidBodySplitting::<Quaternion>::new(h, INERTIA);
+        let matrix = RigidBodySplitting::<RotationMatrix>::new(h, INERTIA);
+        let energy = quaternion.energy(RigidBodySplitting::<Quaternion>::initial(m0.view()).view());
+
+        let mut ode = Ode::explicit(
+            quaternion,
+            RigidBodySplitting::<Quaternion>::initial(m0.view()),
+        );
+        ode.set_step_size(h).set_t(100.).set_with_progress(false);
+        let by_quaternion = ode.run();
+        let mut ode = Ode::explicit(
+            matrix,
+            RigidBodySplitting::<RotationMatrix>::initial(m0.view()),
+        );
+        ode.set_step_size(h).set_t(100.).set_with_progress(false);
+        let by_matrix = ode.run();
+
+        let splitting = RigidBodySplitting::<Quaternion>::new(h, INERTIA);
+        for ((_, q), (_, r)) in by_quaternion.iter().zip(by_matrix.iter()) {
+            let (rq, m) = (Quaternion::matrix(q.slice(s![..4])), q.slice(s![4..]));
+            let rm = RotationMatrix::matrix(r.slice(s![..9]));
+            assert!((q.slice(s![..4]).dot(&q.slice(s![..4])) - 1.).abs() < 1e-12);
+            assert!(max_error(&rm.t().dot(&rm), &Array2::eye(3)) < 1e-12);
+            // both representations take the same steps
+            assert!(max_error(&rq, &rm) < 1e-10);
+            assert!((&rq.dot(&m) - &m0).iter().all(|e| e.abs() < 1e-12));
+            assert!((splitting.energy(q) - energy).abs() < 1e-2 * energy);
+        }
+        // the Euler equations agree with the splitting
+        let mut ode = Ode::explicit(
+            RungeKutta::new(1e-3, ButcherTableau::rk4(), rigid_body(INERTIA)),
+            m0.clone(),
+        );
+        ode.set_step_size(1e-3).set_t(1.).set_with_progress(false);
+        let euler = ode.run();
+        let (_, m) = euler.iter().last().unwrap();
+        let t = euler.time.last().unwrap();
+        let split = by_quaternion.at(*t).unwrap();
+        assert!((&split.slice(s![4..]) - &m).iter().all(|e| e.abs() < 1e-2));
+    }
+}
@@ -0,0 +1,195 @@
+use ndarray::{Array2, ArrayView2};
+use ndarray_linalg::{error::Result, Factorize, Solve};
+
+use crate::ode::*;
+
+use super::phi::expm;
+
+/// The commutator `[a, b] = a b - b a`.
+pub fn commutator(a: &Array2<f64>, b: &Array2<f64>) -> Array2<f64> {
+    a.dot(b) - b.dot(a)
+}
+
+/// The Cayley transform `(I - Ω/2)^-1 (I + Ω/2)`, which maps a skew symmetric `Ω` to an orthogonal matrix.
+pub fn cayley(omega: ArrayView2<f64>) -> Result<Array2<f64>> {
+    let n = omega.nrows();
+    let half = omega.mapv(|x| x / 2.);
+    let lu = (Array2::eye(n) - &half).factorize()?;
+    let rhs = Array2::eye(n) + &half;
+    let mut y = Array2::zeros((n, n));
+    for (mut column, rhs) in y.columns_mut().into_iter().zip(rhs.columns()) {
+        column.assign(&lu.solve(&rhs.to_owned())?);
+    }
+    Ok(y)
+}
+
+/// Magnus integrator of order 2, 4 or 6 for the linear problem `Y' = A(t) Y`, `Y1 = exp(Ω) Y0`.
+///
+/// `Ω` is the Magnus expansion truncated after the commutators the order needs, with `A` evaluated at
+/// the Gauss-Legendre nodes of the step (Blanes, Casas and Ros for order 6). It lies in the Lie algebra
+/// of `A`, so an orthogonal `Y` stays orthogonal up to rounding, for any step size.
+/// A unitary problem enters in the real form `A = [[Re A, -Im A], [Im A, Re A]]`.
+pub struct Magnus<Generator>
+where
+    Generator: Fn(f64) -> Array2<f64>,
+{
+    generator: Generator,
+    order: usize,
+}
+
+impl<Generator> Magnus<Generator>
+where
+    Generator: Fn(f64) -> Array2<f64>,
+{
+    pub fn new(order: usize, generator: Generator) -> Self {
+        assert!(
+            [2, 4, 6].contains(&order),
+            "Magnus methods are implemented for the orders 2, 4 and 6"
+        );
+        Magnus { generator, order }
+    }
+
+    /// The truncated Magnus expansion `Ω` of the step from `t` to `t + h`.
+    pub fn omega(&self, t: f64, h: f64, stats: &mut SolverStats) -> Array2<f64> {
+        let a = |c: f64| (self.generator)(t + c * h);
+        stats.rhs_evaluations += self.order / 2;
+        match self.order {
+            2 => h * a(0.5),
+            4 => {
+                let d = 3f64.sqrt() / 6.;
+                let (a1, a2) = (a(0.5 - d), a(0.5 + d));
+                h / 2. * (&a1 + &a2) + 3f64.sqrt() / 12. * h * h * commutator(&a2, &a1)
+            }
+            _ => {
+                let d = 15f64.sqrt() / 10.;
+                let (a1, a2, a3) = (a(0.5 - d), a(0.5), a(0.5 + d));
+                let α1 = h * &a2;
+                let α2 = 15f64.sqrt() * h / 3. * (&a3 - &a1);
+                let α3 = 10. * h / 3. * (&a3 - 2. * &a2 + &a1);
+                let c1 = commutator(&α1, &α2);
+                let c2 = -1. / 60. * commutator(&α1, &(2. * &α3 + &c1));
+                let left = -20. * &α1 - &α3 + &c1;
+                &α1 + &α3 / 12. + commutator(&left, &(&α2 + &c2)) / 240.
+            }
+        }
+    }
+}
+
+impl<Generator> MatrixScheme for Magnus<Generator>
+where
+    Generator: Fn(f64) -> Array2<f64>,
+{
+    fn order(&self) -> usize {
+        self.order
+    }
+
+    fn next(
+        &mut self,
+        t: f64,
+        y: ArrayView2<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> Result<Array2<f64>> {
+        Ok(expm(self.omega(t, h, stats).view()).dot(&y))
+    }
+}
+
+/// Cayley method of order 2, 4 or 6 for `Y' = A(t) Y` with `A` in a quadratic Lie algebra, `A^T J + J A = 0`,
+/// e.g. skew symmetric `A` for orthogonal `Y`.
+///
+/// It replaces the exponential of the [Magnus] method by the [cayley] transform of `2 tanh(Ω / 2)`,
+/// truncated to the order, which is again in the Lie algebra. So the group is kept exactly with one LU
+/// factorization per step and no matrix exponential.
+pub struct Cayley<Generator>
+where
+    Generator: Fn(f64) -> Array2<f64>,
+{
+    magnus: Magnus<Generator>,
+}
+
+impl<Generator> Cayley<Generator>
+where
+    Generator: Fn(f64) -> Array2<f64>,
+{
+    pub fn new(order: usize, generator: Generator) -> Self {
+        Cayley {
+            magnus: Magnus::new(order, generator),
+        }
+    }
+}
+
+impl<Generator> MatrixScheme for Cayley<Generator>
+where
+    Generator: Fn(f64) -> Array2<f64>,
+{
+    fn order(&self) -> usize {
+        self.magnus.order
+    }
+
+    fn next(
+        &mut self,
+        t: f64,
+        y: ArrayView2<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> Result<Array2<f64>> {
+        let ω = self.magnus.omega(t, h, stats);
+        // 2 tanh(Ω / 2) = Ω - Ω³ / 12 + Ω⁵ / 120 - ...
+        let mut σ = ω.clone();
+        if self.magnus.order > 2 {
+            let ω2 = ω.dot(&ω);
+            let ω3 = ω2.dot(&ω);
+            σ.scaled_add(-1. / 12., &ω3);
+            if self.magnus.order > 4 {
+                σ.scaled_add(1. / 120., &ω2.dot(&ω3));
+            }
+        }
+        stats.lu_factorizations += 1;
+        Ok(cayley(σ.view())?.dot(&y))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array2};
+
+    use crate::{
+        ode::{solver::*, *},
+        test_support::*,
+    };
+
+    /// A rotation about a turning axis.
+    fn generator(t: f64) -> Array2<f64> {
+        let (a, b, c) = (t.cos(), 1. + t * t, (2. * t).sin());
+        array![[0., -a, b], [a, 0., -c], [-b, c, 0.]]
+    }
+
+    fn fixed(mut scheme: impl MatrixScheme, h: f64) -> Array2<f64> {
+        let y0 = Array2::eye(3);
+        fixed_matrix_steps(&mut scheme, y0.view(), h, &mut SolverStats::default())
+    }
+
+    #[test]
+    fn orders_and_orthogonality() {
+        let reference = fixed(Magnus::new(6, generator), 1e-3);
+        for order in [2, 4, 6] {
+            let p = order as f64;
+            let magnus = |h| max_error(&fixed(Magnus::new(order, generator), h), &reference);
+            let observed = observed_order(magnus, 0.1);
+            assert!((observed - p).abs() < 0.3, "Magnus {p}: {observed}");
+            let cayley = |h| max_error(&fixed(Cayley::new(order, generator), h), &reference);
+            let observed = observed_order(cayley, 0.1);
+            assert!((observed - p).abs() < 0.3, "Cayley {p}: {observed}");
+        }
+
+        let mut ode = Ode::matrix(Cayley::new(4, generator), Array2::eye(3));
+        ode.set_step_size(0.5).set_t(20.).set_with_progress(false);
+        let solution = ode.run();
+        assert_eq!(solution.termination, Termination::Completed);
+        assert_eq!(solution.labels[5], "y1_2");
+        for (_, y) in solution.iter() {
+            let y = y.into_shape((3, 3)).unwrap();
+            assert!(max_error(&y.t().dot(&y), &Array2::eye(3)) < 1e-12);
+        }
+    }
+}
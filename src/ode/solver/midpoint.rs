@@ -0,0 +1,58 @@
+use ndarray::{array, Array1, ArrayView1, Zip};
+
+use crate::{ad::*, ode::*};
+
+/// The implicit midpoint rule `x1 = x0 + h f((x0 + x1) / 2)`.
+///
+/// It is the one stage Gauss-Legendre method: of second order, A-stable and symplectic,
+/// so quadratic invariants like the angular momentum are kept exactly.
+#[derive(Clone)]
+pub struct ImplicitMidpoint<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    x0_owned: Array1<AD>,
+    flow: Flow,
+    h: AD,
+}
+impl<Flow> ImplicitMidpoint<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    pub fn new(h: f64, flow: Flow) -> Self {
+        ImplicitMidpoint {
+            x0_owned: array![],
+            h: AD::AD0(h),
+            flow,
+        }
+    }
+}
+impl<Flow> Residual for ImplicitMidpoint<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    #[inline]
+    fn eval(&self, x1: ArrayView1<AD>, update: &mut Array1<AD>) {
+        let h = self.h;
+        let x0 = self.x0_owned.view();
+        let mid: Array1<AD> = Zip::from(x1)
+            .and(x0)
+            .map_collect(|&x1, &x0| 0.5 * (x0 + x1));
+        (self.flow)(mid.view(), update);
+        Zip::from(x1)
+            .and(x0)
+            .and(update)
+            .for_each(|&x1, &x0, f| *f = x1 - x0 - h * *f);
+    }
+}
+
+impl<Flow> Residual1Step for ImplicitMidpoint<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    #[inline]
+    fn update(&mut self, x0: Array1<AD>) {
+        self.x0_owned = x0;
+    }
+}
+impl<Flow> Implicit for ImplicitMidpoint<Flow> where Flow: Fn(ArrayView1<AD>, &mut Array1<AD>) {}
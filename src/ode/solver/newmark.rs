@@ -0,0 +1,298 @@
+use ndarray::{Array1, ArrayView1, Zip};
+
+use crate::{ad::*, ode::*};
+
+/// Generalized-α scheme of Chung and Hulbert for structural dynamics `q'' = f(t, q, q')`,
+/// which contains the Newmark-β family for `α_m = α_f = 0`.
+///
+/// With the acceleration `a1` of the new step as unknown, the scheme solves
+/// `(1 - α_m) a1 + α_m a0 = f(t + (1 - α_f) h, (1 - α_f) q1 + α_f q0, (1 - α_f) v1 + α_f v0)` with [newton], where
+/// `q1 = q0 + h v0 + h² ((½ - β) a0 + β a1)` and `v1 = v0 + h ((1 - γ) a0 + γ a1)`.
+pub struct GeneralizedAlpha<Accel>
+where
+    Accel: Fn(f64, ArrayView1<AD>, ArrayView1<AD>, &mut Array1<AD>) + std::marker::Sync,
+{
+    accel: Accel,
+    α_m: f64,
+    α_f: f64,
+    β: f64,
+    γ: f64,
+    ɛ: f64,
+    a0: Option<Array1<f64>>,
+}
+
+impl<Accel> GeneralizedAlpha<Accel>
+where
+    Accel: Fn(f64, ArrayView1<AD>, ArrayView1<AD>, &mut Array1<AD>) + std::marker::Sync,
+{
+    /// Second order accurate and unconditionally stable, `ρ∞ ∈ [0, 1]` is the spectral radius at infinite
+    /// frequency, smaller values damp high frequencies stronger.
+    pub fn new(ρ_inf: f64, accel: Accel) -> Self {
+        let α_m = (2. * ρ_inf - 1.) / (ρ_inf + 1.);
+        let α_f = ρ_inf / (ρ_inf + 1.);
+        let γ = 0.5 - α_m + α_f;
+        let β = 0.25 * (1. - α_m + α_f).powi(2);
+        GeneralizedAlpha {
+            accel,
+            α_m,
+            α_f,
+            β,
+            γ,
+            ɛ: 1e-10,
+            a0: None,
+        }
+    }
+
+    /// Newmark-β, e.g. `β = ¼, γ = ½` for the average acceleration method.
+    pub fn newmark(β: f64, γ: f64, accel: Accel) -> Self {
+        GeneralizedAlpha {
+            accel,
+            α_m: 0.,
+            α_f: 0.,
+            β,
+            γ,
+            ɛ: 1e-10,
+            a0: None,
+        }
+    }
+
+    pub fn set_epsilon(&mut self, ɛ: f64) -> &mut Self {
+        self.ɛ = ɛ;
+        self
+    }
+
+    fn acceleration(&self, t: f64, q: ArrayView1<f64>, v: ArrayView1<f64>) -> Array1<f64> {
+        let (q, v) = (q.to_ad(), v.to_ad());
+        let mut a = q.clone();
+        (self.accel)(t, q.view(), v.view(), &mut a);
+        a.to_f64()
+    }
+}
+
+/// The residual in the unknown acceleration of the new step.
+struct AlphaResidual<'a, Accel>
+where
+    Accel: Fn(f64, ArrayView1<AD>, ArrayView1<AD>, &mut Array1<AD>) + std::marker::Sync,
+{
+    scheme: &'a GeneralizedAlpha<Accel>,
+    /// The time `t + (1 - α_f) h` of the acceleration.
+    t: f64,
+    q0: ArrayView1<'a, f64>,
+    v0: ArrayView1<'a, f64>,
+    a0: ArrayView1<'a, f64>,
+    h: f64,
+}
+
+impl<'a, Accel> AlphaResidual<'a, Accel>
+where
+    Accel: Fn(f64, ArrayView1<AD>, ArrayView1<AD>, &mut Array1<AD>) + std::marker::Sync,
+{
+    /// Positions and velocities of the new step for the acceleration `a1`.
+    fn state<T>(&self, a1: ArrayView1<T>) -> (Array1<T>, Array1<T>)
+    where
+        T: Copy + std::ops::Add<f64, Output = T> + std::ops::Mul<f64, Output = T>,
+    {
+        let GeneralizedAlpha { β, γ, .. } = *self.scheme;
+        let h = self.h;
+        let mut q1 = a1.to_owned();
+        let mut v1 = a1.to_owned();
+        Zip::from(&mut q1)
+            .and(&mut v1)
+            .and(a1)
+            .and(self.q0)
+            .and(self.v0)
+            .and(self.a0)
+            .for_each(|q1, v1, &a1, &q0, &v0, &a0| {
+                *q1 = a1 * (h * h * β) + (q0 + h * v0 + h * h * (0.5 - β) * a0);
+                *v1 = a1 * (h * γ) + (v0 + h * (1. - γ) * a0);
+            });
+        (q1, v1)
+    }
+}
+
+impl<'a, Accel> Residual for AlphaResidual<'a, Accel>
+where
+    Accel: Fn(f64, ArrayView1<AD>, ArrayView1<AD>, &mut Array1<AD>) + std::marker::Sync,
+{
+    fn eval(&self, a1: ArrayView1<AD>, update: &mut Array1<AD>) {
+        let GeneralizedAlpha { α_m, α_f, .. } = *self.scheme;
+        let (mut q, mut v) = self.state(a1);
+        Zip::from(&mut q)
+            .and(&mut v)
+            .and(self.q0)
+            .and(self.v0)
+            .for_each(|q, v, &q0, &v0| {
+                *q = *q * (1. - α_f) + α_f * q0;
+                *v = *v * (1. - α_f) + α_f * v0;
+            });
+        (self.scheme.accel)(self.t, q.view(), v.view(), update);
+        Zip::from(update)
+            .and(a1)
+            .and(self.a0)
+            .for_each(|f, &a1, &a0| *f = a1 * (1. - α_m) + α_m * a0 - *f);
+    }
+}
+
+impl<Accel> SecondOrder for GeneralizedAlpha<Accel>
+where
+    Accel: Fn(f64, ArrayView1<AD>, ArrayView1<AD>, &mut Array1<AD>) + std::marker::Sync,
+{
+    fn order(&self) -> usize {
+        if (self.γ - 0.5 - self.α_f + self.α_m).abs() < 1e-12 {
+            2
+        } else {
+            1
+        }
+    }
+
+    #[allow(non_snake_case)]
+    fn next(
+        &mut self,
+        t: f64,
+        q: ArrayView1<f64>,
+        v: ArrayView1<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> ndarray_linalg::error::Result<SecondOrderStep> {
+        let a0 = match self.a0.take() {
+            Some(a0) => a0,
+            None => {
+                stats.rhs_evaluations += 1;
+                self.acceleration(t, q, v)
+            }
+        };
+        let residual = AlphaResidual {
+            scheme: self,
+            t: t + (1. - self.α_f) * h,
+            q0: q.view(),
+            v0: v.view(),
+            a0: a0.view(),
+            h,
+        };
+        let l = q.len();
+        let mut J = ndarray::Array2::zeros((l, l));
+        let mut slope_buffer = a0.to_ad();
+        let a1 = match newton_with_stats(
+            self.ɛ,
+            &residual,
+            a0.to_ad(),
+            &mut J,
+            &mut slope_buffer,
+            stats,
+        ) {
+            Ok(a1) => a1.to_f64(),
+            Err(e) => {
+                // keep the acceleration of the last accepted step for a retry
+                self.a0 = Some(a0);
+                return Err(e);
+            }
+        };
+        let (q1, v1) = residual.state(a1.view());
+        self.a0 = Some(a1);
+        Ok(SecondOrderStep {
+            q: q1,
+            v: v1,
+            error: None,
+        })
+    }
+
+    /// The acceleration of the last step, which enters the next one for `α_m ≠ 0`.
+    fn carried_state(&self) -> Option<Array1<f64>> {
+        self.a0.clone()
+    }
+
+    fn restore_state(&mut self, carried: Array1<f64>) {
+        self.a0 = Some(carried);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{
+        ad::AD,
+        ensemble::max_drift,
+        ode::{solver::*, *},
+        test_support::*,
+    };
+
+    fn oscillator(_t: f64, q: ArrayView1<AD>, _v: ArrayView1<AD>, a: &mut Array1<AD>) {
+        a[0] = -q[0];
+    }
+
+    fn run(scheme: impl SecondOrder, h: f64, t: f64) -> Solution {
+        let mut ode = Ode::second_order(scheme, array![1.0], array![0.0]);
+        ode.set_step_size(h)
+            .set_t(t + h / 2.)
+            .set_with_progress(false);
+        ode.run()
+    }
+
+    /// The order from the errors of `q = cos t` at the end of runs with the steps 0.02 and 0.01.
+    fn order<Scheme: SecondOrder>(scheme: impl Fn() -> Scheme) -> f64 {
+        let error = |h: f64| {
+            let solution = run(scheme(), h, 1.0);
+            let (t, x) = solution.iter().last().unwrap();
+            (x[0] - t.cos()).abs()
+        };
+        observed_order(error, 0.02)
+    }
+
+    #[test]
+    fn orders_and_energy() {
+        let average = order(|| GeneralizedAlpha::newmark(0.25, 0.5, oscillator));
+        assert!((average - 2.).abs() < 0.1, "{average}");
+        let damped = order(|| GeneralizedAlpha::newmark(0.3025, 0.6, oscillator));
+        assert!((damped - 1.).abs() < 0.1, "{damped}");
+        let alpha = order(|| GeneralizedAlpha::new(0.5, oscillator));
+        assert!((alpha - 2.).abs() < 0.1, "{alpha}");
+
+        // the average acceleration method conserves the energy of linear problems, ρ∞ < 1 dissipates it
+        let energy = |x: ArrayView1<f64>| 0.5 * (x[0] * x[0] + x[1] * x[1]);
+        let solution = run(GeneralizedAlpha::newmark(0.25, 0.5, oscillator), 0.5, 100.);
+        assert!(max_drift(&solution, energy) < 1e-12);
+        let solution = run(GeneralizedAlpha::new(0., oscillator), 0.5, 100.);
+        let (_, x) = solution.iter().last().unwrap();
+        assert!(
+            energy(x) < 0.9 * energy(solution.states.row(0)),
+            "{}",
+            energy(x)
+        );
+    }
+
+    #[test]
+    fn time_dependent_forcing() {
+        // q'' = cos t with q = 1 - cos t, the acceleration of generalized-α is taken at t + (1 - α_f) h
+        let forcing = |t: f64, _q: ArrayView1<AD>, _v: ArrayView1<AD>, a: &mut Array1<AD>| {
+            a[0] = AD::AD0(t.cos());
+        };
+        let error = |h: f64| {
+            let mut ode = Ode::second_order(
+                GeneralizedAlpha::new(0.5, forcing),
+                array![0.0],
+                array![0.0],
+            );
+            ode.set_step_size(h)
+                .set_t(ending_at(1.0, h))
+                .set_with_progress(false);
+            let solution = ode.run();
+            (state_at(&solution, 1.0)[0] - (1. - 1f64.cos())).abs()
+        };
+        let order = observed_order(error, 0.02);
+        assert!((order - 2.).abs() < 0.1, "{order}");
+    }
+
+    #[test]
+    fn singular_iteration_fails() {
+        // the Jacobian 1 - h² β ∂f/∂q of the residual vanishes
+        let unstable =
+            |_t: f64, q: ArrayView1<AD>, _v: ArrayView1<AD>, a: &mut Array1<AD>| a[0] = 16. * q[0];
+        let solution = run(GeneralizedAlpha::newmark(0.25, 0.5, unstable), 0.5, 1.0);
+        assert!(matches!(
+            solution.termination,
+            Termination::Failed { t, .. } if t == 0.
+        ));
+        assert_eq!(solution.len(), 1);
+    }
+}
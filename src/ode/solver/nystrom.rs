@@ -0,0 +1,299 @@
+use ndarray::{array, Array1, Array2, ArrayView1};
+
+use crate::ode::{solver::ButcherTableau, *};
+
+/// Coefficients of a Runge-Kutta-Nyström method for `q'' = f(t, q, q')`.
+///
+/// The stages are
+/// `Q_i = q + c_i h v + h² Σ_j a_bar_ij F_j`, `V_i = v + h Σ_j a_ij F_j`, `F_i = f(t + c_i h, Q_i, V_i)`
+/// and the new state is `q + h v + h² Σ_i b_bar_i F_i`, `v + h Σ_i b_i F_i`.
+#[derive(Debug, Clone)]
+pub struct NystromTableau {
+    pub a_bar: Array2<f64>,
+    /// `None` for methods which only solve special problems `q'' = f(t, q)`, their stages are handed `V_i = v`.
+    pub a: Option<Array2<f64>>,
+    pub b_bar: Array1<f64>,
+    pub b: Array1<f64>,
+    pub c: Array1<f64>,
+    /// Weights `(b_bar_hat, b_hat)` of an embedded solution of lower order.
+    pub embedded: Option<(Array1<f64>, Array1<f64>)>,
+    pub order: usize,
+    /// Order of the embedded solution, if any.
+    pub embedded_order: usize,
+}
+
+impl NystromTableau {
+    /// The Nyström method which is equivalent to the Runge-Kutta method applied to `(q, v)`,
+    /// i.e. `a_bar = A²` and `b_bar = bA`.
+    pub fn from_runge_kutta(tableau: &ButcherTableau) -> Self {
+        let a = tableau.a.clone();
+        NystromTableau {
+            a_bar: a.dot(&a),
+            b_bar: tableau.b.dot(&a),
+            b: tableau.b.clone(),
+            c: tableau.c.clone(),
+            embedded: tableau
+                .b_hat
+                .as_ref()
+                .map(|b_hat| (b_hat.dot(&a), b_hat.clone())),
+            order: tableau.order,
+            embedded_order: tableau.order - 1,
+            a: Some(a),
+        }
+    }
+
+    /// The fourth order method of Nyström with three stages for special problems `q'' = f(t, q)`.
+    pub fn nystrom4() -> Self {
+        NystromTableau {
+            a_bar: array![[0., 0., 0.], [1. / 8., 0., 0.], [0., 1. / 2., 0.]],
+            a: None,
+            b_bar: array![1. / 6., 1. / 3., 0.],
+            b: array![1. / 6., 4. / 6., 1. / 6.],
+            c: array![0., 1. / 2., 1.],
+            embedded: None,
+            order: 4,
+            embedded_order: 3,
+        }
+    }
+
+    /// Embedded 6(4) pair with six stages on equidistant nodes for special problems `q'' = f(t, q)`.
+    ///
+    /// Positions and velocities are of sixth order, the weights are those of the Newton-Cotes rule with
+    /// `b_bar_i = b_i (1 - c_i)` and the embedded fourth order solution does not use the last stage.
+    pub fn rkn64() -> Self {
+        let b = array![19., 75., 50., 50., 75., 19.] / 288.;
+        let b_hat = array![1. / 96., 5. / 12., 5. / 48., 0., 15. / 32., 0.];
+        let c = array![0., 1. / 5., 2. / 5., 3. / 5., 4. / 5., 1.];
+        NystromTableau {
+            a_bar: array![
+                [0., 0., 0., 0., 0., 0.],
+                [1. / 50., 0., 0., 0., 0., 0.],
+                [0., 2. / 25., 0., 0., 0., 0.],
+                [1. / 50., 4. / 25., 0., 0., 0., 0.],
+                [2. / 25., 2. / 15., 2. / 75., 2. / 25., 0., 0.],
+                [1. / 19., 2. / 19., 7. / 19., -2. / 19., 3. / 38., 0.],
+            ],
+            a: None,
+            b_bar: &b * &(1. - &c),
+            embedded: Some((&b_hat * &(1. - &c), b_hat)),
+            b,
+            c,
+            order: 6,
+            embedded_order: 4,
+        }
+    }
+
+    /// Fourth order for `q'' = f(t, q, q')`, derived from [ButcherTableau::rk4].
+    pub fn rkn4() -> Self {
+        Self::from_runge_kutta(&ButcherTableau::rk4())
+    }
+
+    /// Embedded 5(4) pair for `q'' = f(t, q, q')`, derived from [ButcherTableau::dormand_prince].
+    pub fn rkn54() -> Self {
+        Self::from_runge_kutta(&ButcherTableau::dormand_prince())
+    }
+
+    /// Sixth order for `q'' = f(t, q, q')`, derived from [ButcherTableau::butcher6]. Special problems are solved
+    /// with fewer stages by [NystromTableau::rkn64].
+    pub fn rkn6() -> Self {
+        Self::from_runge_kutta(&ButcherTableau::butcher6())
+    }
+
+    pub fn stages(&self) -> usize {
+        self.b.len()
+    }
+}
+
+/// Explicit Runge-Kutta-Nyström scheme for `q'' = f(t, q, q')`.
+pub struct RungeKuttaNystrom<Accel>
+where
+    Accel: Fn(f64, ArrayView1<f64>, ArrayView1<f64>) -> Array1<f64>,
+{
+    accel: Accel,
+    tableau: NystromTableau,
+}
+
+impl<Accel> RungeKuttaNystrom<Accel>
+where
+    Accel: Fn(f64, ArrayView1<f64>, ArrayView1<f64>) -> Array1<f64>,
+{
+    pub fn new(tableau: NystromTableau, accel: Accel) -> Self {
+        RungeKuttaNystrom { accel, tableau }
+    }
+}
+
+impl<Accel> SecondOrder for RungeKuttaNystrom<Accel>
+where
+    Accel: Fn(f64, ArrayView1<f64>, ArrayView1<f64>) -> Array1<f64>,
+{
+    fn order(&self) -> usize {
+        self.tableau.order
+    }
+
+    fn embedded_order(&self) -> usize {
+        self.tableau.embedded_order
+    }
+
+    fn next(
+        &mut self,
+        t: f64,
+        q: ArrayView1<f64>,
+        v: ArrayView1<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> ndarray_linalg::error::Result<SecondOrderStep> {
+        let tab = &self.tableau;
+        stats.rhs_evaluations += tab.stages();
+        let mut f: Vec<Array1<f64>> = Vec::with_capacity(tab.stages());
+        for i in 0..tab.stages() {
+            let mut q_stage = q.to_owned();
+            q_stage.scaled_add(tab.c[i] * h, &v);
+            let mut v_stage = v.to_owned();
+            for (j, f) in f.iter().enumerate() {
+                q_stage.scaled_add(h * h * tab.a_bar[[i, j]], f);
+                if let Some(a) = &tab.a {
+                    v_stage.scaled_add(h * a[[i, j]], f);
+                }
+            }
+            f.push((self.accel)(
+                t + tab.c[i] * h,
+                q_stage.view(),
+                v_stage.view(),
+            ));
+        }
+
+        let weighted = |weights: &Array1<f64>| {
+            let mut sum = Array1::zeros(q.len());
+            f.iter()
+                .zip(weights.iter())
+                .for_each(|(f, &w)| sum.scaled_add(w, f));
+            sum
+        };
+        let mut q1 = q.to_owned();
+        q1.scaled_add(h, &v);
+        q1.scaled_add(h * h, &weighted(&tab.b_bar));
+        let mut v1 = v.to_owned();
+        v1.scaled_add(h, &weighted(&tab.b));
+
+        let error = tab.embedded.as_ref().map(|(b_bar_hat, b_hat)| {
+            let q_err = h * h * weighted(&(&tab.b_bar - b_bar_hat));
+            let v_err = h * weighted(&(&tab.b - b_hat));
+            ndarray::concatenate![ndarray::Axis(0), q_err, v_err]
+        });
+        Ok(SecondOrderStep {
+            q: q1,
+            v: v1,
+            error,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{
+        ensemble::max_drift,
+        ode::{solver::*, *},
+        test_support::*,
+    };
+
+    fn kepler(_t: f64, q: ArrayView1<f64>, _v: ArrayView1<f64>) -> Array1<f64> {
+        -&q / q.dot(&q).powf(1.5)
+    }
+
+    fn run(tableau: NystromTableau, h: f64, t: f64) -> Solution {
+        let scheme = RungeKuttaNystrom::new(tableau, kepler);
+        let mut ode = Ode::second_order(scheme, array![1., 0.], array![0., 1.2]);
+        ode.set_step_size(h)
+            .set_t(ending_at(t, h))
+            .set_with_progress(false);
+        ode.run()
+    }
+
+    #[test]
+    fn orders_on_kepler() {
+        let reference = state_at(&run(NystromTableau::rkn6(), 1e-3, 1.0), 1.0);
+        let tableaux = [
+            (NystromTableau::rkn4(), 4.),
+            (NystromTableau::nystrom4(), 4.),
+            (NystromTableau::rkn54(), 5.),
+            (NystromTableau::rkn6(), 6.),
+            (NystromTableau::rkn64(), 6.),
+        ];
+        for (tableau, p) in tableaux {
+            let error = |h| max_error(&state_at(&run(tableau.clone(), h, 1.0), 1.0), &reference);
+            let order = observed_order(error, 0.05);
+            assert!((order - p).abs() < 0.3, "{}: {order}", tableau.stages());
+        }
+    }
+
+    #[test]
+    fn embedded_estimate_keeps_the_energy() {
+        let energy = |x: ArrayView1<f64>| {
+            0.5 * (x[2] * x[2] + x[3] * x[3]) - 1. / (x[0] * x[0] + x[1] * x[1]).sqrt()
+        };
+        for tableau in [NystromTableau::rkn54(), NystromTableau::rkn64()] {
+            let scheme = RungeKuttaNystrom::new(tableau, kepler);
+            let mut ode = Ode::second_order(scheme, array![1., 0.], array![0., 1.2]);
+            ode.set_step_size(0.01)
+                .set_t(20.)
+                .set_tolerances(1e-10, 1e-10)
+                .set_with_progress(false);
+            let solution = ode.run();
+            assert_eq!(solution.termination, Termination::Completed);
+            assert!(max_drift(&solution, energy) < 1e-8);
+        }
+
+        let scheme = RungeKuttaNystrom::new(NystromTableau::nystrom4(), kepler);
+        let mut ode = Ode::second_order(scheme, array![1., 0.], array![0., 1.2]);
+        ode.set_tolerances(1e-10, 1e-10).set_with_progress(false);
+        let solution = ode.run();
+        assert!(matches!(
+            solution.termination,
+            Termination::Failed { t, .. } if t == 0.
+        ));
+    }
+
+    #[test]
+    fn time_dependent_forcing() {
+        // q'' = cos t with q = 1 - cos t and v = sin t
+        let forcing = |t: f64, q: ArrayView1<f64>, _v: ArrayView1<f64>| q.mapv(|_| t.cos());
+        for (tableau, p) in [
+            (NystromTableau::nystrom4(), 4.),
+            (NystromTableau::rkn54(), 5.),
+            (NystromTableau::rkn64(), 6.),
+        ] {
+            let error = |h| {
+                let scheme = RungeKuttaNystrom::new(tableau.clone(), forcing);
+                let mut ode = Ode::second_order(scheme, array![0.], array![0.]);
+                ode.set_step_size(h)
+                    .set_t(ending_at(1.0, h))
+                    .set_with_progress(false);
+                let solution = ode.run();
+                let n = solution.len() - 1;
+                let q_error = (solution.positions()[[n, 0]] - (1. - 1f64.cos())).abs();
+                let v_error = (solution.velocities()[[n, 0]] - 1f64.sin()).abs();
+                q_error.max(v_error)
+            };
+            let order = observed_order(error, 0.1);
+            assert!((order - p).abs() < 0.3, "{}: {order}", tableau.stages());
+        }
+    }
+
+    #[test]
+    fn not_a_number_fails() {
+        // uniform motion until the acceleration breaks down at q = 0.5
+        let accel = |_t: f64, q: ArrayView1<f64>, _v: ArrayView1<f64>| {
+            q.mapv(|q| if q < 0.5 { 0. } else { f64::NAN })
+        };
+        let scheme = RungeKuttaNystrom::new(NystromTableau::rkn54(), accel);
+        let mut ode = Ode::second_order(scheme, array![0.], array![1.]);
+        ode.set_tolerances(1e-8, 1e-8).set_with_progress(false);
+        let solution = ode.run();
+        match solution.termination {
+            Termination::Failed { t, .. } => assert!((t - 0.5).abs() < 1e-6, "{t}"),
+            termination => panic!("{termination:?}"),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use ndarray::{s, Array1, Array2, ArrayView1, ArrayView2};
+use ndarray_linalg::{Factorize, Solve};
+
+/// Coefficients of the diagonal (6, 6) Padé approximant of the exponential.
+const PADE: [f64; 7] = [
+    1.,
+    1. / 2.,
+    5. / 44.,
+    1. / 66.,
+    1. / 792.,
+    1. / 15840.,
+    1. / 665280.,
+];
+
+fn norm_inf(a: ArrayView2<f64>) -> f64 {
+    a.rows()
+        .into_iter()
+        .map(|row| row.iter().map(|x| x.abs()).sum::<f64>())
+        .fold(0., f64::max)
+}
+
+/// Matrix exponential by scaling and squaring with the diagonal (6, 6) Padé approximant.
+///
+/// The matrix is scaled until its ∞-norm is at most 1/2, where the approximant is exact up to rounding.
+pub fn expm(a: ArrayView2<f64>) -> Array2<f64> {
+    let n = a.nrows();
+    let norm = norm_inf(a);
+    let squarings = if norm > 0.5 {
+        (norm / 0.5).log2().ceil() as i32
+    } else {
+        0
+    };
+    let a = a.mapv(|x| x / 2f64.powi(squarings));
+
+    let mut power = Array2::eye(n);
+    let mut numerator = Array2::eye(n);
+    let mut denominator = Array2::eye(n);
+    for (k, &c) in PADE.iter().enumerate().skip(1) {
+        power = power.dot(&a);
+        numerator.scaled_add(c, &power);
+        denominator.scaled_add(if k % 2 == 0 { c } else { -c }, &power);
+    }
+    let lu = denominator
+        .factorize()
+        .expect("The Padé denominator is regular for a scaled matrix");
+    let mut e = Array2::zeros((n, n));
+    for (mut column, rhs) in e.columns_mut().into_iter().zip(numerator.columns()) {
+        column.assign(&lu.solve(&rhs.to_owned()).expect("Regular denominator"));
+    }
+    for _ in 0..squarings {
+        e = e.dot(&e);
+    }
+    e
+}
+
+/// The matrices `φ_0(Z), ..., φ_p(Z)` with `φ_0(z) = e^z` and `φ_k+1(z) = (φ_k(z) - 1/k!) / z`.
+///
+/// They are the blocks of the first block row of the exponential of `[[Z, I, 0], [0, 0, I], [0, 0, 0]]`
+/// with `p + 1` blocks per row.
+pub fn phi_functions(z: ArrayView2<f64>, p: usize) -> Vec<Array2<f64>> {
+    let n = z.nrows();
+    let mut augmented = Array2::zeros((n * (p + 1), n * (p + 1)));
+    augmented.slice_mut(s![..n, ..n]).assign(&z);
+    for k in 0..p {
+        for i in 0..n {
+            augmented[[k * n + i, (k + 1) * n + i]] = 1.;
+        }
+    }
+    let e = expm(augmented.view());
+    (0..=p)
+        .map(|k| e.slice(s![..n, k * n..(k + 1) * n]).to_owned())
+        .collect()
+}
+
+/// The vectors `φ_0(Z) v, ..., φ_p(Z) v` from the exponential of `[[Z, v, 0], [0, 0, 1], [0, 0, 0]]`,
+/// which has only `p` more rows than `Z`.
+pub fn phi_vectors(z: ArrayView2<f64>, v: ArrayView1<f64>, p: usize) -> Vec<Array1<f64>> {
+    let n = z.nrows();
+    let mut augmented = Array2::zeros((n + p, n + p));
+    augmented.slice_mut(s![..n, ..n]).assign(&z);
+    if p > 0 {
+        augmented.slice_mut(s![..n, n]).assign(&v);
+    }
+    for k in 1..p {
+        augmented[[n + k - 1, n + k]] = 1.;
+    }
+    let e = expm(augmented.view());
+    let mut phis = vec![e.slice(s![..n, ..n]).dot(&v)];
+    phis.extend((0..p).map(|k| e.slice(s![..n, n + k]).to_owned()));
+    phis
+}
+
+/// The vectors `φ_0(τ A) v, ..., φ_p(τ A) v` in the Krylov space of `A` and `v`, for a large `A` given by its products.
+///
+/// The Arnoldi process stops once the estimate of Saad, `τ β h_m+1,m |e_m^T φ_p+1(τ H_m) e_1|`, is below
+/// `tolerance β` with `β = |v|`, or at `max_dim` basis vectors. It converges fast once `τ |A|` is of the
+/// order of the dimension, otherwise the step size should be reduced.
+pub fn phi_krylov(
+    apply: impl Fn(ArrayView1<f64>) -> Array1<f64>,
+    v: ArrayView1<f64>,
+    τ: f64,
+    p: usize,
+    max_dim: usize,
+    tolerance: f64,
+) -> Vec<Array1<f64>> {
+    let n = v.len();
+    let β = v.dot(&v).sqrt();
+    if β == 0. {
+        return vec![Array1::zeros(n); p + 1];
+    }
+    let max_dim = max_dim.clamp(1, n);
+    let mut basis = vec![&v / β];
+    let mut hessenberg = Array2::zeros((max_dim + 1, max_dim));
+    for j in 0..max_dim {
+        let mut w = apply(basis[j].view());
+        for (i, b) in basis.iter().enumerate() {
+            let h = w.dot(b);
+            hessenberg[[i, j]] = h;
+            w.scaled_add(-h, b);
+        }
+        let norm = w.dot(&w).sqrt();
+        hessenberg[[j + 1, j]] = norm;
+        let m = j + 1;
+        let scale: f64 = hessenberg.column(j).iter().map(|h| h.abs()).sum();
+        // the Krylov space is invariant, so the approximation is exact
+        let breakdown = norm <= 1e-14 * scale;
+        if breakdown || m == max_dim || m % 5 == 0 {
+            let h_m = hessenberg.slice(s![..m, ..m]).mapv(|h| τ * h);
+            let mut e_1 = Array1::zeros(m);
+            e_1[0] = 1.;
+            let phis = phi_vectors(h_m.view(), e_1.view(), p + 1);
+            let estimate = τ.abs() * norm * phis[p + 1][m - 1].abs();
+            if breakdown || m == max_dim || estimate <= tolerance {
+                let v_m = Array2::from_shape_fn((n, m), |(i, k)| basis[k][i]);
+                return phis[..=p].iter().map(|φ| β * v_m.dot(φ)).collect();
+            }
+        }
+        basis.push(w / norm);
+    }
+    unreachable!("The Arnoldi process stops at the maximal dimension")
+}
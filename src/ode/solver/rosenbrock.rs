@@ -0,0 +1,422 @@
+use ndarray::{array, Array1, Array2, ArrayView1};
+use ndarray_linalg::{error::Result, Factorize, Solve};
+
+use crate::{ad::*, ode::*};
+
+/// Coefficients of a Rosenbrock method in the transformed form of Hairer and Wanner,
+/// which needs no products with the Jacobian:
+///
+/// `(I / (h γ) - J) k_i = f(x + Σ_j a_ij k_j) + Σ_j c_ij k_j / h`,
+/// `x1 = x + Σ_i m_i k_i` with the error estimate `Σ_i e_i k_i`.
+#[derive(Debug, Clone)]
+pub struct RosenbrockTableau {
+    pub gamma: f64,
+    pub a: Array2<f64>,
+    pub c: Array2<f64>,
+    pub m: Array1<f64>,
+    pub e: Array1<f64>,
+    pub order: usize,
+}
+
+/// A strictly lower triangular matrix from its rows below the diagonal.
+fn lower(rows: &[&[f64]]) -> Array2<f64> {
+    let s = rows.len() + 1;
+    Array2::from_shape_fn((s, s), |(i, j)| if j < i { rows[i - 1][j] } else { 0. })
+}
+
+impl RosenbrockTableau {
+    /// The transformed coefficients of a method in the standard form
+    /// `(I - h γ J) k_i = h f(x + Σ_j α_ij k_j) + h J Σ_j γ_ij k_j`, `x1 = x + Σ_i b_i k_i`,
+    /// with the weights `b_hat` of the embedded solution and the lower triangular `Γ = (γ_ij)`.
+    #[allow(non_snake_case)]
+    fn from_standard(
+        gamma: f64,
+        alpha: Array2<f64>,
+        Gamma: Array2<f64>,
+        b: Array1<f64>,
+        b_hat: Array1<f64>,
+        order: usize,
+    ) -> Self {
+        let s = b.len();
+        // forward substitution for the inverse of Γ
+        let mut inverse = Array2::<f64>::zeros((s, s));
+        for i in 0..s {
+            inverse[[i, i]] = 1. / Gamma[[i, i]];
+            for j in 0..i {
+                let sum: f64 = (j..i).map(|k| Gamma[[i, k]] * inverse[[k, j]]).sum();
+                inverse[[i, j]] = -sum / Gamma[[i, i]];
+            }
+        }
+        let m = b.dot(&inverse);
+        RosenbrockTableau {
+            gamma,
+            a: alpha.dot(&inverse),
+            c: Array2::from_diag_elem(s, 1. / gamma) - &inverse,
+            e: &m - &b_hat.dot(&inverse),
+            m,
+            order,
+        }
+    }
+
+    /// Third order W-method ROS34PW2 of Rang and Angermann with four stages, stiffly accurate and L-stable.
+    ///
+    /// Its order conditions hold for any matrix in place of the Jacobian, so it keeps the order with
+    /// [Rosenbrock::set_approximate_jacobian] and [Rosenbrock::set_jacobian_reuse].
+    #[allow(non_snake_case)]
+    pub fn ros34pw2() -> Self {
+        let gamma = 0.435866521508459;
+        let alpha = lower(&[
+            &[0.871733043016918],
+            &[0.8445706001536942, -0.11299064236484185],
+            &[0., 0., 1.],
+        ]);
+        let Gamma = Array2::from_diag_elem(4, gamma)
+            + lower(&[
+                &[-0.871733043016918],
+                &[-0.9033805701304408, 0.054180672388095326],
+                &[0.24212380706095346, -1.2232505839045147, 0.5452602553351021],
+            ]);
+        let b = array![
+            0.24212380706095346,
+            -1.2232505839045147,
+            1.545260255335102,
+            0.435866521508459
+        ];
+        // embedded weights of order two
+        let b_hat = array![
+            0.3781090314581937,
+            -0.09604229221242318,
+            0.5,
+            0.2179332607542295
+        ];
+        Self::from_standard(gamma, alpha, Gamma, b, b_hat, 3)
+    }
+
+    /// Third order method of Lang and Verwer with three stages, A-stable and free of order reduction
+    /// for parabolic problems.
+    pub fn ros3p() -> Self {
+        let gamma = 0.5 + 3f64.sqrt() / 6.;
+        let ig = 1. / gamma;
+        let c32 = -ig * (2. - ig / 2.);
+        let b2 = ig * (2. / 3. - ig / 6.);
+        let m = array![ig * (1. + b2), b2, ig / 3.];
+        // embedded weights of order two
+        let m_hat = array![2.113248654051871, 1.0, 0.4226497308103742];
+        RosenbrockTableau {
+            gamma,
+            a: lower(&[&[ig], &[ig, 0.]]),
+            c: lower(&[&[-ig * ig], &[-ig * (1. - c32), c32]]),
+            e: &m - &m_hat,
+            m,
+            order: 3,
+        }
+    }
+
+    /// Fourth order stiffly accurate method of Hairer and Wanner with six stages, L-stable.
+    pub fn rodas4() -> Self {
+        let a5 = [
+            1.221224509226641,
+            6.019134481288629,
+            12.53708332932087,
+            -0.687886036105895,
+        ];
+        RosenbrockTableau {
+            gamma: 0.25,
+            a: lower(&[
+                &[1.544],
+                &[0.9466785280815826, 0.2557011698983284],
+                &[3.314825187068521, 2.896124015972201, 0.9986419139977817],
+                &a5,
+                &[a5[0], a5[1], a5[2], a5[3], 1.],
+            ]),
+            c: lower(&[
+                &[-5.6688],
+                &[-2.430093356833875, -0.2063599157091915],
+                &[-0.1073529058151375, -9.594562251023355, -20.47028614809616],
+                &[
+                    7.496443313967647,
+                    -10.24680431464352,
+                    -33.99990352819905,
+                    11.7089089320616,
+                ],
+                &[
+                    8.083246795921522,
+                    -7.981132988064893,
+                    -31.52159432874371,
+                    16.31930543123136,
+                    -6.058818238834054,
+                ],
+            ]),
+            m: array![a5[0], a5[1], a5[2], a5[3], 1., 1.],
+            e: array![0., 0., 0., 0., 0., 1.],
+            order: 4,
+        }
+    }
+
+    /// Fifth order stiffly accurate method of Di Marzo with eight stages, L-stable.
+    pub fn rodas5() -> Self {
+        let a6 = [
+            -14.09640773051259,
+            6.925207756232704,
+            -41.47510893210728,
+            2.343771018586405,
+            24.13215229196062,
+        ];
+        let a7 = [a6[0], a6[1], a6[2], a6[3], a6[4], 1.];
+        RosenbrockTableau {
+            gamma: 0.19,
+            a: lower(&[
+                &[2.0],
+                &[3.040894194418781, 1.041747909077569],
+                &[2.576417536461461, 1.62208306077664, -0.9089668560264532],
+                &[
+                    2.760842080225597,
+                    1.446624659844071,
+                    -0.3036980084553738,
+                    0.2877498600325443,
+                ],
+                &a6,
+                &a7,
+                &[a7[0], a7[1], a7[2], a7[3], a7[4], a7[5], 1.],
+            ]),
+            c: lower(&[
+                &[-10.31323885133993],
+                &[-21.04823117650003, -7.234992135176716],
+                &[32.22751541853323, -4.943732386540191, 19.44922031041879],
+                &[
+                    -20.69865579590063,
+                    -8.816374604402768,
+                    1.260436877740897,
+                    -0.7495647613787146,
+                ],
+                &[
+                    -46.22004352711257,
+                    -17.49534862857472,
+                    -289.6389582892057,
+                    93.60855400400906,
+                    318.3822534212147,
+                ],
+                &[
+                    34.20013733472935,
+                    -14.1553540271769,
+                    57.823356409884,
+                    25.83362985412365,
+                    1.408950972071624,
+                    -6.551835421242162,
+                ],
+                &[
+                    42.57076742291101,
+                    -13.80770672017997,
+                    93.98938432427124,
+                    18.77919633714503,
+                    -31.5835918722337,
+                    -6.685968952921985,
+                    -5.810979938412932,
+                ],
+            ]),
+            m: array![a7[0], a7[1], a7[2], a7[3], a7[4], a7[5], 1., 1.],
+            e: array![0., 0., 0., 0., 0., 0., 0., 1.],
+            order: 5,
+        }
+    }
+
+    pub fn stages(&self) -> usize {
+        self.m.len()
+    }
+}
+
+/// An approximation of the Jacobian of the flow at a state, see [Rosenbrock::set_approximate_jacobian].
+pub type ApproximateJacobian = Box<dyn Fn(ArrayView1<f64>) -> Array2<f64> + Send + Sync>;
+
+/// Linearly implicit Rosenbrock method with adaptive steps, driven by [Ode::adaptive].
+///
+/// Each step needs one Jacobian of the flow, from automatic differentiation, and one LU factorization,
+/// the stages are linear solves without any Newton iteration. A rejected step keeps the Jacobian.
+///
+/// The Jacobian may be kept for several steps or replaced by an approximation. Only the W-method
+/// [RosenbrockTableau::ros34pw2] keeps its order then, the order conditions of the other tableaus
+/// assume the exact Jacobian and their embedded error estimate makes the controller take smaller steps.
+/// The flow is autonomous, a time dependence has to be added to the state.
+pub struct Rosenbrock<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    tableau: RosenbrockTableau,
+    flow: Flow,
+    approximation: Option<ApproximateJacobian>,
+    reuse: usize,
+    /// The last Jacobian, the state it belongs to and the steps it was used for.
+    jacobian: Option<(Array1<f64>, Array2<f64>, usize)>,
+}
+
+impl<Flow> Rosenbrock<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    pub fn new(tableau: RosenbrockTableau, flow: Flow) -> Self {
+        Rosenbrock {
+            tableau,
+            flow,
+            approximation: None,
+            reuse: 1,
+            jacobian: None,
+        }
+    }
+
+    /// Keeps a Jacobian for up to `steps` accepted steps.
+    pub fn set_jacobian_reuse(&mut self, steps: usize) -> &mut Self {
+        self.reuse = steps.max(1);
+        self
+    }
+
+    /// Uses `approximation` instead of the exact Jacobian, e.g. only its stiff part or a sparse pattern,
+    /// which needs a W-method like [RosenbrockTableau::ros34pw2] to keep the order.
+    pub fn set_approximate_jacobian(
+        &mut self,
+        approximation: impl Fn(ArrayView1<f64>) -> Array2<f64> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.approximation = Some(Box::new(approximation));
+        self
+    }
+
+    pub fn tableau(&self) -> &RosenbrockTableau {
+        &self.tableau
+    }
+
+    /// The Jacobian for a step from `x`, computed again once it is too old.
+    #[allow(non_snake_case)]
+    fn jacobian(&mut self, x: ArrayView1<f64>, stats: &mut SolverStats) -> Array2<f64> {
+        match self.jacobian.as_mut() {
+            // A repeated step, the Jacobian was already counted for it
+            Some((x_J, J, _)) if *x_J == x => return J.clone(),
+            Some((x_J, J, age)) if *age < self.reuse => {
+                x_J.assign(&x);
+                *age += 1;
+                return J.clone();
+            }
+            _ => {}
+        }
+        let J = match &self.approximation {
+            Some(approximation) => approximation(x),
+            None => {
+                stats.jacobian_evaluations += 1;
+                flow_jacobian(&self.flow, x)
+            }
+        };
+        self.jacobian = Some((x.to_owned(), J.clone(), 1));
+        J
+    }
+}
+
+impl<Flow> Embedded for Rosenbrock<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    fn order(&self) -> usize {
+        self.tableau.order
+    }
+
+    #[allow(non_snake_case)]
+    fn next(
+        &mut self,
+        _t: f64,
+        x: ArrayView1<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> Result<EmbeddedStep> {
+        let J = self.jacobian(x, stats);
+        let n = x.len();
+        let RosenbrockTableau {
+            gamma, a, c, m, e, ..
+        } = &self.tableau;
+        let M = Array2::from_diag_elem(n, 1. / (h * gamma)) - J;
+        let lu = M.factorize()?;
+        stats.lu_factorizations += 1;
+
+        let mut k: Vec<Array1<f64>> = Vec::with_capacity(m.len());
+        for i in 0..m.len() {
+            let mut stage = x.to_owned();
+            let mut rhs = Array1::zeros(n);
+            for (j, k) in k.iter().enumerate() {
+                stage.scaled_add(a[[i, j]], k);
+                rhs.scaled_add(c[[i, j]] / h, k);
+            }
+            rhs += &eval_flow(&self.flow, stage.view());
+            k.push(lu.solve(&rhs)?);
+        }
+        stats.rhs_evaluations += m.len();
+
+        let mut x1 = x.to_owned();
+        let mut error = Array1::zeros(n);
+        for ((k, &m), &e) in k.iter().zip(m.iter()).zip(e.iter()) {
+            x1.scaled_add(m, k);
+            error.scaled_add(e, k);
+        }
+        Ok(EmbeddedStep { x: x1, error })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1};
+
+    use crate::{ode::solver::*, test_support::*};
+
+    /// The final state of fixed steps of size `h` until 1 and the largest error estimate.
+    fn fixed(tableau: RosenbrockTableau, h: f64) -> (Array1<f64>, f64) {
+        fixed_steps(
+            &mut Rosenbrock::new(tableau, pendulum),
+            array![1.0, 0.0].view(),
+            h,
+        )
+    }
+
+    #[test]
+    fn order_and_stiff_problem() {
+        let (reference, _) = fixed(RosenbrockTableau::rodas5(), 1e-3);
+        for (tableau, h) in [
+            (RosenbrockTableau::ros3p(), 0.05),
+            (RosenbrockTableau::ros34pw2(), 0.05),
+            (RosenbrockTableau::rodas4(), 0.1),
+            (RosenbrockTableau::rodas5(), 0.2),
+        ] {
+            let p = tableau.order as f64;
+            let order = observed_order(|h| max_error(&fixed(tableau.clone(), h).0, &reference), h);
+            assert!((order - p).abs() < 0.3, "{p}: {order}");
+            // the local error of the embedded solution is of order p
+            let order = observed_order(|h| fixed(tableau.clone(), h).1, h);
+            assert!((order - p).abs() < 0.3, "{p}: {order}");
+        }
+
+        for tableau in [RosenbrockTableau::rodas4(), RosenbrockTableau::rodas5()] {
+            let solution = relaxation_oscillation(Rosenbrock::new(tableau, van_der_pol));
+            assert_eq!(solution.stats.jacobian_evaluations, solution.len() - 1);
+            assert!(solution.len() < 1000, "{}", solution.len());
+        }
+
+        let mut scheme = Rosenbrock::new(RosenbrockTableau::rodas4(), van_der_pol);
+        scheme.set_jacobian_reuse(5);
+        let solution = relaxation_oscillation(scheme);
+        assert!(solution.stats.jacobian_evaluations < solution.len() / 2);
+    }
+
+    #[test]
+    fn w_method_keeps_its_order() {
+        let (reference, _) = fixed(RosenbrockTableau::rodas5(), 1e-3);
+        // the Jacobian of the pendulum linearized at the wrong angle
+        let order = |tableau: RosenbrockTableau| {
+            let error = |h: f64| {
+                let mut scheme = Rosenbrock::new(tableau.clone(), pendulum);
+                scheme.set_approximate_jacobian(|_| array![[0., 1.], [-0.5, 0.]]);
+                let (x, _) = fixed_steps(&mut scheme, array![1.0, 0.0].view(), h);
+                max_error(&x, &reference)
+            };
+            observed_order(error, 0.05)
+        };
+        let w = order(RosenbrockTableau::ros34pw2());
+        assert!((w - 3.).abs() < 0.3, "{w}");
+        // a Rosenbrock method drops to first order
+        let ros3p = order(RosenbrockTableau::ros3p());
+        assert!(ros3p < 1.5, "{ros3p}");
+    }
+}
@@ -0,0 +1,247 @@
+use std::ops::{Add, Mul};
+
+use ndarray::{array, Array1, Array2, ArrayView1};
+
+use crate::ode::*;
+
+/// Coefficients of an explicit Runge-Kutta method.
+///
+/// Embedded pairs carry the weights `b_hat` of a second solution of lower order,
+/// whose difference to the main solution serves as error estimate.
+#[derive(Debug, Clone)]
+pub struct ButcherTableau {
+    pub a: Array2<f64>,
+    pub b: Array1<f64>,
+    pub c: Array1<f64>,
+    pub b_hat: Option<Array1<f64>>,
+    pub order: usize,
+}
+
+impl ButcherTableau {
+    pub fn euler() -> Self {
+        ButcherTableau {
+            a: array![[0.]],
+            b: array![1.],
+            c: array![0.],
+            b_hat: None,
+            order: 1,
+        }
+    }
+
+    /// The classical Runge-Kutta method of order four.
+    pub fn rk4() -> Self {
+        ButcherTableau {
+            a: array![
+                [0., 0., 0., 0.],
+                [0.5, 0., 0., 0.],
+                [0., 0.5, 0., 0.],
+                [0., 0., 1., 0.]
+            ],
+            b: array![1. / 6., 1. / 3., 1. / 3., 1. / 6.],
+            c: array![0., 0.5, 0.5, 1.],
+            b_hat: None,
+            order: 4,
+        }
+    }
+
+    /// Butcher's seven stage method of order six.
+    pub fn butcher6() -> Self {
+        ButcherTableau {
+            a: array![
+                [0., 0., 0., 0., 0., 0., 0.],
+                [1. / 3., 0., 0., 0., 0., 0., 0.],
+                [0., 2. / 3., 0., 0., 0., 0., 0.],
+                [1. / 12., 1. / 3., -1. / 12., 0., 0., 0., 0.],
+                [-1. / 16., 9. / 8., -3. / 16., -3. / 8., 0., 0., 0.],
+                [0., 9. / 8., -3. / 8., -3. / 4., 1. / 2., 0., 0.],
+                [
+                    9. / 44.,
+                    -9. / 11.,
+                    63. / 44.,
+                    18. / 11.,
+                    0.,
+                    -16. / 11.,
+                    0.
+                ]
+            ],
+            b: array![
+                11. / 120.,
+                0.,
+                27. / 40.,
+                27. / 40.,
+                -4. / 15.,
+                -4. / 15.,
+                11. / 120.
+            ],
+            c: array![0., 1. / 3., 2. / 3., 1. / 3., 1. / 2., 1. / 2., 1.],
+            b_hat: None,
+            order: 6,
+        }
+    }
+
+    /// Bogacki-Shampine 3(2) pair, the last stage is evaluated at the new solution (FSAL).
+    pub fn bogacki_shampine() -> Self {
+        ButcherTableau {
+            a: array![
+                [0., 0., 0., 0.],
+                [0.5, 0., 0., 0.],
+                [0., 0.75, 0., 0.],
+                [2. / 9., 1. / 3., 4. / 9., 0.]
+            ],
+            b: array![2. / 9., 1. / 3., 4. / 9., 0.],
+            c: array![0., 0.5, 0.75, 1.],
+            b_hat: Some(array![7. / 24., 0.25, 1. / 3., 0.125]),
+            order: 3,
+        }
+    }
+
+    /// Dormand-Prince 5(4) pair, the last stage is evaluated at the new solution (FSAL).
+    pub fn dormand_prince() -> Self {
+        ButcherTableau {
+            a: array![
+                [0., 0., 0., 0., 0., 0., 0.],
+                [1. / 5., 0., 0., 0., 0., 0., 0.],
+                [3. / 40., 9. / 40., 0., 0., 0., 0., 0.],
+                [44. / 45., -56. / 15., 32. / 9., 0., 0., 0., 0.],
+                [
+                    19372. / 6561.,
+                    -25360. / 2187.,
+                    64448. / 6561.,
+                    -212. / 729.,
+                    0.,
+                    0.,
+                    0.
+                ],
+                [
+                    9017. / 3168.,
+                    -355. / 33.,
+                    46732. / 5247.,
+                    49. / 176.,
+                    -5103. / 18656.,
+                    0.,
+                    0.
+                ],
+                [
+                    35. / 384.,
+                    0.,
+                    500. / 1113.,
+                    125. / 192.,
+                    -2187. / 6784.,
+                    11. / 84.,
+                    0.
+                ]
+            ],
+            b: array![
+                35. / 384.,
+                0.,
+                500. / 1113.,
+                125. / 192.,
+                -2187. / 6784.,
+                11. / 84.,
+                0.
+            ],
+            c: array![0., 1. / 5., 3. / 10., 4. / 5., 8. / 9., 1., 1.],
+            b_hat: Some(array![
+                5179. / 57600.,
+                0.,
+                7571. / 16695.,
+                393. / 640.,
+                -92097. / 339200.,
+                187. / 2100.,
+                1. / 40.
+            ]),
+            order: 5,
+        }
+    }
+
+    pub fn stages(&self) -> usize {
+        self.b.len()
+    }
+
+    /// Evaluates all stages `k_i = f(c_i, x + h Σ_j a_ij k_j)`.
+    ///
+    /// The flow receives the node `c_i`, so that non autonomous or delayed problems can derive the stage time.
+    /// It works on [f64] as well as on [crate::ad::AD] states.
+    pub fn stage_slopes<T, Flow>(&self, mut flow: Flow, x: ArrayView1<T>, h: f64) -> Vec<Array1<T>>
+    where
+        T: Copy + Add<Output = T> + Mul<f64, Output = T>,
+        Flow: FnMut(f64, ArrayView1<T>) -> Array1<T>,
+    {
+        let mut k: Vec<Array1<T>> = Vec::with_capacity(self.stages());
+        for i in 0..self.stages() {
+            let weights = self.a.row(i).map(|a| h * a);
+            let stage = combine(x, &k, weights.view());
+            k.push(flow(self.c[i], stage.view()));
+        }
+        k
+    }
+
+    /// `x + h Σ_i b_i k_i`
+    pub fn solution<T>(&self, x: ArrayView1<T>, k: &[Array1<T>], h: f64) -> Array1<T>
+    where
+        T: Copy + Add<Output = T> + Mul<f64, Output = T>,
+    {
+        combine(x, k, self.b.map(|b| h * b).view())
+    }
+
+    /// `h Σ_i (b_i - b_hat_i) k_i`, if the tableau carries an embedded method.
+    pub fn error_estimate(&self, k: &[Array1<f64>], h: f64) -> Option<Array1<f64>> {
+        let b_hat = self.b_hat.as_ref()?;
+        let mut err = Array1::zeros(k[0].len());
+        for ((k, &b), &b_hat) in k.iter().zip(self.b.iter()).zip(b_hat.iter()) {
+            err.scaled_add(h * (b - b_hat), k);
+        }
+        Some(err)
+    }
+}
+
+/// `x + Σ_j w_j k_j`, skipping vanishing weights.
+fn combine<T>(x: ArrayView1<T>, k: &[Array1<T>], weights: ArrayView1<f64>) -> Array1<T>
+where
+    T: Copy + Add<Output = T> + Mul<f64, Output = T>,
+{
+    let mut result = x.to_owned();
+    for (k, &w) in k.iter().zip(weights.iter()) {
+        if w != 0. {
+            result
+                .iter_mut()
+                .zip(k.iter())
+                .for_each(|(r, &k)| *r = *r + k * w);
+        }
+    }
+    result
+}
+
+/// Explicit Runge-Kutta method with fixed step size for any [ButcherTableau].
+pub struct RungeKutta<Flow>
+where
+    Flow: Fn(ArrayView1<f64>) -> Array1<f64>,
+{
+    flow: Flow,
+    tableau: ButcherTableau,
+    h: f64,
+}
+
+impl<Flow> RungeKutta<Flow>
+where
+    Flow: Fn(ArrayView1<f64>) -> Array1<f64>,
+{
+    pub fn new(h: f64, tableau: ButcherTableau, flow: Flow) -> Self {
+        RungeKutta { flow, tableau, h }
+    }
+}
+
+impl<Flow> Explicit for RungeKutta<Flow>
+where
+    Flow: Fn(ArrayView1<f64>) -> Array1<f64>,
+{
+    #[inline]
+    fn next(&self, x: ArrayView1<f64>) -> ndarray_linalg::error::Result<Array1<f64>> {
+        let k = self.tableau.stage_slopes(|_, x| (self.flow)(x), x, self.h);
+        Ok(self.tableau.solution(x, &k, self.h))
+    }
+
+    fn evaluations(&self) -> usize {
+        self.tableau.stages()
+    }
+}
@@ -0,0 +1,388 @@
+use ndarray::{array, Array1, Array2, ArrayView1, Zip};
+use ndarray_linalg::{error::Result, Factorize, Solve};
+
+use crate::{ad::*, ode::*};
+
+/// Butcher tableau of a singly diagonally implicit Runge-Kutta method with an embedded method for the error estimate.
+///
+/// All implicit stages share the diagonal coefficient `γ`, an explicit first stage with `a_11 = 0` makes it an ESDIRK method.
+#[derive(Debug, Clone)]
+pub struct DirkTableau {
+    pub a: Array2<f64>,
+    pub b: Array1<f64>,
+    pub b_hat: Array1<f64>,
+    pub c: Array1<f64>,
+    pub order: usize,
+}
+
+impl DirkTableau {
+    /// Two stage method of Alexander, L-stable and stiffly accurate, with the implicit Euler as embedded method.
+    pub fn sdirk2() -> Self {
+        let γ = 1. - 0.5f64.sqrt();
+        DirkTableau {
+            a: array![[γ, 0.], [1. - γ, γ]],
+            b: array![1. - γ, γ],
+            b_hat: array![1., 0.],
+            c: array![γ, 1.],
+            order: 2,
+        }
+    }
+
+    /// Five stage method of order four of Hairer and Wanner with `γ = 1/4`, L-stable and stiffly accurate.
+    pub fn sdirk4() -> Self {
+        DirkTableau {
+            a: array![
+                [1. / 4., 0., 0., 0., 0.],
+                [1. / 2., 1. / 4., 0., 0., 0.],
+                [17. / 50., -1. / 25., 1. / 4., 0., 0.],
+                [371. / 1360., -137. / 2720., 15. / 544., 1. / 4., 0.],
+                [25. / 24., -49. / 48., 125. / 16., -85. / 12., 1. / 4.]
+            ],
+            b: array![25. / 24., -49. / 48., 125. / 16., -85. / 12., 1. / 4.],
+            b_hat: array![59. / 48., -17. / 96., 225. / 32., -85. / 12., 0.],
+            c: array![1. / 4., 3. / 4., 11. / 20., 1. / 2., 1.],
+            order: 4,
+        }
+    }
+
+    /// A trapezoidal rule up to `γ = 2 - √2` followed by BDF2, written as ESDIRK method, L-stable.
+    ///
+    /// The embedded method is the one of Hosea and Shampine.
+    #[allow(non_snake_case)]
+    pub fn tr_bdf2() -> Self {
+        let γ = 2. - 2f64.sqrt();
+        let d = γ / 2.;
+        let w = 2f64.sqrt() / 4.;
+        DirkTableau {
+            a: array![[0., 0., 0.], [d, d, 0.], [w, w, d]],
+            b: array![w, w, d],
+            b_hat: array![(1. - w) / 3., (3. * w + 1.) / 3., d / 3.],
+            c: array![0., γ, 1.],
+            order: 2,
+        }
+    }
+
+    /// Four stage ESDIRK pair of order three and two of Kværnø, L-stable and stiffly accurate.
+    pub fn kvaerno3() -> Self {
+        let γ = 0.4358665215;
+        let a3 = [0.490563388419108, 0.073570090080892, γ];
+        let a4 = [0.308809969973036, 1.490563388254106, -1.235239879727145, γ];
+        DirkTableau {
+            a: array![
+                [0., 0., 0., 0.],
+                [γ, γ, 0., 0.],
+                [a3[0], a3[1], a3[2], 0.],
+                [a4[0], a4[1], a4[2], a4[3]]
+            ],
+            b: Array1::from(a4.to_vec()),
+            b_hat: array![a3[0], a3[1], a3[2], 0.],
+            c: array![0., 2. * γ, 1., 1.],
+            order: 3,
+        }
+    }
+
+    /// Five stage ESDIRK pair of order four and three of Kværnø, L-stable and stiffly accurate.
+    pub fn kvaerno4() -> Self {
+        let γ = 0.4358665215;
+        let a3 = [0.140737774731968, -0.108365551378832, γ];
+        let a4 = [0.102399400616089, -0.376878452267324, 0.838612530151233, γ];
+        let a5 = [
+            0.157024897860995,
+            0.117330441357768,
+            0.61667803039168,
+            -0.326899891110444,
+            γ,
+        ];
+        DirkTableau {
+            a: array![
+                [0., 0., 0., 0., 0.],
+                [γ, γ, 0., 0., 0.],
+                [a3[0], a3[1], a3[2], 0., 0.],
+                [a4[0], a4[1], a4[2], a4[3], 0.],
+                [a5[0], a5[1], a5[2], a5[3], a5[4]]
+            ],
+            b: Array1::from(a5.to_vec()),
+            b_hat: array![a4[0], a4[1], a4[2], a4[3], 0.],
+            c: array![0., 2. * γ, 0.468238744853136, 1., 1.],
+            order: 4,
+        }
+    }
+
+    /// Seven stage ESDIRK pair of order five and four of Kværnø, L-stable and stiffly accurate.
+    pub fn kvaerno5() -> Self {
+        let γ = 0.26;
+        let a3 = [0.13, 0.8403332099679081, γ];
+        let a4 = [
+            0.22371961478320504,
+            0.476755323197997,
+            -0.06470895363112615,
+            γ,
+        ];
+        let a5 = [
+            0.16648564323248322,
+            0.1045001884159172,
+            0.03631482272098715,
+            -0.13090704451073998,
+            γ,
+        ];
+        let a6 = [
+            0.13855640231268224,
+            0.,
+            -0.04245337201752043,
+            0.02446657898003141,
+            0.6194303907248068,
+            γ,
+        ];
+        let a7 = [
+            0.13659751177640292,
+            0.,
+            -0.05496908796538376,
+            -0.04118626728321046,
+            0.629933048990164,
+            0.06962479448202728,
+            γ,
+        ];
+        DirkTableau {
+            a: array![
+                [0., 0., 0., 0., 0., 0., 0.],
+                [γ, γ, 0., 0., 0., 0., 0.],
+                [a3[0], a3[1], a3[2], 0., 0., 0., 0.],
+                [a4[0], a4[1], a4[2], a4[3], 0., 0., 0.],
+                [a5[0], a5[1], a5[2], a5[3], a5[4], 0., 0.],
+                [a6[0], a6[1], a6[2], a6[3], a6[4], a6[5], 0.],
+                [a7[0], a7[1], a7[2], a7[3], a7[4], a7[5], a7[6]]
+            ],
+            b: Array1::from(a7.to_vec()),
+            b_hat: array![a6[0], a6[1], a6[2], a6[3], a6[4], a6[5], 0.],
+            c: array![
+                0.,
+                2. * γ,
+                1.230333209967908,
+                0.895765984350076,
+                0.436393609858648,
+                1.,
+                1.
+            ],
+            order: 5,
+        }
+    }
+
+    pub fn stages(&self) -> usize {
+        self.b.len()
+    }
+
+    /// The diagonal coefficient shared by the implicit stages.
+    pub fn gamma(&self) -> f64 {
+        self.a[[self.stages() - 1, self.stages() - 1]]
+    }
+}
+
+/// The stage equation `Z - known - h γ f(Z) = 0`.
+pub(crate) struct Stage<'a, Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    pub(crate) known: Array1<AD>,
+    pub(crate) hγ: AD,
+    pub(crate) flow: &'a Flow,
+}
+
+impl<Flow> Residual for Stage<'_, Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    #[inline]
+    fn eval(&self, z: ArrayView1<AD>, update: &mut Array1<AD>) {
+        (self.flow)(z, update);
+        let hγ = self.hγ;
+        Zip::from(z)
+            .and(&self.known)
+            .and(update)
+            .for_each(|&z, &known, f| *f = z - known - hγ * *f);
+    }
+}
+
+/// Singly diagonally implicit Runge-Kutta method with adaptive steps, driven by [Ode::adaptive].
+///
+/// Each implicit stage is solved by [simplified_newton_with_stats] with the iteration matrix `I - h γ J`,
+/// which is the same for all stages, so one Jacobian at the start of the step and one LU factorization
+/// are needed per step. A stage starts from the slope of the previous stage as predictor.
+/// A step whose stage iteration fails is rejected and repeated with a smaller step size.
+pub struct Sdirk<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    tableau: DirkTableau,
+    flow: Flow,
+    ɛ: f64,
+    max_iter: usize,
+    /// The last Jacobian and the state it belongs to, kept for a repeated step.
+    jacobian: Option<(Array1<f64>, Array2<f64>)>,
+}
+
+impl<Flow> Sdirk<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    pub fn new(tableau: DirkTableau, flow: Flow) -> Self {
+        let γ = tableau.gamma();
+        let s = tableau.stages();
+        assert!(γ > 0., "The implicit stages need a positive diagonal");
+        assert!(
+            (1..s).all(|i| tableau.a[[i, i]] == γ)
+                && (tableau.a[[0, 0]] == γ || tableau.a[[0, 0]] == 0.),
+            "The tableau is not singly diagonally implicit"
+        );
+        Sdirk {
+            tableau,
+            flow,
+            ɛ: 1e-10,
+            max_iter: 10,
+            jacobian: None,
+        }
+    }
+
+    /// Relative tolerance of the stage iterations.
+    pub fn set_epsilon(&mut self, ɛ: f64) -> &mut Self {
+        self.ɛ = ɛ;
+        self
+    }
+
+    pub fn set_max_iter(&mut self, max_iter: usize) -> &mut Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn tableau(&self) -> &DirkTableau {
+        &self.tableau
+    }
+
+    #[allow(non_snake_case)]
+    fn jacobian(&mut self, x: ArrayView1<f64>, stats: &mut SolverStats) -> Array2<f64> {
+        if let Some((x_J, J)) = &self.jacobian {
+            if *x_J == x {
+                return J.clone();
+            }
+        }
+        stats.jacobian_evaluations += 1;
+        let J = flow_jacobian(&self.flow, x);
+        self.jacobian = Some((x.to_owned(), J.clone()));
+        J
+    }
+}
+
+impl<Flow> Embedded for Sdirk<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    fn order(&self) -> usize {
+        self.tableau.order
+    }
+
+    #[allow(non_snake_case)]
+    fn next(
+        &mut self,
+        _t: f64,
+        x: ArrayView1<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> Result<EmbeddedStep> {
+        let J = self.jacobian(x, stats);
+        let n = x.len();
+        let γ = self.tableau.gamma();
+        let lu = (Array2::eye(n) - h * γ * J).factorize()?;
+        stats.lu_factorizations += 1;
+
+        let failures = stats.newton_failures;
+        let a = &self.tableau.a;
+        let mut k: Vec<Array1<f64>> = Vec::with_capacity(self.tableau.stages());
+        for i in 0..self.tableau.stages() {
+            if a[[i, i]] == 0. {
+                k.push(eval_flow(&self.flow, x));
+                stats.rhs_evaluations += 1;
+                continue;
+            }
+            let mut known = x.to_owned();
+            for (j, k) in k.iter().enumerate() {
+                known.scaled_add(h * a[[i, j]], k);
+            }
+            let mut predictor = known.clone();
+            if let Some(k) = k.last() {
+                predictor.scaled_add(h * γ, k);
+            }
+            let stage = Stage {
+                known: known.to_ad(),
+                hγ: AD::AD0(h * γ),
+                flow: &self.flow,
+            };
+            let z = simplified_newton_with_stats(
+                self.ɛ,
+                &stage,
+                predictor.to_ad(),
+                &lu,
+                self.max_iter,
+                stats,
+            )?
+            .to_f64();
+            // the slope from the stage equation, which is exact for stiff components
+            k.push((z - known) / (h * γ));
+        }
+
+        let mut x1 = x.to_owned();
+        let mut error = Array1::zeros(n);
+        let DirkTableau { b, b_hat, .. } = &self.tableau;
+        for ((k, &b), &b_hat) in k.iter().zip(b.iter()).zip(b_hat.iter()) {
+            x1.scaled_add(h * b, k);
+            error.scaled_add(h * (b - b_hat), k);
+        }
+        // damps the stiff components of the estimate, which the embedded method does not resolve (Shampine)
+        let mut error = lu.solve(&error)?;
+        if stats.newton_failures > failures {
+            // rejects the step, the controller shrinks it as far as possible
+            error.fill(f64::INFINITY);
+        }
+        Ok(EmbeddedStep { x: x1, error })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1};
+
+    use crate::{ode::solver::*, test_support::*};
+
+    fn fixed(tableau: DirkTableau, h: f64) -> Array1<f64> {
+        let mut scheme = Sdirk::new(tableau, pendulum);
+        scheme.set_epsilon(1e-14);
+        fixed_steps(&mut scheme, array![1.0, 0.0].view(), h).0
+    }
+
+    #[test]
+    fn order_and_stiff_problem() {
+        let reference = fixed(DirkTableau::sdirk4(), 1e-3);
+        for (tableau, h) in [
+            (DirkTableau::sdirk2(), 0.05),
+            (DirkTableau::sdirk4(), 0.1),
+            (DirkTableau::tr_bdf2(), 0.05),
+            (DirkTableau::kvaerno3(), 0.05),
+            (DirkTableau::kvaerno4(), 0.1),
+            (DirkTableau::kvaerno5(), 0.1),
+        ] {
+            let p = tableau.order as f64;
+            let order = observed_order(|h| max_error(&fixed(tableau.clone(), h), &reference), h);
+            assert!((order - p).abs() < 0.3, "{p}: {order}");
+        }
+
+        for tableau in [
+            DirkTableau::sdirk4(),
+            DirkTableau::kvaerno3(),
+            DirkTableau::kvaerno4(),
+            DirkTableau::kvaerno5(),
+        ] {
+            let solution = relaxation_oscillation(Sdirk::new(tableau, van_der_pol));
+            let stats = solution.stats;
+            assert_eq!(stats.lu_factorizations, stats.steps + stats.rejected_steps);
+            assert!(solution.len() < 3000, "{}", solution.len());
+        }
+    }
+}
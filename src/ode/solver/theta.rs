@@ -0,0 +1,161 @@
+use ndarray::{array, Array1, ArrayView1, Zip};
+
+use crate::{ad::*, ode::*};
+
+/// The θ-method `x1 = x0 + h ((1 - θ) f(x0) + θ f(x1))`.
+///
+/// `θ = 0` is the explicit Euler, `θ = 1/2` the [Trapezoidal] rule and `θ = 1` the implicit Euler.
+/// It is A-stable for `θ >= 1/2` and of second order only for `θ = 1/2`.
+#[derive(Clone)]
+pub struct ThetaMethod<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    x0_owned: Array1<AD>,
+    f0: Array1<AD>,
+    flow: Flow,
+    h: AD,
+    θ: AD,
+}
+impl<Flow> ThetaMethod<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    pub fn new(h: f64, θ: f64, flow: Flow) -> Self {
+        assert!((0. ..=1.).contains(&θ), "θ has to lie in [0, 1]");
+        ThetaMethod {
+            x0_owned: array![],
+            f0: array![],
+            h: AD::AD0(h),
+            θ: AD::AD0(θ),
+            flow,
+        }
+    }
+}
+impl<Flow> Residual for ThetaMethod<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    #[inline]
+    fn eval(&self, x1: ArrayView1<AD>, update: &mut Array1<AD>) {
+        let (h, θ) = (self.h, self.θ);
+        (self.flow)(x1, update);
+        Zip::from(x1)
+            .and(&self.x0_owned)
+            .and(&self.f0)
+            .and(update)
+            .for_each(|&x1, &x0, &f0, f| *f = x1 - x0 - h * ((1. - θ) * f0 + θ * *f));
+    }
+}
+
+impl<Flow> Residual1Step for ThetaMethod<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    /// Evaluates `f(x0)` once per step.
+    #[inline]
+    fn update(&mut self, x0: Array1<AD>) {
+        let mut f0 = x0.clone();
+        (self.flow)(x0.view(), &mut f0);
+        self.f0 = f0;
+        self.x0_owned = x0;
+    }
+}
+impl<Flow> Implicit for ThetaMethod<Flow> where Flow: Fn(ArrayView1<AD>, &mut Array1<AD>) {}
+
+/// The trapezoidal rule or Crank-Nicolson scheme `x1 = x0 + h/2 (f(x0) + f(x1))`, of second order and A-stable.
+#[derive(Clone)]
+pub struct Trapezoidal<Flow>(ThetaMethod<Flow>)
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>);
+
+impl<Flow> Trapezoidal<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    pub fn new(h: f64, flow: Flow) -> Self {
+        Trapezoidal(ThetaMethod::new(h, 0.5, flow))
+    }
+}
+impl<Flow> Residual for Trapezoidal<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    #[inline]
+    fn eval(&self, x1: ArrayView1<AD>, update: &mut Array1<AD>) {
+        self.0.eval(x1, update);
+    }
+}
+
+impl<Flow> Residual1Step for Trapezoidal<Flow>
+where
+    Flow: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    #[inline]
+    fn update(&mut self, x0: Array1<AD>) {
+        self.0.update(x0);
+    }
+}
+impl<Flow> Implicit for Trapezoidal<Flow> where Flow: Fn(ArrayView1<AD>, &mut Array1<AD>) {}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{
+        ad::AD,
+        ode::{solver::*, *},
+    };
+
+    fn oscillator(x: ArrayView1<AD>, f: &mut Array1<AD>) {
+        f[0] = x[1];
+        f[1] = -x[0];
+    }
+
+    fn error<Scheme>(scheme: impl Fn(f64) -> Scheme, h: f64) -> f64
+    where
+        Scheme: Implicit + Residual1Step + std::marker::Sync,
+    {
+        let mut ode = Ode::implicit(scheme(h), array![1.0, 0.0]);
+        ode.set_step_size(h)
+            .set_t(1.0 + h / 2.)
+            .set_with_progress(false);
+        let solution = ode.run();
+        let (t, x) = solution.iter().last().unwrap();
+        (x[0] - t.cos()).abs()
+    }
+
+    #[test]
+    fn second_order_schemes() {
+        let ratios = [
+            error(|h| Trapezoidal::new(h, oscillator), 0.02)
+                / error(|h| Trapezoidal::new(h, oscillator), 0.01),
+            error(|h| ImplicitMidpoint::new(h, oscillator), 0.02)
+                / error(|h| ImplicitMidpoint::new(h, oscillator), 0.01),
+            error(|h| ThetaMethod::new(h, 0.5, oscillator), 0.02)
+                / error(|h| ThetaMethod::new(h, 0.5, oscillator), 0.01),
+        ];
+        for ratio in ratios {
+            assert!((ratio - 4.).abs() < 0.1, "{ratio}");
+        }
+        let ratio = error(|h| ThetaMethod::new(h, 0.75, oscillator), 0.02)
+            / error(|h| ThetaMethod::new(h, 0.75, oscillator), 0.01);
+        assert!((ratio - 2.).abs() < 0.1, "{ratio}");
+    }
+
+    #[test]
+    fn unconverged_newton_fails() {
+        // the implicit Euler step x1 - 1 - 0.6 x1² = 0 of x' = x² from 1 has no solution
+        let blow_up = |x: ArrayView1<AD>, f: &mut Array1<AD>| f[0] = x[0] * x[0];
+        let mut ode = Ode::implicit(ThetaMethod::new(0.6, 1., blow_up), array![1.0]);
+        ode.set_step_size(0.6).set_t(2.0).set_with_progress(false);
+        let solution = ode.run();
+        assert!(matches!(
+            solution.termination,
+            Termination::Failed { t, .. } if t == 0.
+        ));
+        assert_eq!(solution.len(), 1);
+        assert_eq!(solution.stats.steps, 0);
+        assert_eq!(solution.stats.newton_failures, 1);
+    }
+}
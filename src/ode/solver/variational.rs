@@ -0,0 +1,161 @@
+use ndarray::{array, concatenate, s, Array1, ArrayView1, Axis, Zip};
+
+use crate::{ad::*, ode::*};
+
+/// The approximation `L_d(q0, q1) ≈ ∫ L dt` over one step of a [VariationalIntegrator].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscreteLagrangian {
+    /// `h L((q0 + q1) / 2, (q1 - q0) / h)`, the implicit midpoint rule for quadratic kinetic energies.
+    Midpoint,
+    /// `h / 2 (L(q0, (q1 - q0) / h) + L(q1, (q1 - q0) / h))`, the Störmer-Verlet method for separable ones.
+    Trapezoidal,
+}
+
+/// Variational integrator from a discrete Lagrangian `L_d`, of second order with both [DiscreteLagrangian]s.
+///
+/// The states are positions and discrete momenta `x = (q, p)`, a step solves `p0 = -∂L_d/∂q0 (q0, q1)` for
+/// `q1` and sets `p1 = ∂L_d/∂q1 (q0, q1)` by [newton], driven by [Ode::implicit]. The method is symplectic and,
+/// by the discrete Noether theorem, keeps the momentum maps of the symmetries of `L` exactly, e.g. the
+/// angular momentum of a rotationally symmetric `L`.
+#[derive(Clone)]
+pub struct VariationalIntegrator<L>
+where
+    L: Fn(ArrayView1<AD>, ArrayView1<AD>) -> AD,
+{
+    x0_owned: Array1<AD>,
+    lagrangian: Lagrangian<L>,
+    discrete: DiscreteLagrangian,
+    h: AD,
+}
+
+impl<L> VariationalIntegrator<L>
+where
+    L: Fn(ArrayView1<AD>, ArrayView1<AD>) -> AD,
+{
+    pub fn new(h: f64, discrete: DiscreteLagrangian, lagrangian: Lagrangian<L>) -> Self {
+        VariationalIntegrator {
+            x0_owned: array![],
+            lagrangian,
+            discrete,
+            h: AD::AD0(h),
+        }
+    }
+
+    pub fn midpoint(h: f64, lagrangian: Lagrangian<L>) -> Self {
+        VariationalIntegrator::new(h, DiscreteLagrangian::Midpoint, lagrangian)
+    }
+
+    pub fn trapezoidal(h: f64, lagrangian: Lagrangian<L>) -> Self {
+        VariationalIntegrator::new(h, DiscreteLagrangian::Trapezoidal, lagrangian)
+    }
+
+    /// The state `(q0, p0)` with the momentum `p0 = ∂L/∂q' (q0, v0)` of the initial velocity.
+    pub fn initial(&self, q0: ArrayView1<f64>, v0: ArrayView1<f64>) -> Array1<f64> {
+        concatenate![Axis(0), q0, self.lagrangian.momentum(q0, v0)]
+    }
+
+    /// `L_d` of the positions `y = (q0, q1)`.
+    fn discrete_lagrangian(&self, y: ArrayView1<AD>) -> AD {
+        let h = self.h;
+        let (q0, q1) = y.split_at(Axis(0), self.lagrangian.dof());
+        let v: Array1<AD> = Zip::from(q1).and(q0).map_collect(|&q1, &q0| (q1 - q0) / h);
+        let l = |q: ArrayView1<AD>| self.lagrangian.lagrangian(q, v.view());
+        match self.discrete {
+            DiscreteLagrangian::Midpoint => {
+                let mid: Array1<AD> = Zip::from(q1)
+                    .and(q0)
+                    .map_collect(|&q1, &q0| 0.5 * (q0 + q1));
+                h * l(mid.view())
+            }
+            DiscreteLagrangian::Trapezoidal => 0.5 * h * (l(q0) + l(q1)),
+        }
+    }
+}
+
+impl<L> Residual for VariationalIntegrator<L>
+where
+    L: Fn(ArrayView1<AD>, ArrayView1<AD>) -> AD,
+{
+    #[inline]
+    fn eval(&self, x1: ArrayView1<AD>, update: &mut Array1<AD>) {
+        let n = self.lagrangian.dof();
+        let x0 = self.x0_owned.view();
+        let y = concatenate![Axis(0), x0.slice(s![..n]), x1.slice(s![..n])];
+        let g = gradient(|y: ArrayView1<AD>| self.discrete_lagrangian(y), y.view());
+        for i in 0..n {
+            update[i] = x0[n + i] + g[i];
+            update[n + i] = x1[n + i] - g[n + i];
+        }
+    }
+}
+
+impl<L> Residual1Step for VariationalIntegrator<L>
+where
+    L: Fn(ArrayView1<AD>, ArrayView1<AD>) -> AD,
+{
+    #[inline]
+    fn update(&mut self, x0: Array1<AD>) {
+        self.x0_owned = x0;
+    }
+}
+
+impl<L> Implicit for VariationalIntegrator<L> where L: Fn(ArrayView1<AD>, ArrayView1<AD>) -> AD {}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, s, ArrayView1};
+
+    use crate::{
+        ad::*,
+        ensemble::max_drift,
+        ode::{solver::*, *},
+        test_support::*,
+    };
+
+    fn kepler(q: ArrayView1<AD>, v: ArrayView1<AD>) -> AD {
+        0.5 * (v[0] * v[0] + v[1] * v[1]) + 1. / (q[0] * q[0] + q[1] * q[1]).sqrt()
+    }
+
+    fn run(discrete: DiscreteLagrangian, h: f64, t: f64) -> Solution {
+        let scheme = VariationalIntegrator::new(h, discrete, Lagrangian::new(2, kepler));
+        let x0 = scheme.initial(array![1., 0.].view(), array![0., 1.2].view());
+        let mut ode = Ode::implicit(scheme, x0);
+        ode.set_step_size(h)
+            .set_t(ending_at(t, h))
+            .set_with_progress(false);
+        ode.run()
+    }
+
+    #[test]
+    fn momentum_maps_and_order() {
+        let lagrangian = Lagrangian::new(2, kepler);
+        let h = 1e-3;
+        let mut ode = Ode::explicit(
+            RungeKutta::new(h, ButcherTableau::rk4(), lagrangian.flow_f64()),
+            lagrangian.state(array![1., 0.].view(), array![0., 1.2].view()),
+        );
+        ode.set_step_size(h)
+            .set_t(ending_at(1.0, h))
+            .set_with_progress(false);
+        let reference = state_at(&ode.run(), 1.0);
+
+        // the velocities are the momenta here
+        let angular_momentum = |x: ArrayView1<f64>| x[0] * x[3] - x[1] * x[2];
+        let energy = |x: ArrayView1<f64>| lagrangian.energy(x);
+        for discrete in [
+            DiscreteLagrangian::Midpoint,
+            DiscreteLagrangian::Trapezoidal,
+        ] {
+            let solution = run(discrete, 0.05, 20.);
+            assert!(max_drift(&solution, angular_momentum) < 1e-12);
+            assert!(max_drift(&solution, energy) < 1e-2);
+
+            let error = |h| {
+                let x = state_at(&run(discrete, h, 1.0), 1.0);
+                max_error(x.slice(s![..2]), reference.slice(s![..2]))
+            };
+            let order = observed_order(error, 0.02);
+            assert!((order - 2.).abs() < 0.2, "{discrete:?}: {order}");
+        }
+    }
+}
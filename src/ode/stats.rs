@@ -0,0 +1,65 @@
+use std::ops::AddAssign;
+
+/// Counters of the work done by a solver during one run.
+///
+/// The right hand side evaluations do not include the evaluations on [crate::ad::AD] numbers
+/// needed for a Jacobian, those are counted once per Jacobian.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SolverStats {
+    /// Accepted steps.
+    pub steps: usize,
+    /// Steps which were repeated with a smaller step size.
+    pub rejected_steps: usize,
+    pub rhs_evaluations: usize,
+    pub jacobian_evaluations: usize,
+    pub lu_factorizations: usize,
+    pub newton_iterations: usize,
+    /// Newton solves which stopped without reaching the tolerance.
+    pub newton_failures: usize,
+}
+
+impl SolverStats {
+    /// Key value pairs for the metadata of a stored result, see [crate::plot::Dataframe::push_metadata].
+    pub fn metadata(&self) -> Vec<(String, String)> {
+        [
+            ("steps", self.steps),
+            ("rejected_steps", self.rejected_steps),
+            ("rhs_evaluations", self.rhs_evaluations),
+            ("jacobian_evaluations", self.jacobian_evaluations),
+            ("lu_factorizations", self.lu_factorizations),
+            ("newton_iterations", self.newton_iterations),
+            ("newton_failures", self.newton_failures),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+    }
+}
+
+impl AddAssign for SolverStats {
+    fn add_assign(&mut self, other: Self) {
+        self.steps += other.steps;
+        self.rejected_steps += other.rejected_steps;
+        self.rhs_evaluations += other.rhs_evaluations;
+        self.jacobian_evaluations += other.jacobian_evaluations;
+        self.lu_factorizations += other.lu_factorizations;
+        self.newton_iterations += other.newton_iterations;
+        self.newton_failures += other.newton_failures;
+    }
+}
+
+impl std::fmt::Display for SolverStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "steps: {}, rejected: {}, rhs evaluations: {}, jacobians: {}, LU factorizations: {}, newton iterations: {}, newton failures: {}",
+            self.steps,
+            self.rejected_steps,
+            self.rhs_evaluations,
+            self.jacobian_evaluations,
+            self.lu_factorizations,
+            self.newton_iterations,
+            self.newton_failures
+        )
+    }
+}
@@ -1,9 +1,22 @@
-use ndarray::{Array1, Array2, ArrayView1};
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
 
-use crate::ad::*;
+use crate::{
+    ad::*,
+    ode::{CancellationToken, Limits, Solution, SolverStats},
+    progress::*,
+};
 
 pub trait Explicit {
-    fn next(&self, x: ArrayView1<f64>) -> Array1<f64>;
+    /// The step from `x`, which fails e.g. on a singular matrix of an iteration.
+    fn next(&self, x: ArrayView1<f64>) -> ndarray_linalg::error::Result<Array1<f64>>;
+    /// Evaluations of the right hand side in each call of [Explicit::next].
+    fn evaluations(&self) -> usize {
+        1
+    }
+    /// Iterations for the stages of the last call of [Explicit::next] which stopped before they converged.
+    fn newton_failures(&self) -> usize {
+        0
+    }
 }
 
 pub trait Implicit: Residual {}
@@ -15,16 +28,32 @@ pub trait ODE<Scheme> {
     /// Set the total runtime of the simulation.
     #[allow(non_snake_case)]
     fn set_t(&mut self, T: f64) -> &mut Self;
-    /// To deactivate tqdm during runtime
-    fn set_with_progress(&mut self, with_progress: bool) -> &mut Self;
+    /// Switches between a [TqdmProgress] bar and [NoProgress].
+    fn set_with_progress(&mut self, with_progress: bool) -> &mut Self {
+        if with_progress {
+            self.set_progress(TqdmProgress::default())
+        } else {
+            self.set_progress(NoProgress)
+        }
+    }
+    /// Reports the progress of the run in simulated time.
+    fn set_progress<P: Progress + 'static>(&mut self, progress: P) -> &mut Self;
+    /// The conditions under which the run stops early with a partial [Solution].
+    fn limits_mut(&mut self) -> &mut Limits;
+    /// Stops the run between two steps once the token is cancelled.
+    fn set_cancellation(&mut self, token: CancellationToken) -> &mut Self {
+        self.limits_mut().set_cancellation(token);
+        self
+    }
+    /// Stops the run between two steps once the wall-clock time `limit` has passed.
+    fn set_time_limit(&mut self, limit: std::time::Duration) -> &mut Self {
+        self.limits_mut().set_time_limit(limit);
+        self
+    }
     /// Consumes the defined ODE and runs the simulation.
     ///
-    /// It returns a vector of all time steps and an array which corresponds to the state at each timestep.
-    ///
-    /// columns: state
-    ///
-    /// rows: timestep
-    fn run(self) -> (Vec<f64>, Array2<f64>);
+    /// The [Solution] contains all time steps, the state at each of them and the work done by the solver.
+    fn run(self) -> Solution;
 }
 
 pub trait OneStep {
@@ -52,3 +81,137 @@ pub trait Residual2Step {
     fn new(x0: Array1<AD>, x1: Array1<AD>, h: f64) -> Self;
     fn update(&mut self, x0: Array1<AD>, x1: Array1<AD>);
 }
+
+/// The result of one step of a [SecondOrder] scheme.
+pub struct SecondOrderStep {
+    pub q: Array1<f64>,
+    pub v: Array1<f64>,
+    /// Estimated local error of `(q, v)` stacked into one vector, if the scheme has an embedded method.
+    pub error: Option<Array1<f64>>,
+}
+
+/// Schemes for second order problems `q'' = f(t, q, q')` which keep positions and velocities apart.
+pub trait SecondOrder {
+    fn order(&self) -> usize;
+    /// Order of the embedded solution the error estimate compares with, one order lower by default.
+    /// The step size control uses the lower of both orders.
+    fn embedded_order(&self) -> usize {
+        self.order().saturating_sub(1)
+    }
+    /// Advances positions `q` and velocities `v` at time `t` by `h` and adds the work to `stats`.
+    fn next(
+        &mut self,
+        t: f64,
+        q: ArrayView1<f64>,
+        v: ArrayView1<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> ndarray_linalg::error::Result<SecondOrderStep>;
+    /// What the scheme carries from one step to the next besides `q` and `v`, kept in checkpoints.
+    fn carried_state(&self) -> Option<Array1<f64>> {
+        None
+    }
+    /// Continues with what [SecondOrder::carried_state] returned when a run is resumed.
+    fn restore_state(&mut self, _carried: Array1<f64>) {}
+}
+
+/// The result of one step of an [Embedded] scheme.
+pub struct EmbeddedStep {
+    pub x: Array1<f64>,
+    /// Estimated local error of `x`, the difference to the embedded solution of lower order.
+    pub error: Array1<f64>,
+}
+
+/// One step schemes for `x' = f(t, x)` with an embedded error estimate, which take the step size of each step.
+pub trait Embedded {
+    /// Order of the propagated solution.
+    fn order(&self) -> usize;
+    /// Order of the embedded solution the error estimate compares with, one order lower by default.
+    /// The step size control uses the lower of both orders.
+    fn embedded_order(&self) -> usize {
+        self.order().saturating_sub(1)
+    }
+    /// Advances `x` at time `t` by `h` and adds the work to `stats`.
+    fn next(
+        &mut self,
+        t: f64,
+        x: ArrayView1<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> ndarray_linalg::error::Result<EmbeddedStep>;
+}
+
+/// One step schemes for matrix valued states `Y' = F(t, Y)`, e.g. on a matrix Lie group.
+pub trait MatrixScheme {
+    fn order(&self) -> usize;
+    /// Advances `y` at time `t` by `h` and adds the work to `stats`.
+    fn next(
+        &mut self,
+        t: f64,
+        y: ArrayView2<f64>,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> ndarray_linalg::error::Result<Array2<f64>>;
+}
+
+/// The accepted steps a [Multistep] method looks back on, oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct StepHistory {
+    pub time: Vec<f64>,
+    pub states: Vec<Array1<f64>>,
+    /// The right hand side `f(x)` at the states.
+    pub slopes: Vec<Array1<f64>>,
+}
+
+impl StepHistory {
+    /// Appends an accepted step and forgets all but the last `keep` steps.
+    pub fn push(&mut self, t: f64, x: Array1<f64>, slope: Array1<f64>, keep: usize) {
+        self.time.push(t);
+        self.states.push(x);
+        self.slopes.push(slope);
+        let surplus = self.time.len().saturating_sub(keep);
+        self.time.drain(..surplus);
+        self.states.drain(..surplus);
+        self.slopes.drain(..surplus);
+    }
+
+    pub fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+}
+
+/// The result of one step of a [Multistep] method.
+pub struct MultistepStep {
+    pub x: Array1<f64>,
+    /// `f(x)` for the history of the following steps.
+    pub slope: Array1<f64>,
+    /// Estimated local error of `x`, if the method has one.
+    pub error: Option<Array1<f64>>,
+}
+
+/// Linear multistep methods for `x' = f(x)`, which advance the newest state of a [StepHistory] of past steps.
+///
+/// The steps of the history need not be equidistant.
+pub trait Multistep {
+    fn order(&self) -> usize;
+    /// Order of the solution whose local error the error estimate is, one order lower by default.
+    /// The step size control uses the lower of both orders.
+    fn embedded_order(&self) -> usize {
+        self.order().saturating_sub(1)
+    }
+    /// Number of past steps `k` the method needs, the missing ones at the start are taken by a one step method.
+    fn steps(&self) -> usize;
+    /// The right hand side `f(x)`.
+    fn slope(&self, x: ArrayView1<f64>) -> Array1<f64>;
+    /// Advances the newest state of `history` by `h` and adds the work to `stats`.
+    fn next(
+        &mut self,
+        history: &StepHistory,
+        h: f64,
+        stats: &mut SolverStats,
+    ) -> ndarray_linalg::error::Result<MultistepStep>;
+}
@@ -1,8 +1,22 @@
-use crate::{ad::*, ode::*};
+use crate::{
+    ad::*,
+    ode::{solver::ButcherTableau, *},
+    progress::*,
+};
 use ndarray::*;
-use tqdm::tqdm;
+use std::path::Path;
+
+/// The right hand side of the problem for the start-up step.
+type Slope = Box<dyn Fn(ArrayView1<f64>) -> Array1<f64>>;
+
 /// This ode solver uses a two step scheme, through which one may get better solutions
 /// but also needs to define different residual functions.
+///
+/// The second starting value `x1` at `t = h` has to be as accurate as the scheme, so either pass it to
+/// [OdeTwoStep::new], e.g. from a step of a Runge-Kutta method of at least the same order, or let
+/// [OdeTwoStep::with_startup] take that step with the flow of the problem.
+///
+/// A step whose Newton iteration does not converge ends the run with [Termination::Failed].
 #[allow(non_snake_case)]
 pub struct OdeTwoStep<Scheme>
 where
@@ -10,10 +24,16 @@ where
 {
     scheme: Scheme,
     x0: Array1<AD>,
-    x1: Array1<AD>,
+    x1: Option<Array1<AD>>,
+    flow: Option<Slope>,
+    startup: ButcherTableau,
     h: f64,
     T: f64,
     ɛ: f64,
+    progress: Box<dyn Progress>,
+    limits: Limits,
+    checkpoints: Option<Checkpoints>,
+    resume: Option<Checkpoint>,
 }
 impl<Scheme> OdeTwoStep<Scheme>
 where
@@ -23,12 +43,52 @@ where
         OdeTwoStep {
             scheme,
             x0,
-            x1,
+            x1: Some(x1),
+            flow: None,
+            startup: ButcherTableau::dormand_prince(),
             h: 0.1,
             T: 1.0,
             ɛ: 10e-9,
+            progress: Box::<TqdmProgress>::default(),
+            limits: Limits::default(),
+            checkpoints: None,
+            resume: None,
         }
     }
+
+    /// Starts from `x0` alone, `x1` is one step of size `h` of the Dormand-Prince method of order five
+    /// for `x' = flow(x)`, which is taken when the run starts.
+    pub fn with_startup<Flow>(scheme: Scheme, x0: Array1<AD>, flow: Flow) -> Self
+    where
+        Flow: Fn(ArrayView1<AD>, &mut Array1<AD>) + 'static,
+    {
+        let mut ode = OdeTwoStep::new(scheme, x0, array![]);
+        ode.x1 = None;
+        ode.flow = Some(Box::new(move |x| eval_flow(&flow, x)));
+        ode
+    }
+
+    /// The explicit Runge-Kutta method of [OdeTwoStep::with_startup].
+    pub fn set_startup(&mut self, tableau: ButcherTableau) -> &mut Self {
+        self.startup = tableau;
+        self
+    }
+
+    /// The given second value, or the step of the start-up method from `x0`.
+    fn second_value(&self, stats: &mut SolverStats) -> Array1<f64> {
+        if let Some(x1) = &self.x1 {
+            return x1.to_f64();
+        }
+        let flow = self
+            .flow
+            .as_ref()
+            .expect("Without a second value the flow is needed for the start-up");
+        let x0 = self.x0.to_f64();
+        let k = self.startup.stage_slopes(|_, x| flow(x), x0.view(), self.h);
+        stats.steps += 1;
+        stats.rhs_evaluations += self.startup.stages();
+        self.startup.solution(x0.view(), &k, self.h)
+    }
 }
 
 impl<Scheme> ODE<Scheme> for OdeTwoStep<Scheme>
@@ -46,40 +106,182 @@ where
         self
     }
 
-    fn run(mut self) -> (Vec<f64>, Array2<f64>) {
+    fn run(mut self) -> Solution {
         let n: f64 = self.T / self.h;
-        let n = n.floor() as usize;
-        let mut x0 = self.x0.clone().to_f64();
-        let mut x1 = self.x1.clone().to_f64();
-
-        let mut result: Array2<f64> = Array::zeros((0, x0.len()));
-        result.push_row(self.x0.to_f64().view()).unwrap();
-        result.push_row(self.x1.to_f64().view()).unwrap();
-        let mut time = vec![0.0; n];
-        time[1] = 1. * self.h;
+        let mut result: Array2<f64> = Array::zeros((0, self.x0.len()));
+        let mut time = vec![];
+        let (first, mut x0, mut x1, mut stats) = match self.resume.take() {
+            Some(checkpoint) => {
+                let [x0, x1] = &checkpoint.history[..] else {
+                    panic!("A checkpoint of a two step method holds two states")
+                };
+                (checkpoint.step, x0.clone(), x1.clone(), checkpoint.stats)
+            }
+            None => {
+                result.push_row(self.x0.to_f64().view()).unwrap();
+                time.push(0.0);
+                let mut stats = SolverStats::default();
+                let x1 = self.second_value(&mut stats);
+                (1, self.x0.to_f64(), x1, stats)
+            }
+        };
+        result.push_row(x1.view()).unwrap();
+        time.push(first as f64 * self.h);
+        let n = (n.floor() as usize).max(first + 1);
 
         let l = x0.len();
         #[allow(non_snake_case)]
         let mut J = Array2::zeros((l, l));
         let mut slope_buffer = x0.to_ad();
+        let mut termination = Termination::Completed;
 
-        for t in 2..n {
+        self.progress.start(time[0], self.T);
+        self.limits.start();
+        for t in first + 1..n {
+            if let Some(stop) = self.limits.check(time[time.len() - 1]) {
+                termination = stop;
+                break;
+            }
             self.scheme.update(x0.to_ad(), x1.to_ad());
-            match newton(self.ɛ, &self.scheme, x1.to_ad(), &mut J, &mut slope_buffer) {
+            let failures = stats.newton_failures;
+            match newton_with_stats(
+                self.ɛ,
+                &self.scheme,
+                x1.to_ad(),
+                &mut J,
+                &mut slope_buffer,
+                &mut stats,
+            ) {
+                Ok(_) if stats.newton_failures > failures => {
+                    termination = Termination::Failed {
+                        t: (t - 1) as f64 * self.h,
+                        reason: "The Newton iteration did not converge".into(),
+                    };
+                    break;
+                }
                 Ok(x2) => {
+                    stats.steps += 1;
                     let x2 = x2.to_f64();
                     result.push_row(x2.view()).unwrap();
                     x0 = x1;
                     x1 = x2;
-                    time[t] = t as f64 * self.h;
+                    time.push(t as f64 * self.h);
+                    self.progress.update(t as f64 * self.h);
+                }
+                Err(e) => {
+                    stats.newton_failures += 1;
+                    termination = Termination::Failed {
+                        t: (t - 1) as f64 * self.h,
+                        reason: e.to_string(),
+                    };
+                    break;
                 }
-                Err(e) => eprintln!("Did not converge, how to handle it?\n{e}"),
             }
+            if let Some(failed) =
+                Checkpoints::write_due(self.checkpoints.as_ref(), t, || Checkpoint {
+                    step: t,
+                    t: t as f64 * self.h,
+                    h: self.h,
+                    history: vec![x0.clone(), x1.clone()],
+                    controller: None,
+                    stats,
+                })
+            {
+                termination = failed;
+                break;
+            }
+        }
+        self.progress.finish();
+        let mut solution = Solution::new(time, result, stats);
+        solution.termination = termination;
+        solution
+    }
+
+    fn set_progress<P: Progress + 'static>(&mut self, progress: P) -> &mut Self {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    fn limits_mut(&mut self) -> &mut Limits {
+        &mut self.limits
+    }
+}
+
+impl<Scheme> Restart for OdeTwoStep<Scheme>
+where
+    Scheme: Implicit + std::marker::Sync + Residual2Step,
+{
+    fn set_checkpoints(&mut self, path: impl AsRef<Path>, every: usize) -> &mut Self {
+        self.checkpoints = Some(Checkpoints::new(path, every));
+        self
+    }
+
+    fn resume(&mut self, checkpoint: Checkpoint) -> &mut Self {
+        self.h = checkpoint.h;
+        self.resume = Some(checkpoint);
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1, Zip};
+
+    use crate::{
+        ad::*,
+        ode::{two_step::OdeTwoStep, *},
+        test_support::observed_order,
+    };
+
+    fn oscillator(x: ArrayView1<AD>, f: &mut Array1<AD>) {
+        f[0] = x[1];
+        f[1] = -x[0];
+    }
+
+    /// The explicit midpoint rule `x2 = x0 + 2 h f(x1)` of second order.
+    struct Leapfrog {
+        x0: Array1<AD>,
+        x1: Array1<AD>,
+        h: f64,
+    }
+
+    impl Residual for Leapfrog {
+        fn eval(&self, x2: ArrayView1<AD>, update: &mut Array1<AD>) {
+            oscillator(self.x1.view(), update);
+            let h = self.h;
+            Zip::from(x2)
+                .and(&self.x0)
+                .and(update)
+                .for_each(|&x2, &x0, f| *f = x2 - x0 - 2. * h * *f);
+        }
+    }
+
+    impl Residual2Step for Leapfrog {
+        fn new(x0: Array1<AD>, x1: Array1<AD>, h: f64) -> Self {
+            Leapfrog { x0, x1, h }
+        }
+
+        fn update(&mut self, x0: Array1<AD>, x1: Array1<AD>) {
+            (self.x0, self.x1) = (x0, x1);
         }
-        (time, result)
     }
 
-    fn set_with_progress(&mut self, _with_tqdm: bool) -> &mut Self {
-        todo!("Implement setting the progress, currently always show it.")
+    impl Implicit for Leapfrog {}
+
+    fn error(h: f64) -> f64 {
+        let x0 = array![1.0, 0.0].to_ad();
+        let scheme = Leapfrog::new(x0.clone(), x0.clone(), h);
+        let mut ode = OdeTwoStep::with_startup(scheme, x0, oscillator);
+        ode.set_step_size(h).set_t(1.0).set_with_progress(false);
+        let solution = ode.run();
+        assert_eq!(solution.termination, Termination::Completed);
+        let (t, x) = solution.iter().last().unwrap();
+        (x[0] - t.cos()).abs().max((x[1] + t.sin()).abs())
+    }
+
+    #[test]
+    fn starts_from_the_initial_value_alone() {
+        let order = observed_order(error, 0.02);
+        assert!((order - 2.).abs() < 0.1, "{order}");
     }
 }
@@ -4,14 +4,22 @@ use super::Series;
 
 pub struct Dataframe<'a> {
     data: Vec<Series<'a>>,
+    metadata: Vec<(String, String)>,
 }
 impl<'a> Dataframe<'a> {
     pub fn new() -> Self {
-        Dataframe { data: vec![] }
+        Dataframe {
+            data: vec![],
+            metadata: vec![],
+        }
     }
     pub fn push(&mut self, series: Series<'a>) {
         self.data.push(series);
     }
+    /// Adds a key value pair to the metadata of the parquet file, e.g. from [crate::ode::SolverStats::metadata].
+    pub fn push_metadata(&mut self, key: &str, value: &str) {
+        self.metadata.push((key.to_string(), value.to_string()));
+    }
     pub fn store(&self, file: &mut File) -> Result<(), polars::prelude::PolarsError> {
         use polars::prelude::*;
 
@@ -21,7 +29,52 @@ impl<'a> Dataframe<'a> {
             .map(|x| Series::new(x.name, x.values.clone()))
             .collect();
         let mut df = DataFrame::new(columns)?;
-        ParquetWriter::new(file).finish(&mut df)?;
+        if self.metadata.is_empty() {
+            ParquetWriter::new(file).finish(&mut df)?;
+        } else {
+            self.store_with_metadata(&df, file)?;
+        }
+        Ok(())
+    }
+    /// The polars writer has no access to the key value metadata, so the file is written with arrow directly.
+    fn store_with_metadata(
+        &self,
+        df: &polars::prelude::DataFrame,
+        file: &mut File,
+    ) -> Result<(), polars::prelude::PolarsError> {
+        use polars::export::arrow::io::parquet::write::{
+            transverse, CompressionOptions, Encoding, FileWriter, KeyValue, RowGroupIterator,
+            Version, WriteOptions,
+        };
+
+        let schema = df.schema().to_arrow();
+        let options = WriteOptions {
+            write_statistics: true,
+            compression: CompressionOptions::Snappy,
+            version: Version::V2,
+            data_pagesize_limit: None,
+        };
+        let encodings = schema
+            .fields
+            .iter()
+            .map(|field| transverse(&field.data_type, |_| Encoding::Plain))
+            .collect();
+        let row_groups =
+            RowGroupIterator::try_new(df.iter_chunks().map(Ok), &schema, options, encodings)?;
+
+        let mut writer = FileWriter::try_new(file, schema, options)?;
+        for group in row_groups {
+            writer.write(group?)?;
+        }
+        let metadata = self
+            .metadata
+            .iter()
+            .map(|(key, value)| KeyValue {
+                key: key.clone(),
+                value: Some(value.clone()),
+            })
+            .collect();
+        writer.end(Some(metadata))?;
         Ok(())
     }
 }
@@ -1,6 +1,11 @@
 pub use crate::{
     ad::*,
+    bvp::*,
+    dde::*,
+    ensemble::*,
     ode::{solver::*, *},
+    progress::*,
+    sde::*,
 };
 
 pub use crate::plot;
@@ -0,0 +1,212 @@
+//! Progress reporting of long runs, measured in simulated time instead of steps.
+//!
+//! Every solver accepts any [Progress] via `set_progress`, `set_with_progress(true)` selects a [TqdmProgress].
+//! Bars of runs in parallel threads are drawn below each other, give them a description to tell them apart.
+
+/// Receives the simulated time of a run.
+pub trait Progress: Send + Sync {
+    /// Called before the first step with the simulated time span.
+    fn start(&mut self, _t0: f64, _t_end: f64) {}
+    /// Called after every accepted step with the reached time.
+    fn update(&mut self, t: f64);
+    /// Called after the last step.
+    fn finish(&mut self) {}
+}
+
+/// Reports nothing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn update(&mut self, _t: f64) {}
+}
+
+/// Fraction of the time span `[t0, t_end]` which is done at `t`.
+fn fraction(t0: f64, t_end: f64, t: f64) -> f64 {
+    if t_end > t0 {
+        ((t - t0) / (t_end - t0)).clamp(0., 1.)
+    } else {
+        1.
+    }
+}
+
+/// A tqdm bar with `resolution` ticks over the simulated time span.
+pub struct TqdmProgress {
+    description: Option<String>,
+    resolution: usize,
+    span: (f64, f64),
+    ticks: usize,
+    bar: Option<tqdm::Tqdm<()>>,
+}
+
+impl TqdmProgress {
+    pub fn new() -> Self {
+        TqdmProgress {
+            description: None,
+            resolution: 1000,
+            span: (0., 1.),
+            ticks: 0,
+            bar: None,
+        }
+    }
+
+    /// Name of the bar, e.g. the scheme, to distinguish concurrent runs.
+    pub fn set_description(&mut self, description: &str) -> &mut Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub fn set_resolution(&mut self, resolution: usize) -> &mut Self {
+        self.resolution = resolution.max(1);
+        self
+    }
+}
+
+impl Default for TqdmProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Progress for TqdmProgress {
+    fn start(&mut self, t0: f64, t_end: f64) {
+        self.span = (t0, t_end);
+        self.ticks = 0;
+        self.bar = Some(
+            tqdm::pbar(Some(self.resolution))
+                .desc(self.description.as_deref())
+                .width(Some(100)),
+        );
+    }
+
+    fn update(&mut self, t: f64) {
+        let ticks = (fraction(self.span.0, self.span.1, t) * self.resolution as f64) as usize;
+        if let Some(bar) = self.bar.as_mut() {
+            if ticks > self.ticks {
+                bar.update(ticks - self.ticks).ok();
+                self.ticks = ticks;
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(mut bar) = self.bar.take() {
+            bar.close().ok();
+        }
+    }
+}
+
+/// Calls `callback(t, fraction)` after every step, where `fraction` is the part of the time span which is done.
+pub struct CallbackProgress<F>
+where
+    F: FnMut(f64, f64) + Send + Sync,
+{
+    callback: F,
+    span: (f64, f64),
+}
+
+impl<F> CallbackProgress<F>
+where
+    F: FnMut(f64, f64) + Send + Sync,
+{
+    pub fn new(callback: F) -> Self {
+        CallbackProgress {
+            callback,
+            span: (0., 1.),
+        }
+    }
+}
+
+impl<F> Progress for CallbackProgress<F>
+where
+    F: FnMut(f64, f64) + Send + Sync,
+{
+    fn start(&mut self, t0: f64, t_end: f64) {
+        self.span = (t0, t_end);
+    }
+
+    fn update(&mut self, t: f64) {
+        (self.callback)(t, fraction(self.span.0, self.span.1, t));
+    }
+}
+
+/// Emits a `tracing` event at info level whenever another `step` of the time span is done.
+#[cfg(feature = "tracing")]
+pub struct TracingProgress {
+    name: String,
+    step: f64,
+    span: (f64, f64),
+    next: f64,
+}
+
+#[cfg(feature = "tracing")]
+impl TracingProgress {
+    /// `step` is the fraction of the time span between two events, e.g. `0.1` for every ten percent.
+    pub fn new(name: &str, step: f64) -> Self {
+        TracingProgress {
+            name: name.to_string(),
+            step: step.max(f64::EPSILON),
+            span: (0., 1.),
+            next: 0.,
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Progress for TracingProgress {
+    fn start(&mut self, t0: f64, t_end: f64) {
+        self.span = (t0, t_end);
+        self.next = self.step;
+        tracing::info!(run = %self.name, t0, t_end, "started");
+    }
+
+    fn update(&mut self, t: f64) {
+        let done = fraction(self.span.0, self.span.1, t);
+        if done >= self.next {
+            tracing::info!(run = %self.name, t, done, "progress");
+            while self.next <= done {
+                self.next += self.step;
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        tracing::info!(run = %self.name, "finished");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{
+        ode::{solver::*, *},
+        progress::*,
+    };
+
+    fn decay(x: ArrayView1<f64>) -> Array1<f64> {
+        -&x
+    }
+
+    #[test]
+    fn callback_receives_simulated_time() {
+        let reported = Arc::new(Mutex::new(vec![]));
+        let sink = reported.clone();
+        let mut ode = Ode::explicit(ExplicitEuler::new(0.25, decay), array![1.0]);
+        ode.set_step_size(0.25)
+            .set_t(2.0)
+            .set_progress(CallbackProgress::new(move |t, done| {
+                sink.lock().unwrap().push((t, done))
+            }));
+        let solution = ode.run();
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), solution.len() - 1);
+        for (&(t, done), &time) in reported.iter().zip(&solution.time[1..]) {
+            assert_eq!(t, time);
+            assert_eq!(done, t / 2.0);
+        }
+    }
+}
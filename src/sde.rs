@@ -0,0 +1,29 @@
+//! Solvers for Itô stochastic differential equations `dX = f(X) dt + g(X) dW`.
+//!
+//! The drift is given like the flow of [crate::ode::solver::ExplicitEuler], the diffusion as a [Diffusion]
+//! which describes the noise structure. Wiener increments are taken from a [BrownianPath],
+//! so that several schemes or step sizes can be compared on the same realisation.
+use ndarray::Array1;
+
+mod traits;
+pub use traits::*;
+mod brownian;
+pub use brownian::*;
+mod noise;
+pub use noise::*;
+mod ensemble;
+pub use ensemble::*;
+mod solver;
+pub use solver::*;
+mod one_step;
+use one_step::*;
+
+pub struct Sde;
+impl Sde {
+    pub fn explicit<Scheme>(scheme: Scheme, initial: Array1<f64>) -> SdeEx<Scheme>
+    where
+        Scheme: Stochastic + std::marker::Sync,
+    {
+        SdeEx::new(scheme, initial)
+    }
+}
@@ -0,0 +1,205 @@
+use ndarray::Array1;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, StandardNormal};
+
+/// Wiener increments over one step `[t, t + h]`.
+///
+/// `dw` is `W(t + h) - W(t)` and `dz` the double integral `∫ (W(s) - W(t)) ds` over the step,
+/// which strong order 1.5 schemes need in addition to `dw`.
+#[derive(Debug, Clone)]
+pub struct Increment {
+    pub h: f64,
+    pub dw: Array1<f64>,
+    pub dz: Array1<f64>,
+}
+
+/// A multidimensional Wiener path which is sampled lazily and can be refined at arbitrary times.
+///
+/// Next to `W(t)` the path keeps the running integral `I(t) = ∫_0^t W(s) ds`.
+/// Points beyond the last known time are sampled forward, points in between two known ones are drawn
+/// from the Brownian bridge of the pair `(W, I)`. Thus a refined path stays consistent with every increment
+/// handed out before, which allows to compare step sizes on one realisation or to reject and retry steps.
+#[derive(Debug, Clone)]
+pub struct BrownianPath {
+    rng: ChaCha8Rng,
+    t: Vec<f64>,
+    w: Vec<Array1<f64>>,
+    i: Vec<Array1<f64>>,
+}
+
+impl BrownianPath {
+    /// Starts a path of `dim` independent Wiener processes at `t = 0` with `W(0) = 0`.
+    pub fn new(dim: usize, seed: u64) -> Self {
+        BrownianPath {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            t: vec![0.0],
+            w: vec![Array1::zeros(dim)],
+            i: vec![Array1::zeros(dim)],
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.w[0].len()
+    }
+
+    /// All times at which the path has been sampled so far.
+    pub fn times(&self) -> &[f64] {
+        &self.t
+    }
+
+    /// `W(t)`, sampling the path at `t` if necessary.
+    pub fn value(&mut self, t: f64) -> Array1<f64> {
+        let k = self.refine(t);
+        self.w[k].clone()
+    }
+
+    /// Increments over `[t0, t1]`, sampling the path at both ends if necessary.
+    pub fn increment(&mut self, t0: f64, t1: f64) -> Increment {
+        assert!(t0 <= t1, "Increments are only defined forward in time");
+        let a = self.refine(t0);
+        let b = self.refine(t1);
+        let h = self.t[b] - self.t[a];
+        let dw = &self.w[b] - &self.w[a];
+        let dz = &self.i[b] - &self.i[a] - h * &self.w[a];
+        Increment { h, dw, dz }
+    }
+
+    /// Makes sure the path is known at `t` and returns the index of that node.
+    pub fn refine(&mut self, t: f64) -> usize {
+        assert!(t >= self.t[0], "The path starts at t = {}", self.t[0]);
+        let k = self.t.partition_point(|&s| s < t);
+        if k < self.t.len() && same_time(self.t[k], t) {
+            return k;
+        }
+        if k > 0 && same_time(self.t[k - 1], t) {
+            return k - 1;
+        }
+        if k == self.t.len() {
+            self.extend(t);
+        } else {
+            self.bridge(k, t);
+        }
+        k
+    }
+
+    /// Samples a new node after the last known one.
+    fn extend(&mut self, t: f64) {
+        let last = self.t.len() - 1;
+        let τ = t - self.t[last];
+        let mut w = self.w[last].clone();
+        let mut i = &self.i[last] + τ * &self.w[last];
+        for (w, i) in w.iter_mut().zip(i.iter_mut()) {
+            let (ξ1, ξ2) = self.normal_pair();
+            // (ΔW, ΔZ) ~ N(0, [[τ, τ²/2], [τ²/2, τ³/3]])
+            *w += τ.sqrt() * ξ1;
+            *i += 0.5 * τ.powf(1.5) * (ξ1 + ξ2 / 3f64.sqrt());
+        }
+        self.t.push(t);
+        self.w.push(w);
+        self.i.push(i);
+    }
+
+    /// Inserts a node at `t` between the nodes `k - 1` and `k`.
+    fn bridge(&mut self, k: usize, t: f64) {
+        let (ta, tb) = (self.t[k - 1], self.t[k]);
+        let (τ1, τ2) = (t - ta, tb - t);
+        let τ = tb - ta;
+        // The whole interval is split into (w1, a1) on [ta, t] and (w2, a2) on [t, tb] with
+        //   ΔW = w1 + w2,   ΔZ = a1 + a2 + τ2 w1.
+        // Conditioning (w1, a1) on (ΔW, ΔZ) gives a two dimensional Gaussian.
+        let s = [[τ, τ * τ / 2.], [τ * τ / 2., τ * τ * τ / 3.]];
+        let det = s[0][0] * s[1][1] - s[0][1] * s[1][0];
+        let s_inv = [
+            [s[1][1] / det, -s[0][1] / det],
+            [-s[1][0] / det, s[0][0] / det],
+        ];
+        let c = [
+            [τ1, τ1 * τ2 + τ1 * τ1 / 2.],
+            [τ1 * τ1 / 2., τ1 * τ1 * τ2 / 2. + τ1 * τ1 * τ1 / 3.],
+        ];
+        let k_gain = mat_mul(c, s_inv);
+        let prior = [[τ1, τ1 * τ1 / 2.], [τ1 * τ1 / 2., τ1 * τ1 * τ1 / 3.]];
+        let reduction = mat_mul(k_gain, transpose(c));
+        let cov = [
+            [prior[0][0] - reduction[0][0], prior[0][1] - reduction[0][1]],
+            [prior[1][0] - reduction[1][0], prior[1][1] - reduction[1][1]],
+        ];
+        let l11 = cov[0][0].max(0.).sqrt();
+        let l21 = if l11 > 0. { cov[1][0] / l11 } else { 0. };
+        let l22 = (cov[1][1] - l21 * l21).max(0.).sqrt();
+
+        let dim = self.dim();
+        let mut w = Array1::zeros(dim);
+        let mut i = Array1::zeros(dim);
+        for j in 0..dim {
+            let (wa, wb) = (self.w[k - 1][j], self.w[k][j]);
+            let dw = wb - wa;
+            let dz = self.i[k][j] - self.i[k - 1][j] - τ * wa;
+            let (ξ1, ξ2) = self.normal_pair();
+            let w1 = k_gain[0][0] * dw + k_gain[0][1] * dz + l11 * ξ1;
+            let a1 = k_gain[1][0] * dw + k_gain[1][1] * dz + l21 * ξ1 + l22 * ξ2;
+            w[j] = wa + w1;
+            i[j] = self.i[k - 1][j] + τ1 * wa + a1;
+        }
+        self.t.insert(k, t);
+        self.w.insert(k, w);
+        self.i.insert(k, i);
+    }
+
+    fn normal_pair(&mut self) -> (f64, f64) {
+        (
+            StandardNormal.sample(&mut self.rng),
+            StandardNormal.sample(&mut self.rng),
+        )
+    }
+}
+
+#[inline]
+fn same_time(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-12 * a.abs().max(b.abs()).max(1.)
+}
+
+fn mat_mul(a: [[f64; 2]; 2], b: [[f64; 2]; 2]) -> [[f64; 2]; 2] {
+    [
+        [
+            a[0][0] * b[0][0] + a[0][1] * b[1][0],
+            a[0][0] * b[0][1] + a[0][1] * b[1][1],
+        ],
+        [
+            a[1][0] * b[0][0] + a[1][1] * b[1][0],
+            a[1][0] * b[0][1] + a[1][1] * b[1][1],
+        ],
+    ]
+}
+
+fn transpose(a: [[f64; 2]; 2]) -> [[f64; 2]; 2] {
+    [[a[0][0], a[1][0]], [a[0][1], a[1][1]]]
+}
+
+#[cfg(test)]
+mod test {
+    use super::BrownianPath;
+
+    #[test]
+    fn refined_increments_add_up() {
+        let mut path = BrownianPath::new(2, 42);
+        let whole = path.increment(0.0, 1.0);
+        let first = path.increment(0.0, 0.3);
+        let second = path.increment(0.3, 1.0);
+
+        for j in 0..2 {
+            assert!((first.dw[j] + second.dw[j] - whole.dw[j]).abs() < 1e-12);
+            assert!((first.dz[j] + second.dz[j] + 0.7 * first.dw[j] - whole.dz[j]).abs() < 1e-12);
+        }
+        assert_eq!(path.times(), &[0.0, 0.3, 1.0]);
+    }
+
+    #[test]
+    fn same_seed_same_path() {
+        let mut a = BrownianPath::new(1, 7);
+        let mut b = BrownianPath::new(1, 7);
+        assert_eq!(a.value(0.5), b.value(0.5));
+        assert_eq!(a.value(0.25), b.value(0.25));
+    }
+}
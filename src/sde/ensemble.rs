@@ -0,0 +1,15 @@
+use ndarray::Array2;
+
+/// Statistics of an ensemble of realisations, see [crate::sde::SdeEx::run_ensemble].
+///
+/// columns: state
+///
+/// rows: timestep
+#[derive(Debug, Clone)]
+pub struct EnsembleMoments {
+    pub time: Vec<f64>,
+    pub mean: Array2<f64>,
+    /// Unbiased sample variance.
+    pub variance: Array2<f64>,
+    pub paths: usize,
+}
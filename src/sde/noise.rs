@@ -0,0 +1,137 @@
+use ndarray::{Array1, Array2, ArrayView1};
+
+use crate::{ad::*, sde::*};
+
+/// Every state component `x_i` is driven by its own Wiener process `W_i` with intensity `g_i(x)`.
+///
+/// Milstein's scheme is of strong order one as long as the noise is commutative,
+/// which is the case if `g_i` only depends on `x_i`.
+pub struct DiagonalNoise<G>
+where
+    G: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    g: G,
+}
+
+impl<G> DiagonalNoise<G>
+where
+    G: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    pub fn new(g: G) -> Self {
+        DiagonalNoise { g }
+    }
+
+    /// The diagonal `g(x)`.
+    pub fn eval(&self, x: ArrayView1<f64>) -> Array1<f64> {
+        eval_flow(&self.g, x)
+    }
+}
+
+impl<G> Diffusion for DiagonalNoise<G>
+where
+    G: Fn(ArrayView1<AD>, &mut Array1<AD>),
+{
+    fn noise_dim(&self, n: usize) -> usize {
+        n
+    }
+
+    fn matrix(&self, x: ArrayView1<f64>) -> Array2<f64> {
+        Array2::from_diag(&self.eval(x))
+    }
+
+    fn is_diagonal(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, x: ArrayView1<f64>, dw: ArrayView1<f64>) -> Array1<f64> {
+        self.eval(x) * dw
+    }
+
+    fn milstein(&self, x: ArrayView1<f64>, dw: ArrayView1<f64>, h: f64) -> Array1<f64> {
+        let g = self.eval(x);
+        let mut x_ad: Array1<AD> = x.iter().map(|&x| AD::AD1(x, 0f64)).collect();
+        let mut dg = x_ad.clone();
+        let mut correction = Array1::zeros(x.len());
+        // Column j of g is g_j e_j, hence L^j g = g_j ∂_j g.
+        for j in 0..x.len() {
+            x_ad[j][1] = g[j];
+            (self.g)(x_ad.view(), &mut dg);
+            for (i, c) in correction.iter_mut().enumerate() {
+                let δ = if i == j { h } else { 0. };
+                *c += 0.5 * dg[i].dx() * (dw[i] * dw[j] - δ);
+            }
+            x_ad[j][1] = 0f64;
+        }
+        correction
+    }
+}
+
+/// `m` Wiener processes enter through the `n × m` matrix `g(x)`.
+///
+/// The noise is assumed to be commutative, `L^j g_k = L^k g_j`, so that Milstein's scheme
+/// gets along without Lévy areas.
+pub struct CommutativeNoise<G>
+where
+    G: Fn(ArrayView1<AD>, &mut Array2<AD>),
+{
+    g: G,
+    m: usize,
+}
+
+impl<G> CommutativeNoise<G>
+where
+    G: Fn(ArrayView1<AD>, &mut Array2<AD>),
+{
+    pub fn new(m: usize, g: G) -> Self {
+        CommutativeNoise { g, m }
+    }
+
+    /// The matrix `g(x)`.
+    pub fn eval(&self, x: ArrayView1<f64>) -> Array2<f64> {
+        let x = x.to_ad();
+        let mut g = Array2::from_elem((x.len(), self.m), AD::AD0(0f64));
+        (self.g)(x.view(), &mut g);
+        g.map(|g| g.x())
+    }
+}
+
+impl<G> Diffusion for CommutativeNoise<G>
+where
+    G: Fn(ArrayView1<AD>, &mut Array2<AD>),
+{
+    fn noise_dim(&self, _n: usize) -> usize {
+        self.m
+    }
+
+    fn matrix(&self, x: ArrayView1<f64>) -> Array2<f64> {
+        self.eval(x)
+    }
+
+    fn apply(&self, x: ArrayView1<f64>, dw: ArrayView1<f64>) -> Array1<f64> {
+        self.eval(x).dot(&dw)
+    }
+
+    fn milstein(&self, x: ArrayView1<f64>, dw: ArrayView1<f64>, h: f64) -> Array1<f64> {
+        let g = self.eval(x);
+        let mut dg = Array2::from_elem((x.len(), self.m), AD::AD0(0f64));
+        let mut correction = Array1::zeros(x.len());
+        for j in 0..self.m {
+            // Seeding the direction g_j yields L^j g_k for all k at once.
+            let x_ad: Array1<AD> = x
+                .iter()
+                .zip(g.column(j).iter())
+                .map(|(&x, &g)| AD::AD1(x, g))
+                .collect();
+            (self.g)(x_ad.view(), &mut dg);
+            for k in 0..self.m {
+                let δ = if j == k { h } else { 0. };
+                let weight = 0.5 * (dw[j] * dw[k] - δ);
+                correction
+                    .iter_mut()
+                    .zip(dg.column(k).iter())
+                    .for_each(|(c, dg)| *c += weight * dg.dx());
+            }
+        }
+        correction
+    }
+}
@@ -0,0 +1,218 @@
+use ndarray::*;
+use rayon::prelude::*;
+
+use crate::{ode::*, progress::*, sde::*};
+
+/// Runs a [Stochastic] scheme with fixed timesteps `h` along one realisation of a [BrownianPath].
+#[allow(non_snake_case)]
+pub struct SdeEx<Scheme>
+where
+    Scheme: Stochastic + std::marker::Sync,
+{
+    scheme: Scheme,
+    initial: Array1<f64>,
+    h: f64,
+    T: f64,
+    seed: u64,
+    path: Option<BrownianPath>,
+    progress: Box<dyn Progress>,
+    limits: Limits,
+}
+
+impl<Scheme> SdeEx<Scheme>
+where
+    Scheme: Stochastic + std::marker::Sync,
+{
+    pub fn new(scheme: Scheme, initial: Array1<f64>) -> Self {
+        SdeEx {
+            scheme,
+            initial,
+            h: 0.1,
+            T: 1.0,
+            seed: 0,
+            path: None,
+            progress: Box::<TqdmProgress>::default(),
+            limits: Limits::default(),
+        }
+    }
+
+    /// Seed of the Brownian path which is generated if none was given with [SdeEx::set_path].
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Integrate along a given path, e.g. a clone of a path used with a different step size.
+    pub fn set_path(&mut self, path: BrownianPath) -> &mut Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Runs `paths` independent realisations in parallel and returns the mean and variance at every timestep.
+    ///
+    /// Realisation `i` uses the seed `seed + i`.
+    pub fn run_ensemble(self, paths: usize) -> EnsembleMoments {
+        assert!(paths > 0, "An ensemble needs at least one path");
+        let (n, l) = (self.steps(), self.initial.len());
+        let noise_dim = self.scheme.noise_dim(l);
+
+        let (count, mean, m2) = (0..paths)
+            .into_par_iter()
+            .map(|i| {
+                let mut path = BrownianPath::new(noise_dim, self.seed.wrapping_add(i as u64));
+                let mut result = Array::zeros((n, l));
+                self.integrate(&mut path, &mut result, &mut NoProgress, &Limits::default());
+                (1usize, result, Array2::<f64>::zeros((n, l)))
+            })
+            .reduce(
+                || (0usize, Array2::zeros((n, l)), Array2::zeros((n, l))),
+                |(n_a, mean_a, m2_a), (n_b, mean_b, m2_b)| {
+                    if n_a == 0 {
+                        return (n_b, mean_b, m2_b);
+                    }
+                    // Pairwise update of Chan et al.
+                    let count = n_a + n_b;
+                    let δ = &mean_b - &mean_a;
+                    let w = n_b as f64 / count as f64;
+                    let mean = &mean_a + &(w * &δ);
+                    let m2 = m2_a + m2_b + (n_a as f64 * w) * &(&δ * &δ);
+                    (count, mean, m2)
+                },
+            );
+
+        let variance = if count > 1 {
+            m2 / (count - 1) as f64
+        } else {
+            m2
+        };
+        EnsembleMoments {
+            time: self.time(),
+            mean,
+            variance,
+            paths: count,
+        }
+    }
+
+    fn steps(&self) -> usize {
+        let n: f64 = self.T / self.h;
+        n.floor() as usize
+    }
+
+    fn time(&self) -> Vec<f64> {
+        (0..self.steps()).map(|t| t as f64 * self.h).collect()
+    }
+
+    fn integrate(
+        &self,
+        path: &mut BrownianPath,
+        result: &mut Array2<f64>,
+        progress: &mut dyn Progress,
+        limits: &Limits,
+    ) -> (usize, Termination) {
+        let n = result.nrows();
+        let mut x0 = self.initial.clone();
+        result.row_mut(0).assign(&x0);
+
+        progress.start(0., self.T);
+        for t in 1..n {
+            if let Some(stop) = limits.check((t - 1) as f64 * self.h) {
+                progress.finish();
+                return (t, stop);
+            }
+            let increment = path.increment((t - 1) as f64 * self.h, t as f64 * self.h);
+            x0 = self.scheme.next(x0.view(), &increment);
+            result.row_mut(t).assign(&x0);
+            progress.update(t as f64 * self.h);
+        }
+        progress.finish();
+        (n, Termination::Completed)
+    }
+}
+
+impl<Scheme> ODE<Scheme> for SdeEx<Scheme>
+where
+    Scheme: Stochastic + std::marker::Sync,
+{
+    fn set_step_size(&mut self, h: f64) -> &mut Self {
+        self.h = h;
+        self
+    }
+
+    #[allow(non_snake_case)]
+    fn set_t(&mut self, T: f64) -> &mut Self {
+        self.T = T;
+        self
+    }
+
+    fn set_progress<P: Progress + 'static>(&mut self, progress: P) -> &mut Self {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    fn limits_mut(&mut self) -> &mut Limits {
+        &mut self.limits
+    }
+
+    fn run(mut self) -> Solution {
+        let noise_dim = self.scheme.noise_dim(self.initial.len());
+        let mut path = self
+            .path
+            .take()
+            .unwrap_or_else(|| BrownianPath::new(noise_dim, self.seed));
+        assert_eq!(
+            path.dim(),
+            noise_dim,
+            "The Brownian path does not match the noise of the scheme"
+        );
+
+        let mut result = Array::zeros((self.steps(), self.initial.len()));
+        let mut progress = std::mem::replace(&mut self.progress, Box::new(NoProgress));
+        self.limits.start();
+        let (rows, termination) =
+            self.integrate(&mut path, &mut result, progress.as_mut(), &self.limits);
+        let steps = rows.saturating_sub(1);
+        let stats = SolverStats {
+            steps,
+            rhs_evaluations: steps * self.scheme.evaluations(self.initial.len()),
+            ..Default::default()
+        };
+        let mut solution = Solution::new(self.time(), result, stats);
+        solution.truncate(rows).termination = termination;
+        solution
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, ArrayView1};
+
+    use crate::{ad::*, ode::ODE, sde::*};
+
+    fn drift(x: ArrayView1<f64>) -> Array1<f64> {
+        0.5 * &x
+    }
+
+    fn diffusion(x: ArrayView1<AD>, g: &mut Array1<AD>) {
+        g[0] = 0.4 * x[0];
+    }
+
+    #[test]
+    fn ensemble_moments_of_geometric_brownian_motion() {
+        let h = 1. / 64.;
+        let mut sde = Sde::explicit(
+            Milstein::new(drift, DiagonalNoise::new(diffusion)),
+            array![1.],
+        );
+        sde.set_step_size(h).set_t(1. + h).set_seed(3);
+        let moments = sde.run_ensemble(2000);
+        assert_eq!(moments.paths, 2000);
+
+        // E X(t) = e^(μ t) and Var X(t) = e^(2 μ t) (e^(σ² t) - 1) at t = 1
+        let last = moments.time.len() - 1;
+        assert!((moments.time[last] - 1.).abs() < 1e-12);
+        let (mean, variance) = (0.5f64.exp(), 1f64.exp() * (0.16f64.exp() - 1.));
+        let (m, v) = (moments.mean[[last, 0]], moments.variance[[last, 0]]);
+        assert!((m - mean).abs() < 0.05 * mean, "{m}");
+        assert!((v - variance).abs() < 0.15 * variance, "{v}");
+    }
+}
@@ -0,0 +1,293 @@
+//! This module defines all preimplemented schemes for stochastic differential equations.
+use ndarray::{Array1, Array2, ArrayView1, Zip};
+
+use crate::sde::*;
+
+/// Euler-Maruyama scheme of strong order ½ and weak order one.
+pub struct EulerMaruyama<Drift, Noise>
+where
+    Drift: Fn(ArrayView1<f64>) -> Array1<f64>,
+    Noise: Diffusion,
+{
+    drift: Drift,
+    noise: Noise,
+}
+
+impl<Drift, Noise> EulerMaruyama<Drift, Noise>
+where
+    Drift: Fn(ArrayView1<f64>) -> Array1<f64>,
+    Noise: Diffusion,
+{
+    pub fn new(drift: Drift, noise: Noise) -> Self {
+        EulerMaruyama { drift, noise }
+    }
+}
+
+impl<Drift, Noise> Stochastic for EulerMaruyama<Drift, Noise>
+where
+    Drift: Fn(ArrayView1<f64>) -> Array1<f64>,
+    Noise: Diffusion,
+{
+    fn noise_dim(&self, n: usize) -> usize {
+        self.noise.noise_dim(n)
+    }
+
+    #[inline]
+    fn next(&self, x: ArrayView1<f64>, increment: &Increment) -> Array1<f64> {
+        let h = increment.h;
+        let mut x1 = self.noise.apply(x, increment.dw.view());
+        x1.iter_mut()
+            .zip(x.iter())
+            .zip((self.drift)(x).iter())
+            .for_each(|((x1, &x0), &f)| *x1 += x0 + h * f);
+        x1
+    }
+}
+
+/// Milstein scheme of strong order one for diagonal and commutative noise.
+///
+/// The derivatives of the diffusion are obtained with [crate::ad::AD].
+pub struct Milstein<Drift, Noise>
+where
+    Drift: Fn(ArrayView1<f64>) -> Array1<f64>,
+    Noise: Diffusion,
+{
+    drift: Drift,
+    noise: Noise,
+}
+
+impl<Drift, Noise> Milstein<Drift, Noise>
+where
+    Drift: Fn(ArrayView1<f64>) -> Array1<f64>,
+    Noise: Diffusion,
+{
+    pub fn new(drift: Drift, noise: Noise) -> Self {
+        Milstein { drift, noise }
+    }
+}
+
+impl<Drift, Noise> Stochastic for Milstein<Drift, Noise>
+where
+    Drift: Fn(ArrayView1<f64>) -> Array1<f64>,
+    Noise: Diffusion,
+{
+    fn noise_dim(&self, n: usize) -> usize {
+        self.noise.noise_dim(n)
+    }
+
+    #[inline]
+    fn next(&self, x: ArrayView1<f64>, increment: &Increment) -> Array1<f64> {
+        let h = increment.h;
+        let dw = increment.dw.view();
+        let mut x1 = self.noise.apply(x, dw) + self.noise.milstein(x, dw, h);
+        x1.iter_mut()
+            .zip(x.iter())
+            .zip((self.drift)(x).iter())
+            .for_each(|((x1, &x0), &f)| *x1 += x0 + h * f);
+        x1
+    }
+}
+
+/// Derivative free stochastic Runge-Kutta scheme of strong order 1.5, the explicit order 1.5 strong scheme of
+/// Kloeden & Platen, Numerical Solution of Stochastic Differential Equations, eq. (11.2.19), with the
+/// supporting values `Υ±_j = x + a h / m ± g_j √h`.
+///
+/// The iterated integrals of mixed noise components are replaced by products of the increments. This is
+/// exact for [DiagonalNoise] where `g_i` only depends on `x_i`, and for [CommutativeNoise] which commutes
+/// in the second kind, `L^j1 L^j2 g_j3` symmetric in all indices, e.g. columns `G_j x` with commuting `G_j`.
+/// The drift enters the supporting values with the share `h / m` of each of the `m` noise components, so
+/// that the drift terms of the expansion are not counted once per component.
+pub struct Srk15<Drift, Noise>
+where
+    Drift: Fn(ArrayView1<f64>) -> Array1<f64>,
+    Noise: Diffusion,
+{
+    drift: Drift,
+    noise: Noise,
+}
+
+impl<Drift, Noise> Srk15<Drift, Noise>
+where
+    Drift: Fn(ArrayView1<f64>) -> Array1<f64>,
+    Noise: Diffusion,
+{
+    pub fn new(drift: Drift, noise: Noise) -> Self {
+        Srk15 { drift, noise }
+    }
+}
+
+impl<Drift, Noise> Stochastic for Srk15<Drift, Noise>
+where
+    Drift: Fn(ArrayView1<f64>) -> Array1<f64>,
+    Noise: Diffusion,
+{
+    fn noise_dim(&self, n: usize) -> usize {
+        self.noise.noise_dim(n)
+    }
+
+    fn evaluations(&self, n: usize) -> usize {
+        1 + 2 * self.noise.noise_dim(n)
+    }
+
+    fn next(&self, x: ArrayView1<f64>, increment: &Increment) -> Array1<f64> {
+        let h = increment.h;
+        let sqrt_h = h.sqrt();
+        let (dw, dz) = (&increment.dw, &increment.dz);
+        let m = dw.len();
+        let kronecker = |j: usize, k: usize| if j == k { 1. } else { 0. };
+        let a = (self.drift)(x);
+        let b = self.noise.matrix(x);
+
+        // the supporting values Υ±_j = x + a h / m ± g_j √h and g at them
+        let support = &x + &(h / m as f64 * &a);
+        let upsilon: Vec<(Array1<f64>, Array1<f64>)> = (0..m)
+            .map(|j| {
+                let step = sqrt_h * &b.column(j);
+                (&support + &step, &support - &step)
+            })
+            .collect();
+        let b_upsilon: Vec<(Array2<f64>, Array2<f64>)> = upsilon
+            .iter()
+            .map(|(plus, minus)| {
+                (
+                    self.noise.matrix(plus.view()),
+                    self.noise.matrix(minus.view()),
+                )
+            })
+            .collect();
+
+        let mut x1 = &x + &(h * &a) + b.dot(dw);
+        for j1 in 0..m {
+            let (y_plus, y_minus) = &upsilon[j1];
+            let (b_plus, b_minus) = &b_upsilon[j1];
+            let a_plus = (self.drift)(y_plus.view());
+            let a_minus = (self.drift)(y_minus.view());
+            Zip::from(&mut x1)
+                .and(&a)
+                .and(&a_plus)
+                .and(&a_minus)
+                .for_each(|x1, &a, &ap, &am| {
+                    *x1 += (ap - am) / (2. * sqrt_h) * dz[j1] + 0.25 * (ap - 2. * a + am) * h;
+                });
+
+            for j2 in 0..m {
+                // I_(j1,j2) symmetrised by the commutativity and I_(0,j2)
+                let double = 0.5 * (dw[j1] * dw[j2] - kronecker(j1, j2) * h);
+                let time = dw[j2] * h - dz[j2];
+                Zip::from(&mut x1)
+                    .and(b.column(j2))
+                    .and(b_plus.column(j2))
+                    .and(b_minus.column(j2))
+                    .for_each(|x1, &b, &bp, &bm| {
+                        *x1 += (bp - bm) / (2. * sqrt_h) * double
+                            + (bp - 2. * b + bm) / (2. * h) * time;
+                    });
+                if self.noise.is_diagonal() && j1 != j2 {
+                    continue;
+                }
+
+                let step = sqrt_h * &b_plus.column(j2);
+                let b_φ_plus = self.noise.matrix((y_plus + &step).view());
+                let b_φ_minus = self.noise.matrix((y_plus - &step).view());
+                let (b2_plus, b2_minus) = &b_upsilon[j2];
+                for j3 in 0..m {
+                    // I_(j1,j2,j3) symmetrised
+                    let triple = (dw[j1] * dw[j2] * dw[j3]
+                        - h * (kronecker(j1, j2) * dw[j3]
+                            + kronecker(j1, j3) * dw[j2]
+                            + kronecker(j2, j3) * dw[j1]))
+                        / 6.;
+                    Zip::from(&mut x1)
+                        .and(b_φ_plus.column(j3))
+                        .and(b_φ_minus.column(j3))
+                        .and(b2_plus.column(j3))
+                        .and(b2_minus.column(j3))
+                        .for_each(|x1, &φp, &φm, &bp, &bm| {
+                            *x1 += (φp - φm - bp + bm) / (2. * h) * triple;
+                        });
+                }
+            }
+        }
+        x1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{array, Array1, Array2, ArrayView1};
+
+    use crate::{ad::*, ode::ODE, sde::*, test_support::*};
+
+    const MU: f64 = 0.5;
+    const SIGMA: [f64; 2] = [0.8, 0.4];
+
+    fn drift(x: ArrayView1<f64>) -> Array1<f64> {
+        MU * &x
+    }
+
+    fn diagonal(x: ArrayView1<AD>, g: &mut Array1<AD>) {
+        g[0] = SIGMA[0] * x[0];
+    }
+
+    /// Two noise components acting on one state, whose columns `σ_j x` commute.
+    fn commutative(x: ArrayView1<AD>, g: &mut Array2<AD>) {
+        for j in 0..2 {
+            g[[0, j]] = SIGMA[j] * x[0];
+        }
+    }
+
+    /// Mean absolute error at `t = 1` of geometric Brownian motion `dX = μ X dt + Σ σ_j X dW_j` against
+    /// `X(t) = exp((μ - Σ σ_j² / 2) t + Σ σ_j W_j(t))`.
+    fn strong_error<S: Stochastic + Sync>(scheme: impl Fn() -> S, m: usize, h: f64) -> f64 {
+        let paths = 200;
+        let total: f64 = (0..paths)
+            .map(|seed| {
+                let mut path = BrownianPath::new(m, seed);
+                // sample the finest grid first, every step size then sees the same realisation
+                for k in 1..=256 {
+                    path.refine(k as f64 / 256.);
+                }
+                let w = path.value(1.);
+                let exponent =
+                    (0..m).fold(MU, |e, j| e - 0.5 * SIGMA[j] * SIGMA[j] + SIGMA[j] * w[j]);
+
+                let mut sde = Sde::explicit(scheme(), array![1.]);
+                sde.set_path(path)
+                    .set_step_size(h)
+                    .set_t(ending_at(1., h))
+                    .set_with_progress(false);
+                let x = state_at(&sde.run(), 1.);
+                (x[0] - exponent.exp()).abs()
+            })
+            .sum();
+        total / paths as f64
+    }
+
+    fn strong_order<S: Stochastic + Sync>(scheme: impl Fn() -> S, m: usize) -> f64 {
+        (strong_error(&scheme, m, 1. / 8.) / strong_error(&scheme, m, 1. / 64.)).log2() / 3.
+    }
+
+    #[test]
+    fn strong_orders_on_geometric_brownian_motion() {
+        let euler = strong_order(
+            || EulerMaruyama::new(drift, DiagonalNoise::new(diagonal)),
+            1,
+        );
+        assert!((euler - 0.5).abs() < 0.2, "{euler}");
+        let milstein = strong_order(|| Milstein::new(drift, DiagonalNoise::new(diagonal)), 1);
+        assert!((milstein - 1.).abs() < 0.2, "{milstein}");
+        let srk = strong_order(|| Srk15::new(drift, DiagonalNoise::new(diagonal)), 1);
+        assert!((srk - 1.5).abs() < 0.2, "{srk}");
+
+        let milstein = strong_order(
+            || Milstein::new(drift, CommutativeNoise::new(2, commutative)),
+            2,
+        );
+        assert!((milstein - 1.).abs() < 0.2, "{milstein}");
+        let srk = strong_order(
+            || Srk15::new(drift, CommutativeNoise::new(2, commutative)),
+            2,
+        );
+        assert!((srk - 1.5).abs() < 0.2, "{srk}");
+    }
+}
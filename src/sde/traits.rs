@@ -0,0 +1,32 @@
+use ndarray::{Array1, Array2, ArrayView1};
+
+use crate::sde::Increment;
+
+/// A scheme advancing `dX = f dt + g dW` by one step with the given Wiener increments.
+pub trait Stochastic {
+    /// Number of independent Wiener processes for a state of length `n`.
+    fn noise_dim(&self, n: usize) -> usize;
+    fn next(&self, x: ArrayView1<f64>, increment: &Increment) -> Array1<f64>;
+    /// Evaluations of the drift in each call of [Stochastic::next] for a state of length `n`.
+    fn evaluations(&self, _n: usize) -> usize {
+        1
+    }
+}
+
+/// Describes how the noise enters `dX = f dt + g dW`.
+///
+/// The diffusion is given on [crate::ad::AD] values so that Milstein type schemes can obtain the derivatives of `g`.
+pub trait Diffusion {
+    /// Number of independent Wiener processes for a state of length `n`.
+    fn noise_dim(&self, n: usize) -> usize;
+    /// The `n × m` matrix `g(x)` whose column `j` multiplies `ΔW_j`.
+    fn matrix(&self, x: ArrayView1<f64>) -> Array2<f64>;
+    /// Whether column `j` of `g` only has the entry `g_jj(x_j)`, so that schemes can skip the mixed terms.
+    fn is_diagonal(&self) -> bool {
+        false
+    }
+    /// `g(x) ΔW`
+    fn apply(&self, x: ArrayView1<f64>, dw: ArrayView1<f64>) -> Array1<f64>;
+    /// Milstein correction `½ Σ_jk (L^j g_k)(ΔW_j ΔW_k - δ_jk h)` with `L^j = Σ_l g_lj ∂_l`.
+    fn milstein(&self, x: ArrayView1<f64>, dw: ArrayView1<f64>, h: f64) -> Array1<f64>;
+}
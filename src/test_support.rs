@@ -0,0 +1,85 @@
+//! Problems and convergence checks shared by the tests of the solvers.
+use ndarray::{array, Array1, Array2, ArrayView, ArrayView1, ArrayView2, Dimension};
+
+use crate::{ad::*, ensemble::final_state, ode::*};
+
+/// The pendulum `q'' = -sin q` as a first order system of the angle and its velocity.
+pub fn pendulum(x: ArrayView1<AD>, f: &mut Array1<AD>) {
+    f[0] = x[1];
+    f[1] = -x[0].sin();
+}
+
+/// Van der Pol oscillator with the stiffness parameter `1 / μ = 1e3`.
+pub fn van_der_pol(x: ArrayView1<AD>, f: &mut Array1<AD>) {
+    f[0] = x[1];
+    f[1] = ((1. - x[0] * x[0]) * x[1] - x[0]) * 1e3;
+}
+
+/// Runs [van_der_pol] from `(2, 0)` until 2 with the tolerances `1e-6` and checks the final position.
+pub fn relaxation_oscillation(scheme: impl Embedded) -> Solution {
+    let mut ode = Ode::adaptive(scheme, array![2.0, 0.0]);
+    ode.set_tolerances(1e-6, 1e-6)
+        .set_step_size(1e-3)
+        .set_t(2.0)
+        .set_with_progress(false);
+    let solution = ode.run();
+    assert_eq!(solution.termination, Termination::Completed);
+    let x = final_state(&solution);
+    // back on the upper slow branch after one period of the relaxation oscillation of about 1.61
+    assert!((x[0] - 1.76).abs() < 0.01, "{}", x[0]);
+    solution
+}
+
+/// Fixed steps of size `h` from `x0` until 1, the final state and the largest error estimate.
+pub fn fixed_steps(scheme: &mut impl Embedded, x0: ArrayView1<f64>, h: f64) -> (Array1<f64>, f64) {
+    let mut stats = SolverStats::default();
+    let mut x = x0.to_owned();
+    let mut error: f64 = 0.;
+    for i in 0..(1. / h).round() as usize {
+        let step = scheme.next(i as f64 * h, x.view(), h, &mut stats).unwrap();
+        error = step.error.iter().fold(error, |m, e| m.max(e.abs()));
+        x = step.x;
+    }
+    (x, error)
+}
+
+/// Fixed steps of size `h` of a [MatrixScheme] from `y0` until 1.
+pub fn fixed_matrix_steps(
+    scheme: &mut (impl MatrixScheme + ?Sized),
+    y0: ArrayView2<f64>,
+    h: f64,
+    stats: &mut SolverStats,
+) -> Array2<f64> {
+    let mut y = y0.to_owned();
+    for i in 0..(1. / h).round() as usize {
+        y = scheme.next(i as f64 * h, y.view(), h, stats).unwrap();
+    }
+    y
+}
+
+/// The largest absolute difference of the entries.
+pub fn max_error<'a, D: Dimension>(
+    a: impl Into<ArrayView<'a, f64, D>>,
+    b: impl Into<ArrayView<'a, f64, D>>,
+) -> f64 {
+    (&a.into() - &b.into())
+        .iter()
+        .fold(0., |e: f64, x| e.max(x.abs()))
+}
+
+/// The order observed from the errors with the step sizes `h` and `h / 2`.
+pub fn observed_order(error: impl Fn(f64) -> f64, h: f64) -> f64 {
+    (error(h) / error(h / 2.)).log2()
+}
+
+/// The end of a run with fixed steps `h` whose last row is at `t`, the rows are at the times `k h` below the end.
+pub fn ending_at(t: f64, h: f64) -> f64 {
+    t + 1.5 * h
+}
+
+/// The final state of a run set up with [ending_at], which has to be at `t`.
+pub fn state_at(solution: &Solution, t: f64) -> Array1<f64> {
+    let end = solution.time[solution.len() - 1];
+    assert!((end - t).abs() < 1e-12, "{end}");
+    final_state(solution)
+}